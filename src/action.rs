@@ -0,0 +1,268 @@
+use std::{
+    path::Path,
+    process::Command,
+    str::FromStr,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::graphics::{parse_moves, ClockMode, PuzzleKind, STATUS_FILE_PREFIX};
+
+/// 外部控制的操作从其他线程经 [`ActionQueue`] 发送，每帧转为 [`Action`] 事件；
+/// 打开配置目录与退出在此处理，其余操作由各插件处理
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Action>()
+            .init_resource::<ActionQueue>()
+            .add_systems(PreUpdate, receive_actions)
+            .add_systems(Update, apply_actions);
+    }
+}
+
+/// 外部控制（IPC、托盘、快捷键）可以触发的操作，IPC 命令直接反序列化为该类型
+#[derive(Event, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Action {
+    /// 执行转动序列，如 `R U R'`
+    Move { seq: String },
+    /// 切换显示模式，见 [`ClockMode::parse`]
+    Mode { name: String },
+    /// 用自定义文字代替时间，为空时恢复显示时间
+    Text { value: Option<String> },
+    /// 切换主题
+    Theme { name: String },
+    /// 设置报时下方的状态行，`text` 为空时移除；`ttl` 秒后自动移除；
+    /// 以 `file:` 开头的标识保留给状态文件
+    Status {
+        id: String,
+        text: Option<String>,
+        #[serde(default)]
+        priority: i32,
+        ttl: Option<u64>,
+    },
+    /// 显示窗口
+    Show,
+    /// 隐藏窗口
+    Hide,
+    /// 切换窗口的显示与隐藏
+    ToggleVisibility,
+    /// 切换窗口是否让鼠标点击穿透
+    ToggleClickThrough,
+    /// 暂停魔方转动
+    Pause,
+    /// 继续魔方转动
+    Resume,
+    /// 用系统文件管理器打开配置目录
+    OpenConfig,
+    /// 退出程序
+    Quit,
+}
+
+impl Action {
+    /// 检查与谜题无关的参数，无效的操作不会发送给应用；
+    /// 转发给已运行的实例时不知道对方的谜题，由对方用 [`Action::validate_for`] 再检查
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Action::Move { seq } => parse_moves(seq).map(|_| ()).map_err(|err| err.to_string()),
+            Action::Mode { name } => ClockMode::parse(name)
+                .map(|_| ())
+                .ok_or_else(|| format!("unknown mode `{name}`")),
+            Action::Status { id, .. } if id.is_empty() => Err("empty status id".to_string()),
+            Action::Status { id, .. } if id.starts_with(STATUS_FILE_PREFIX) => Err(format!(
+                "status id `{id}` uses the reserved prefix `{STATUS_FILE_PREFIX}`"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 检查参数，转动还需要是 `puzzle` 能执行的
+    pub fn validate_for(&self, puzzle: PuzzleKind) -> Result<(), String> {
+        self.validate()?;
+        match self {
+            Action::Move { seq } => puzzle
+                .puzzle()
+                .parse_moves(seq)
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 文本形式的操作，如 `move R U R'`、`mode countdown:25`、`toggle_visibility`，
+/// 用于快捷键配置与 `time-fly ctl`；状态行参数较多，不支持这种形式
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let (cmd, arg) = value
+            .split_once(char::is_whitespace)
+            .map_or((value, ""), |(cmd, arg)| (cmd, arg.trim()));
+        let required = || {
+            if arg.is_empty() {
+                Err(format!("missing argument for `{cmd}`"))
+            } else {
+                Ok(arg.to_string())
+            }
+        };
+        let action = match cmd {
+            "move" => Action::Move { seq: required()? },
+            "mode" => Action::Mode { name: required()? },
+            "theme" => Action::Theme { name: required()? },
+            "text" => Action::Text {
+                value: (!arg.is_empty()).then(|| arg.to_string()),
+            },
+            "status" => return Err("`status` needs structured arguments".to_string()),
+            _ if !arg.is_empty() => return Err(format!("unexpected argument for `{cmd}`")),
+            // 无参数的操作
+            _ => serde_json::from_value(serde_json::json!({ "cmd": cmd }))
+                .map_err(|_| format!("unknown command `{cmd}`"))?,
+        };
+        action.validate()?;
+        Ok(action)
+    }
+}
+
+/// 其他线程发送操作的通道
+#[derive(Resource)]
+pub struct ActionQueue {
+    sender: Sender<Action>,
+    receiver: Mutex<Receiver<Action>>,
+}
+
+impl Default for ActionQueue {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+
+impl ActionQueue {
+    pub fn sender(&self) -> Sender<Action> {
+        self.sender.clone()
+    }
+}
+
+fn receive_actions(queue: Res<ActionQueue>, mut actions: EventWriter<Action>) {
+    let Ok(receiver) = queue.receiver.lock() else {
+        return;
+    };
+    actions.send_batch(receiver.try_iter());
+}
+
+fn apply_actions(mut actions: EventReader<Action>, mut exit: EventWriter<AppExit>) {
+    for action in actions.read() {
+        match action {
+            Action::OpenConfig => {
+                let Some(dir) = dirs::config_dir().map(|dir| dir.join("time-fly")) else {
+                    continue;
+                };
+                if let Err(err) = std::fs::create_dir_all(&dir).and_then(|_| open_path(&dir)) {
+                    warn!("failed to open {dir:?}: {err}");
+                }
+            }
+            Action::Quit => {
+                exit.send(AppExit::Success);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn open_path(path: &Path) -> std::io::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(program).arg(path).spawn().map(|_| ())
+}
+
+/// 解析启动参数 `[--mode countdown:25] [--text 部署中] [--theme neon] [--moves "R U R'"]`
+///
+/// 已有实例在运行时，这些操作会转发给该实例
+pub fn parse_args(args: &[String]) -> Result<Vec<Action>, String> {
+    let mut actions = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value for `{arg}`"))
+        };
+        let action = match arg.as_str() {
+            "--mode" => Action::Mode { name: value()? },
+            "--text" => Action::Text {
+                value: Some(value()?),
+            },
+            "--theme" => Action::Theme { name: value()? },
+            "--moves" => Action::Move { seq: value()? },
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        };
+        action.validate()?;
+        actions.push(action);
+    }
+    Ok(actions)
+}
+
+#[test]
+fn parse_launch_args() {
+    let args = ["--mode", "countdown:25", "--moves", "R U"].map(String::from);
+    assert_eq!(
+        parse_args(&args),
+        Ok(vec![
+            Action::Mode {
+                name: "countdown:25".into()
+            },
+            Action::Move { seq: "R U".into() },
+        ])
+    );
+    assert!(parse_args(&["--mode".to_string()]).is_err());
+    assert!(parse_args(&["--moves", "X"].map(String::from)).is_err());
+}
+
+#[test]
+fn parse_action_text() {
+    assert_eq!(
+        "mode countdown:25".parse(),
+        Ok(Action::Mode {
+            name: "countdown:25".into()
+        })
+    );
+    assert_eq!(" text ".parse(), Ok(Action::Text { value: None }));
+    assert_eq!(
+        "toggle_click_through".parse(),
+        Ok(Action::ToggleClickThrough)
+    );
+    assert!("move".parse::<Action>().is_err());
+    assert!("move X".parse::<Action>().is_err());
+    let deep = Action::Move { seq: "4R".into() };
+    assert!(deep.validate().is_ok());
+    assert!(deep.validate_for(PuzzleKind::default()).is_err());
+    let skewb = Action::Move { seq: "F".into() };
+    assert!(skewb.validate_for(PuzzleKind::Skewb).is_err());
+    let status = |id: &str| Action::Status {
+        id: id.into(),
+        text: None,
+        priority: 0,
+        ttl: None,
+    };
+    assert!(status("build").validate().is_ok());
+    assert!(status("").validate().is_err());
+    assert!(status("file:0").validate().is_err());
+    assert!("quit now".parse::<Action>().is_err());
+    assert!("fly".parse::<Action>().is_err());
+}
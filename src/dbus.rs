@@ -0,0 +1,1062 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::{fs::MetadataExt, net::UnixStream},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+/// 总线协议的最小实现，只支持托盘、桌面门户与 logind 需要的类型，字节序固定为小端
+#[derive(Debug, Error)]
+pub enum DbusError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("session bus address not found")]
+    NoAddress,
+    #[error("authentication rejected: {0}")]
+    Auth(String),
+    #[error("malformed message: {0}")]
+    Malformed(&'static str),
+    #[error("unsupported type `{0}`")]
+    UnsupportedType(char),
+    #[error("{name}: {message}")]
+    Remote { name: String, message: String },
+}
+
+type Result<T> = std::result::Result<T, DbusError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    /// 元素的类型签名与元素
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+    Variant(Box<Value>),
+}
+
+impl Value {
+    pub fn str(value: impl Into<String>) -> Self {
+        Value::Str(value.into())
+    }
+
+    pub fn variant(value: Value) -> Self {
+        Value::Variant(Box::new(value))
+    }
+
+    /// `a{sv}` 字典
+    pub fn dict(entries: impl IntoIterator<Item = (&'static str, Value)>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|(key, value)| {
+                Value::DictEntry(Box::new(Value::str(key)), Box::new(Value::variant(value)))
+            })
+            .collect();
+        Value::Array("{sv}".into(), entries)
+    }
+
+    pub fn signature(&self) -> String {
+        match self {
+            Value::Byte(_) => "y".into(),
+            Value::Bool(_) => "b".into(),
+            Value::Int16(_) => "n".into(),
+            Value::UInt16(_) => "q".into(),
+            Value::Int32(_) => "i".into(),
+            Value::UInt32(_) => "u".into(),
+            Value::Int64(_) => "x".into(),
+            Value::UInt64(_) => "t".into(),
+            Value::Double(_) => "d".into(),
+            Value::Str(_) => "s".into(),
+            Value::ObjectPath(_) => "o".into(),
+            Value::Signature(_) => "g".into(),
+            Value::Array(element, _) => format!("a{element}"),
+            Value::Struct(fields) => {
+                format!(
+                    "({})",
+                    fields.iter().map(Value::signature).collect::<String>()
+                )
+            }
+            Value::DictEntry(key, value) => format!("{{{}{}}}", key.signature(), value.signature()),
+            Value::Variant(_) => "v".into(),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(value) | Value::ObjectPath(value) | Value::Signature(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            Value::Int32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            Value::UInt32(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// 在 `a{sv}` 字典中查找，返回变体中的值
+    pub fn dict_get(&self, key: &str) -> Option<&Value> {
+        let Value::Array(_, entries) = self else {
+            return None;
+        };
+        entries.iter().find_map(|entry| match entry {
+            Value::DictEntry(k, v) if k.as_str() == Some(key) => match v.as_ref() {
+                Value::Variant(value) => Some(value.as_ref()),
+                value => Some(value),
+            },
+            _ => None,
+        })
+    }
+}
+
+fn alignment(signature: u8) -> usize {
+    match signature {
+        b'y' | b'g' | b'v' => 1,
+        b'n' | b'q' => 2,
+        b'x' | b't' | b'd' | b'(' | b'{' => 8,
+        _ => 4,
+    }
+}
+
+/// 拆分出第一个完整类型，不接受空的结构体
+fn split_type(signature: &str) -> Result<(&str, &str)> {
+    let bytes = signature.as_bytes();
+    let mut end = 0;
+    while bytes.get(end) == Some(&b'a') {
+        end += 1;
+    }
+    match bytes.get(end) {
+        Some(b'(' | b'{') => {
+            let mut depth = 0;
+            for (i, c) in bytes.iter().enumerate().skip(end) {
+                match c {
+                    b'(' | b'{' => depth += 1,
+                    b')' | b'}' => depth -= 1,
+                    _ => {}
+                }
+                if depth == 0 {
+                    if i == end + 1 {
+                        return Err(DbusError::Malformed("empty struct"));
+                    }
+                    return Ok(signature.split_at(i + 1));
+                }
+            }
+            Err(DbusError::Malformed("unbalanced signature"))
+        }
+        Some(_) => Ok(signature.split_at(end + 1)),
+        None => Err(DbusError::Malformed("empty signature")),
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn align(&mut self, n: usize) {
+        self.buf.resize(self.buf.len().next_multiple_of(n), 0);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend(value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn signature(&mut self, value: &str) {
+        self.buf.push(value.len() as u8);
+        self.buf.extend(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Byte(v) => self.buf.push(*v),
+            Value::Bool(v) => self.u32(*v as u32),
+            Value::Int16(v) => {
+                self.align(2);
+                self.buf.extend(v.to_le_bytes());
+            }
+            Value::UInt16(v) => {
+                self.align(2);
+                self.buf.extend(v.to_le_bytes());
+            }
+            Value::Int32(v) => {
+                self.align(4);
+                self.buf.extend(v.to_le_bytes());
+            }
+            Value::UInt32(v) => self.u32(*v),
+            Value::Int64(v) => {
+                self.align(8);
+                self.buf.extend(v.to_le_bytes());
+            }
+            Value::UInt64(v) => {
+                self.align(8);
+                self.buf.extend(v.to_le_bytes());
+            }
+            Value::Double(v) => {
+                self.align(8);
+                self.buf.extend(v.to_le_bytes());
+            }
+            Value::Str(v) | Value::ObjectPath(v) => self.string(v),
+            Value::Signature(v) => self.signature(v),
+            Value::Array(element, items) => {
+                self.u32(0);
+                let length_at = self.buf.len() - 4;
+                // 长度不包括第一个元素前的填充
+                self.align(alignment(element.as_bytes()[0]));
+                let start = self.buf.len();
+                for item in items {
+                    self.value(item);
+                }
+                let length = (self.buf.len() - start) as u32;
+                self.buf[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+            }
+            Value::Struct(fields) => {
+                self.align(8);
+                for field in fields {
+                    self.value(field);
+                }
+            }
+            Value::DictEntry(key, value) => {
+                self.align(8);
+                self.value(key);
+                self.value(value);
+            }
+            Value::Variant(value) => {
+                self.signature(&value.signature());
+                self.value(value);
+            }
+        }
+    }
+}
+
+/// 正在读取的值所在的容器层数，规范限制数组与结构体各 32 层、连同变体合计 64 层
+#[derive(Debug, Default, Clone, Copy)]
+struct Depth {
+    arrays: u32,
+    structs: u32,
+    variants: u32,
+}
+
+impl Depth {
+    /// 进入类型码为 `code` 的容器，超出限制时返回错误，避免恶意消息耗尽栈空间
+    fn enter(self, code: u8) -> Result<Self> {
+        let mut depth = self;
+        match code {
+            b'a' => depth.arrays += 1,
+            b'(' | b'{' => depth.structs += 1,
+            b'v' => depth.variants += 1,
+            _ => return Ok(depth),
+        }
+        if depth.arrays > 32
+            || depth.structs > 32
+            || depth.arrays + depth.structs + depth.variants > 64
+        {
+            return Err(DbusError::Malformed("containers nested too deeply"));
+        }
+        Ok(depth)
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Decoder<'_> {
+    fn align(&mut self, n: usize) {
+        self.pos = self.pos.next_multiple_of(n);
+    }
+
+    fn take(&mut self, n: usize) -> Result<&[u8]> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + n)
+            .ok_or(DbusError::Malformed("unexpected end of message"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.align(N);
+        Ok(self.take(N)?.try_into().unwrap_or([0; N]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.fixed()?))
+    }
+
+    fn text(&mut self, len: usize) -> Result<String> {
+        let bytes = self.take(len + 1)?;
+        String::from_utf8(bytes[..len].to_vec()).map_err(|_| DbusError::Malformed("invalid utf-8"))
+    }
+
+    /// 读取一个值，变体中的签名来自消息，先检查是否恰好为一个完整类型
+    fn value(&mut self, signature: &str, depth: Depth) -> Result<Value> {
+        let (_, rest) = split_type(signature)?;
+        if !rest.is_empty() {
+            return Err(DbusError::Malformed("expected a single complete type"));
+        }
+        let code = signature.as_bytes()[0];
+        let depth = depth.enter(code)?;
+        Ok(match code {
+            b'y' => Value::Byte(self.take(1)?[0]),
+            b'b' => Value::Bool(self.u32()? != 0),
+            b'n' => Value::Int16(i16::from_le_bytes(self.fixed()?)),
+            b'q' => Value::UInt16(u16::from_le_bytes(self.fixed()?)),
+            b'i' => Value::Int32(i32::from_le_bytes(self.fixed()?)),
+            b'u' | b'h' => Value::UInt32(self.u32()?),
+            b'x' => Value::Int64(i64::from_le_bytes(self.fixed()?)),
+            b't' => Value::UInt64(u64::from_le_bytes(self.fixed()?)),
+            b'd' => Value::Double(f64::from_le_bytes(self.fixed()?)),
+            b's' | b'o' => {
+                let len = self.u32()? as usize;
+                let text = self.text(len)?;
+                if code == b's' {
+                    Value::Str(text)
+                } else {
+                    Value::ObjectPath(text)
+                }
+            }
+            b'g' => {
+                let len = self.take(1)?[0] as usize;
+                Value::Signature(self.text(len)?)
+            }
+            b'a' => {
+                let element = &signature[1..];
+                let len = self.u32()? as usize;
+                self.align(alignment(element.as_bytes()[0]));
+                let end = self.pos + len;
+                let mut items = Vec::new();
+                while self.pos < end {
+                    items.push(self.value(element, depth)?);
+                }
+                Value::Array(element.to_string(), items)
+            }
+            b'(' | b'{' => {
+                self.align(8);
+                let mut inner = &signature[1..signature.len() - 1];
+                let mut fields = Vec::new();
+                while !inner.is_empty() {
+                    let (field, rest) = split_type(inner)?;
+                    fields.push(self.value(field, depth)?);
+                    inner = rest;
+                }
+                if code == b'(' {
+                    Value::Struct(fields)
+                } else {
+                    let mut fields = fields.into_iter();
+                    match (fields.next(), fields.next()) {
+                        (Some(key), Some(value)) => {
+                            Value::DictEntry(Box::new(key), Box::new(value))
+                        }
+                        _ => return Err(DbusError::Malformed("invalid dict entry")),
+                    }
+                }
+            }
+            b'v' => {
+                let len = self.take(1)?[0] as usize;
+                let signature = self.text(len)?;
+                Value::variant(self.value(&signature, depth)?)
+            }
+            other => return Err(DbusError::UnsupportedType(other as char)),
+        })
+    }
+
+    fn values(&mut self, mut signature: &str) -> Result<Vec<Value>> {
+        let mut values = Vec::new();
+        while !signature.is_empty() {
+            let (first, rest) = split_type(signature)?;
+            values.push(self.value(first, Depth::default())?);
+            signature = rest;
+        }
+        Ok(values)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+/// 不需要回复
+const NO_REPLY_EXPECTED: u8 = 0x1;
+
+/// 规范允许的最大消息长度，128 MiB
+const MAX_MESSAGE_LEN: usize = 1 << 27;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub kind: MessageKind,
+    pub flags: u8,
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    fn new(kind: MessageKind) -> Self {
+        Self {
+            kind,
+            flags: 0,
+            serial: 0,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            destination: None,
+            sender: None,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn method_call(destination: &str, path: &str, interface: &str, member: &str) -> Self {
+        Self {
+            destination: Some(destination.into()),
+            path: Some(path.into()),
+            interface: Some(interface.into()),
+            member: Some(member.into()),
+            ..Self::new(MessageKind::MethodCall)
+        }
+    }
+
+    pub fn signal(path: &str, interface: &str, member: &str) -> Self {
+        Self {
+            path: Some(path.into()),
+            interface: Some(interface.into()),
+            member: Some(member.into()),
+            ..Self::new(MessageKind::Signal)
+        }
+    }
+
+    pub fn method_return(&self, body: Vec<Value>) -> Self {
+        Self {
+            reply_serial: Some(self.serial),
+            destination: self.sender.clone(),
+            body,
+            ..Self::new(MessageKind::MethodReturn)
+        }
+    }
+
+    pub fn error(&self, name: &str, message: &str) -> Self {
+        Self {
+            reply_serial: Some(self.serial),
+            destination: self.sender.clone(),
+            error_name: Some(name.into()),
+            body: vec![Value::str(message)],
+            ..Self::new(MessageKind::Error)
+        }
+    }
+
+    pub fn with_body(mut self, body: Vec<Value>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn expects_reply(&self) -> bool {
+        self.kind == MessageKind::MethodCall && self.flags & NO_REPLY_EXPECTED == 0
+    }
+
+    pub fn is(&self, interface: &str, member: &str) -> bool {
+        self.interface.as_deref() == Some(interface) && self.member.as_deref() == Some(member)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Encoder::default();
+        for value in &self.body {
+            body.value(value);
+        }
+        let signature: String = self.body.iter().map(Value::signature).collect();
+
+        let mut fields = Vec::new();
+        let mut field = |code: u8, value: Value| {
+            fields.push(Value::Struct(vec![
+                Value::Byte(code),
+                Value::variant(value),
+            ]));
+        };
+        if let Some(path) = &self.path {
+            field(1, Value::ObjectPath(path.clone()));
+        }
+        if let Some(interface) = &self.interface {
+            field(2, Value::str(interface));
+        }
+        if let Some(member) = &self.member {
+            field(3, Value::str(member));
+        }
+        if let Some(name) = &self.error_name {
+            field(4, Value::str(name));
+        }
+        if let Some(serial) = self.reply_serial {
+            field(5, Value::UInt32(serial));
+        }
+        if let Some(destination) = &self.destination {
+            field(6, Value::str(destination));
+        }
+        if let Some(sender) = &self.sender {
+            field(7, Value::str(sender));
+        }
+        if !signature.is_empty() {
+            field(8, Value::Signature(signature));
+        }
+
+        let mut message = Encoder::default();
+        message.buf.extend([b'l', self.kind as u8, self.flags, 1]);
+        message.u32(body.buf.len() as u32);
+        message.u32(self.serial);
+        message.value(&Value::Array("(yv)".into(), fields));
+        message.align(8);
+        message.buf.extend(body.buf);
+        message.buf
+    }
+
+    /// 从流中读取一条消息
+    pub fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut fixed = [0; 16];
+        reader.read_exact(&mut fixed)?;
+        if fixed[0] != b'l' {
+            return Err(DbusError::Malformed(
+                "big-endian messages are not supported",
+            ));
+        }
+        let body_len = u32::from_le_bytes(fixed[4..8].try_into().unwrap_or_default()) as usize;
+        let fields_len = u32::from_le_bytes(fixed[12..16].try_into().unwrap_or_default()) as usize;
+        if fields_len > MAX_MESSAGE_LEN || body_len > MAX_MESSAGE_LEN {
+            return Err(DbusError::Malformed("message too long"));
+        }
+        let header_len = (16 + fields_len).next_multiple_of(8);
+        if header_len + body_len > MAX_MESSAGE_LEN {
+            return Err(DbusError::Malformed("message too long"));
+        }
+
+        let mut buf = fixed.to_vec();
+        buf.resize(header_len + body_len, 0);
+        reader.read_exact(&mut buf[16..])?;
+        Self::decode(&buf, header_len)
+    }
+
+    fn decode(buf: &[u8], header_len: usize) -> Result<Self> {
+        let kind = match buf[1] {
+            1 => MessageKind::MethodCall,
+            2 => MessageKind::MethodReturn,
+            3 => MessageKind::Error,
+            4 => MessageKind::Signal,
+            _ => return Err(DbusError::Malformed("unknown message type")),
+        };
+        let mut header = Decoder { buf, pos: 8 };
+        let mut message = Self {
+            flags: buf[2],
+            serial: header.u32()?,
+            ..Self::new(kind)
+        };
+
+        let mut signature = String::new();
+        let Value::Array(_, fields) = header.value("a(yv)", Depth::default())? else {
+            return Err(DbusError::Malformed("invalid header"));
+        };
+        for field in fields {
+            let Value::Struct(field) = field else {
+                continue;
+            };
+            let (Some(Value::Byte(code)), Some(Value::Variant(value))) =
+                (field.first(), field.get(1))
+            else {
+                continue;
+            };
+            let text = value.as_str().map(str::to_string);
+            match code {
+                1 => message.path = text,
+                2 => message.interface = text,
+                3 => message.member = text,
+                4 => message.error_name = text,
+                5 => message.reply_serial = value.as_u32(),
+                6 => message.destination = text,
+                7 => message.sender = text,
+                8 => signature = text.unwrap_or_default(),
+                _ => {}
+            }
+        }
+
+        let mut body = Decoder {
+            buf: &buf[header_len..],
+            pos: 0,
+        };
+        message.body = body.values(&signature)?;
+        Ok(message)
+    }
+}
+
+/// 等待方法回复的最长时间，超时后连接中可能留有读了一半的消息，调用方应丢弃连接
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 等待回复时最多保留的其他消息，超出时丢弃最早的
+const MAX_PENDING: usize = 256;
+
+/// 总线连接，读写分离：读取在托盘或快捷键线程，写入可在任意线程
+pub struct Connection {
+    reader: BufReader<UnixStream>,
+    sender: Arc<Sender>,
+    /// 等待回复时收到的其他消息
+    pending: VecDeque<Message>,
+    pub unique_name: String,
+}
+
+pub struct Sender {
+    stream: Mutex<UnixStream>,
+    serial: AtomicU32,
+}
+
+impl Sender {
+    /// 发送消息，返回分配的序号
+    pub fn send(&self, mut message: Message) -> Result<u32> {
+        message.serial = self.serial.fetch_add(1, Ordering::Relaxed);
+        let bytes = message.encode();
+        let mut stream = self
+            .stream
+            .lock()
+            .map_err(|_| DbusError::Malformed("connection poisoned"))?;
+        stream.write_all(&bytes)?;
+        Ok(message.serial)
+    }
+}
+
+impl Connection {
+    /// 连接会话总线
+    pub fn session() -> Result<Self> {
+        Self::connect(session_address().ok_or(DbusError::NoAddress)?)
+    }
+
+    /// 连接系统总线，用于查询 logind
+    pub fn system() -> Result<Self> {
+        Self::connect(system_address())
+    }
+
+    fn connect(addresses: Vec<Address>) -> Result<Self> {
+        let stream = addresses
+            .into_iter()
+            .find_map(|address| address.connect().ok())
+            .ok_or(DbusError::NoAddress)?;
+        let uid = std::fs::metadata("/proc/self")?.uid();
+        Self::authenticate(stream, uid)
+    }
+
+    /// 以 EXTERNAL 方式认证并注册到总线
+    pub fn authenticate(stream: UnixStream, uid: u32) -> Result<Self> {
+        // 认证期间总线不应答时不一直等待，`call` 之后会恢复为不超时
+        stream.set_read_timeout(Some(CALL_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let hex: String = uid
+            .to_string()
+            .bytes()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        writer.write_all(format!("\0AUTH EXTERNAL {hex}\r\n").as_bytes())?;
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.starts_with("OK ") {
+            return Err(DbusError::Auth(line.trim().to_string()));
+        }
+        writer.write_all(b"BEGIN\r\n")?;
+
+        let mut connection = Self {
+            reader,
+            sender: Arc::new(Sender {
+                stream: Mutex::new(writer),
+                serial: AtomicU32::new(1),
+            }),
+            pending: VecDeque::new(),
+            unique_name: String::new(),
+        };
+        let reply = connection.call(Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+        ))?;
+        connection.unique_name = reply
+            .first()
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        Ok(connection)
+    }
+
+    pub fn sender(&self) -> Arc<Sender> {
+        self.sender.clone()
+    }
+
+    /// 调用方法并等待回复，最多等待 [`CALL_TIMEOUT`]
+    pub fn call(&mut self, message: Message) -> Result<Vec<Value>> {
+        let serial = self.sender.send(message)?;
+        let deadline = Instant::now() + CALL_TIMEOUT;
+        let reply = self.read_reply(serial, deadline);
+        // `receive` 等待信号时不超时
+        self.reader.get_ref().set_read_timeout(None)?;
+        reply
+    }
+
+    fn read_reply(&mut self, serial: u32, deadline: Instant) -> Result<Vec<Value>> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+            }
+            self.reader.get_ref().set_read_timeout(Some(remaining))?;
+            let message = Message::read(&mut self.reader)?;
+            if message.reply_serial != Some(serial) {
+                if self.pending.len() >= MAX_PENDING {
+                    self.pending.pop_front();
+                }
+                self.pending.push_back(message);
+                continue;
+            }
+            if message.kind == MessageKind::Error {
+                return Err(DbusError::Remote {
+                    name: message.error_name.unwrap_or_default(),
+                    message: message
+                        .body
+                        .first()
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                });
+            }
+            return Ok(message.body);
+        }
+    }
+
+    /// 接收下一条消息
+    pub fn receive(&mut self) -> Result<Message> {
+        match self.pending.pop_front() {
+            Some(message) => Ok(message),
+            None => Message::read(&mut self.reader),
+        }
+    }
+}
+
+enum Address {
+    Path(String),
+    Abstract(String),
+}
+
+impl Address {
+    fn connect(&self) -> io::Result<UnixStream> {
+        match self {
+            Address::Path(path) => UnixStream::connect(path),
+            #[cfg(target_os = "linux")]
+            Address::Abstract(name) => {
+                use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+                UnixStream::connect_addr(&SocketAddr::from_abstract_name(name)?)
+            }
+            #[cfg(not(target_os = "linux"))]
+            Address::Abstract(_) => Err(io::ErrorKind::Unsupported.into()),
+        }
+    }
+}
+
+/// `DBUS_SESSION_BUS_ADDRESS`，未设置时为 `$XDG_RUNTIME_DIR/bus`
+fn session_address() -> Option<Vec<Address>> {
+    let Ok(value) = std::env::var("DBUS_SESSION_BUS_ADDRESS") else {
+        let path = dirs::runtime_dir()?.join("bus");
+        return Some(vec![Address::Path(path.to_string_lossy().into_owned())]);
+    };
+    Some(parse_addresses(&value))
+}
+
+/// `DBUS_SYSTEM_BUS_ADDRESS`，未设置时为规范中的默认路径
+fn system_address() -> Vec<Address> {
+    match std::env::var("DBUS_SYSTEM_BUS_ADDRESS") {
+        Ok(value) => parse_addresses(&value),
+        Err(_) => vec![Address::Path("/var/run/dbus/system_bus_socket".into())],
+    }
+}
+
+/// 分号分隔的地址，只支持 `unix:` 传输
+fn parse_addresses(value: &str) -> Vec<Address> {
+    value
+        .split(';')
+        .filter_map(|address| {
+            let params = address.strip_prefix("unix:")?;
+            params
+                .split(',')
+                .find_map(|param| match param.split_once('=') {
+                    Some(("path", path)) => Some(Address::Path(unescape(path))),
+                    Some(("abstract", name)) => Some(Address::Abstract(unescape(name))),
+                    _ => None,
+                })
+        })
+        .collect()
+}
+
+/// 地址中的 `%xx` 转义
+fn unescape(value: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut chars = value.bytes();
+    while let Some(b) = chars.next() {
+        if b == b'%' {
+            let hex: Vec<u8> = chars.by_ref().take(2).collect();
+            if let Some(b) = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                bytes.push(b);
+                continue;
+            }
+        }
+        bytes.push(b);
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+fn round_trip(values: &[Value]) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    for value in values {
+        encoder.value(value);
+    }
+    let signature: String = values.iter().map(Value::signature).collect();
+    let mut decoder = Decoder {
+        buf: &encoder.buf,
+        pos: 0,
+    };
+    assert_eq!(decoder.values(&signature).unwrap(), values, "{signature}");
+    assert_eq!(decoder.pos, encoder.buf.len(), "{signature}");
+    encoder.buf
+}
+
+#[test]
+fn basic_types_round_trip() {
+    let values = [
+        Value::Byte(0xfe),
+        Value::Bool(true),
+        Value::Bool(false),
+        Value::Int16(-2),
+        Value::UInt16(0xfffe),
+        Value::Int32(i32::MIN),
+        Value::UInt32(u32::MAX),
+        Value::Int64(i64::MIN),
+        Value::UInt64(u64::MAX),
+        Value::Double(-1.5),
+        Value::str("时间 flies"),
+        Value::str(""),
+        Value::ObjectPath("/StatusNotifierItem".into()),
+        Value::Signature("a{sv}".into()),
+    ];
+    // 每个值前面放一个字节，检查各种对齐
+    for value in values {
+        round_trip(std::slice::from_ref(&value));
+        round_trip(&[Value::Byte(1), value]);
+    }
+}
+
+#[test]
+fn containers_round_trip() {
+    round_trip(&[
+        Value::Array("i".into(), vec![Value::Int32(1), Value::Int32(-1)]),
+        Value::Array("s".into(), vec![]),
+        Value::Array(
+            "ay".into(),
+            vec![Value::Array("y".into(), vec![Value::Byte(7)])],
+        ),
+        Value::Struct(vec![Value::Byte(1), Value::str("a"), Value::UInt64(2)]),
+        Value::Array(
+            "(ia{sv}av)".into(),
+            vec![Value::Struct(vec![
+                Value::Int32(3),
+                Value::dict([
+                    ("label", Value::str("Quit")),
+                    ("visible", Value::Bool(true)),
+                ]),
+                Value::Array("v".into(), vec![Value::variant(Value::Int16(4))]),
+            ])],
+        ),
+        Value::variant(Value::Struct(vec![Value::Byte(1), Value::Double(2.)])),
+        Value::variant(Value::variant(Value::Array("t".into(), vec![]))),
+    ]);
+    let dict = Value::dict([("LockedHint", Value::Bool(true))]);
+    assert_eq!(dict.dict_get("LockedHint"), Some(&Value::Bool(true)));
+    assert_eq!(dict.signature(), "a{sv}");
+}
+
+#[test]
+fn variant_and_array_alignment() {
+    // 变体内的值按在消息中的位置对齐：签名占 3 字节，之后填充到 8
+    let bytes = round_trip(&[Value::variant(Value::UInt64(1))]);
+    assert_eq!(bytes[..8], [1, b't', 0, 0, 0, 0, 0, 0]);
+    assert_eq!(bytes.len(), 16);
+    // 空数组也要填充到元素的对齐，长度不包括填充
+    let bytes = round_trip(&[Value::Byte(9), Value::Array("x".into(), vec![])]);
+    assert_eq!(bytes, [9, 0, 0, 0, 0, 0, 0, 0]);
+    let bytes = round_trip(&[Value::Array(
+        "(y)".into(),
+        vec![Value::Struct(vec![Value::Byte(5)])],
+    )]);
+    assert_eq!(bytes, [1, 0, 0, 0, 0, 0, 0, 0, 5]);
+    // 字节之后的结构体从 8 的倍数开始
+    let bytes = round_trip(&[Value::Byte(1), Value::Struct(vec![Value::Int16(2)])]);
+    assert_eq!(bytes, [1, 0, 0, 0, 0, 0, 0, 0, 2, 0]);
+
+    assert_eq!(split_type("a{sv}i").unwrap(), ("a{sv}", "i"));
+    assert_eq!(split_type("(i(yy))s").unwrap(), ("(i(yy))", "s"));
+    assert!(split_type("(ii").is_err());
+    let mut decoder = Decoder {
+        buf: &[1, 0],
+        pos: 0,
+    };
+    assert!(matches!(
+        decoder.value("u", Depth::default()),
+        Err(DbusError::Malformed(_))
+    ));
+
+    // 变体的签名来自消息，空签名、不完整的类型、空结构体与多个类型都是错误
+    for signature in ["", "a", "(", "()", "a()", "uu"] {
+        let mut buf = vec![signature.len() as u8];
+        buf.extend(signature.as_bytes());
+        buf.extend([0; 16]);
+        let mut decoder = Decoder { buf: &buf, pos: 0 };
+        assert!(
+            matches!(
+                decoder.value("v", Depth::default()),
+                Err(DbusError::Malformed(_))
+            ),
+            "{signature:?}"
+        );
+    }
+    assert!(split_type("a{}").is_err());
+
+    // 嵌套过深的变体在读取时拒绝，不会耗尽栈空间
+    let nested = |levels: usize| {
+        let mut value = Value::Byte(1);
+        for _ in 0..levels {
+            value = Value::variant(value);
+        }
+        let mut message = Message::signal("/", "org.example", "Nested").with_body(vec![value]);
+        message.serial = 1;
+        Message::read(&mut message.encode().as_slice())
+    };
+    assert!(nested(64).is_ok());
+    assert!(matches!(nested(65), Err(DbusError::Malformed(_))));
+    assert!(matches!(nested(1_000), Err(DbusError::Malformed(_))));
+    // 超出规范的长度不分配内存
+    let mut header = [b'l', 1, 0, 1].to_vec();
+    header.extend(u32::MAX.to_le_bytes());
+    header.extend([0; 8]);
+    assert!(matches!(
+        Message::read(&mut header.as_slice()),
+        Err(DbusError::Malformed(_))
+    ));
+}
+
+#[test]
+fn message_round_trip() {
+    let mut call = Message::method_call(
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/_31",
+        "org.freedesktop.DBus.Properties",
+        "Get",
+    )
+    .with_body(vec![
+        Value::str("org.freedesktop.login1.Session"),
+        Value::str("LockedHint"),
+    ]);
+    call.serial = 7;
+    call.sender = Some(":1.5".into());
+    let bytes = call.encode();
+    assert_eq!(Message::read(&mut bytes.as_slice()).unwrap(), call);
+
+    let reply = call.method_return(vec![Value::variant(Value::Bool(true))]);
+    let decoded = Message::read(&mut reply.encode().as_slice()).unwrap();
+    assert_eq!(decoded.reply_serial, Some(7));
+    assert_eq!(decoded.destination.as_deref(), Some(":1.5"));
+    assert_eq!(decoded.body, reply.body);
+
+    let error = call.error("org.freedesktop.DBus.Error.UnknownMethod", "no");
+    let decoded = Message::read(&mut error.encode().as_slice()).unwrap();
+    assert_eq!(decoded.kind, MessageKind::Error);
+    assert_eq!(decoded.body, [Value::str("no")]);
+    assert!(Message::read(&mut &b"B\x01\0\x01"[..]).is_err());
+}
+
+#[test]
+fn auth_handshake() {
+    use std::thread;
+
+    // 模拟总线：检查认证行，接受或拒绝，接受后回复 Hello
+    let serve = |accept: bool| {
+        let (client, bus) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || {
+            let mut writer = bus.try_clone().unwrap();
+            let mut reader = BufReader::new(bus);
+            let mut line = Vec::new();
+            reader.read_until(b'\n', &mut line).unwrap();
+            assert_eq!(line, b"\0AUTH EXTERNAL 31303030\r\n");
+            if !accept {
+                writer.write_all(b"REJECTED EXTERNAL\r\n").unwrap();
+                return;
+            }
+            writer.write_all(b"OK 0123456789abcdef\r\n").unwrap();
+            line.clear();
+            reader.read_until(b'\n', &mut line).unwrap();
+            assert_eq!(line, b"BEGIN\r\n");
+            let hello = Message::read(&mut reader).unwrap();
+            assert!(hello.is("org.freedesktop.DBus", "Hello"));
+            let mut reply = hello.method_return(vec![Value::str(":1.42")]);
+            reply.serial = 1;
+            writer.write_all(&reply.encode()).unwrap();
+        });
+        let result = Connection::authenticate(client, 1000);
+        server.join().unwrap();
+        result
+    };
+
+    assert_eq!(serve(true).unwrap().unique_name, ":1.42");
+    assert!(matches!(serve(false), Err(DbusError::Auth(line)) if line == "REJECTED EXTERNAL"));
+
+    let addresses = parse_addresses("unix:path=/run/user/1000/bu%73;tcp:host=x;unix:abstract=a");
+    assert!(
+        matches!(&addresses[..], [Address::Path(path), Address::Abstract(name)]
+        if path == "/run/user/1000/bus" && name == "a")
+    );
+}
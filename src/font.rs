@@ -1,43 +1,398 @@
-use bevy::{asset::load_internal_binary_asset, prelude::*};
-// use bevy_egui::egui::{FontData, FontDefinitions, FontFamily};
-// use bevy_egui::EguiContexts;
+use std::path::PathBuf;
+
+use bevy::{prelude::*, ui::UiSystem};
+use index::FontIndex;
+use thiserror::Error;
+use ttf_parser::Face;
+
+mod index;
+
+/// 内置字体，用户字体缺失或无效时使用
+pub(crate) const BUNDLED_FONT: &[u8] = include_bytes!("../assets/fonts/FiraSans-Bold.ttf");
+
+/// 未配置后备字体时依次尝试的中文字体
+const CJK_FALLBACK_FAMILIES: &[&str] = &[
+    "Noto Sans CJK SC",
+    "Source Han Sans SC",
+    "Microsoft YaHei",
+    "PingFang SC",
+    "WenQuanYi Micro Hei",
+];
 
 /// 需要在DefaultPlugin之后加入
-pub struct FontPlugin;
+///
+/// 首选字体注册为默认字体（`Handle::default()`），`TextFont::default()` 即可使用；
+/// 带 [`FallbackText`] 的文本会按字符覆盖情况在字体栈中选择字体
+#[derive(Default)]
+pub struct FontPlugin {
+    /// 用户指定的字体，未指定或加载失败时回退到内置字体
+    pub font: Option<FontSource>,
+    /// 首选字体缺字时依次尝试的字体，为空时使用常见中文字体
+    pub fallbacks: Vec<FontSource>,
+}
+
+/// 字体来源
+#[derive(Debug, Clone)]
+pub enum FontSource {
+    /// 字体文件路径
+    Path(PathBuf),
+    /// 系统字体家族名及字重，如 `Noto Sans CJK SC`、700
+    Family { name: String, weight: u16 },
+}
+
+#[derive(Debug, Error)]
+pub enum FontError {
+    #[error("font family `{0}` not found in system font directories")]
+    NotFound(String),
+    #[error("failed to read font {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid font {path:?}: {reason}")]
+    Invalid { path: PathBuf, reason: String },
+}
+
+/// 按优先级排列的字体及其覆盖的字符，第一个为默认字体
+#[derive(Resource, Default)]
+pub struct FontStack(Vec<(Handle<Font>, Coverage)>);
+
+impl FontStack {
+    /// 选择第一个包含该字符的字体，都不包含时使用默认字体
+    pub fn select(&self, c: char) -> Handle<Font> {
+        self.0
+            .iter()
+            .find(|(_, coverage)| coverage.contains(c))
+            .or(self.0.first())
+            .map(|(handle, _)| handle.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// 字体中有字形的字符，加载时解析一次
+#[derive(Debug, Default)]
+pub struct Coverage(Vec<u32>);
+
+impl Coverage {
+    fn new(font: &Font) -> Self {
+        let Ok(face) = Face::parse(&font.data, 0) else {
+            return Self::default();
+        };
+        let mut chars = Vec::new();
+        let subtables = face
+            .tables()
+            .cmap
+            .into_iter()
+            .flat_map(|cmap| cmap.subtables);
+        for subtable in subtables.filter(|subtable| subtable.is_unicode()) {
+            subtable.codepoints(|c| {
+                if subtable.glyph_index(c).is_some_and(|glyph| glyph.0 != 0) {
+                    chars.push(c);
+                }
+            });
+        }
+        chars.sort_unstable();
+        chars.dedup();
+        Self(chars)
+    }
+
+    /// 是否包含该字符的字形，空白字符视为都包含
+    pub fn contains(&self, c: char) -> bool {
+        c.is_whitespace() || self.0.binary_search(&(c as u32)).is_ok()
+    }
+}
+
+/// 需要按字符选择字体的文本，内容会被拆分为 `Text` 与若干 `TextSpan`
+#[derive(Component, Default, Deref, DerefMut)]
+#[require(Text)]
+pub struct FallbackText(pub String);
+
+impl FontSource {
+    /// 解析 `TIME_FLY_FONT` 中的一项：包含路径分隔符时视为路径，
+    /// 否则为 `家族名[:字重]`，字重可为数字或 `bold` 等名称
+    fn parse(value: &str) -> Self {
+        if value.contains(std::path::MAIN_SEPARATOR) || value.contains('/') {
+            return FontSource::Path(PathBuf::from(value));
+        }
+
+        let (name, weight) = match value.rsplit_once(':') {
+            Some((name, weight)) => (name, parse_weight(weight).unwrap_or(400)),
+            None => (value, 400),
+        };
+        FontSource::Family {
+            name: name.trim().to_string(),
+            weight,
+        }
+    }
+}
+
+impl FontPlugin {
+    /// 从环境变量 `TIME_FLY_FONT` 读取逗号分隔的字体列表，第一项为首选字体，其余为后备字体
+    pub fn from_env() -> Self {
+        let mut sources = std::env::var("TIME_FLY_FONT")
+            .ok()
+            .into_iter()
+            .flat_map(|value| {
+                value
+                    .split(',')
+                    .filter(|item| !item.trim().is_empty())
+                    .map(FontSource::parse)
+                    .collect::<Vec<_>>()
+            });
+        Self {
+            font: sources.next(),
+            fallbacks: sources.collect(),
+        }
+    }
+}
 
 impl Plugin for FontPlugin {
     fn build(&self, app: &mut App) {
-        load_internal_binary_asset!(
-            app,
-            Handle::default(),
-            "../assets/fonts/LXGWWenKaiMono.ttf",
-            |bytes: &[u8], _path: String| { Font::try_from_bytes(bytes.to_vec()).unwrap() }
+        // 只有按家族名查找时才需要扫描系统字体
+        let mut index: Option<FontIndex> = None;
+        let mut load = |source: &FontSource| {
+            if matches!(source, FontSource::Family { .. }) && index.is_none() {
+                index = Some(FontIndex::load_or_build());
+            }
+            load_font(source, index.as_ref())
+        };
+
+        let font = match self.font.as_ref().map(&mut load) {
+            Some(Ok(font)) => Some(font),
+            Some(Err(err)) => {
+                error!("{err}, falling back to bundled font");
+                None
+            }
+            None => None,
+        };
+
+        let fallbacks: Vec<Font> = if self.fallbacks.is_empty() {
+            CJK_FALLBACK_FAMILIES
+                .iter()
+                .map(|name| FontSource::Family {
+                    name: name.to_string(),
+                    weight: 400,
+                })
+                .find_map(|source| load(&source).ok())
+                .into_iter()
+                .collect()
+        } else {
+            self.fallbacks
+                .iter()
+                .filter_map(|source| {
+                    load(source)
+                        .inspect_err(|err| warn!("{err}, skipping fallback font"))
+                        .ok()
+                })
+                .collect()
+        };
+
+        let bundled = match bundled_font() {
+            Ok(font) => Some(font),
+            Err(err) => {
+                error!("{err}");
+                None
+            }
+        };
+
+        let is_bundled_default = font.is_none();
+        let Some(font) = font.or_else(|| bundled.clone()) else {
+            error!("no usable font, text will not be rendered");
+            return;
+        };
+
+        let mut fonts = app.world_mut().resource_mut::<Assets<Font>>();
+        let mut stack = vec![(Handle::default(), Coverage::new(&font))];
+        fonts.insert(AssetId::default(), font);
+        let others = fallbacks
+            .into_iter()
+            .chain(bundled.filter(|_| !is_bundled_default));
+        for font in others {
+            let coverage = Coverage::new(&font);
+            stack.push((fonts.add(font), coverage));
+        }
+
+        app.insert_resource(FontStack(stack)).add_systems(
+            PostUpdate,
+            (split_fallback_text, sync_span_colors)
+                .chain()
+                .before(UiSystem::Prepare),
         );
-        // app.add_systems(Startup, set_egui_fonts);
-    }
-}
-
-// fn set_egui_fonts(mut contexts: EguiContexts) {
-//     let mut fonts = FontDefinitions::default();
-
-//     // Install my own font (maybe supporting non-latin characters):
-//     fonts.font_data.insert(
-//         "lwgw".to_owned(),
-//         FontData::from_static(include_bytes!("../assets/fonts/LXGWWenKaiMono.ttf")),
-//     ); // .ttf and .otf supported
-
-//     // Put my font first (highest priority):
-//     fonts
-//         .families
-//         .get_mut(&FontFamily::Proportional)
-//         .unwrap()
-//         .insert(0, "lwgw".to_owned());
-
-//     // Put my font as last fallback for monospace:
-//     fonts
-//         .families
-//         .get_mut(&FontFamily::Monospace)
-//         .unwrap()
-//         .push("lwgw".to_owned());
-//     contexts.ctx_mut().set_fonts(fonts);
-// }
+    }
+}
+
+/// 将 [`FallbackText`] 按字体拆分为 `Text` 与后续的 `TextSpan`
+fn split_fallback_text(
+    mut commands: Commands,
+    stack: Res<FontStack>,
+    mut texts: Query<
+        (Entity, &FallbackText, &mut Text, &mut TextFont, &TextColor),
+        Changed<FallbackText>,
+    >,
+) {
+    for (entity, fallback, mut text, mut text_font, color) in texts.iter_mut() {
+        let mut runs: Vec<(Handle<Font>, String)> = Vec::new();
+        for c in fallback.chars() {
+            let font = stack.select(c);
+            match runs.last_mut() {
+                Some((last, run)) if *last == font => run.push(c),
+                _ => runs.push((font, c.to_string())),
+            }
+        }
+
+        let mut runs = runs.into_iter();
+        let (font, first) = runs.next().unwrap_or_default();
+        text.0 = first;
+        text_font.font = font;
+
+        let style = text_font.clone();
+        commands
+            .entity(entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for (font, run) in runs {
+                    parent.spawn((
+                        TextSpan(run),
+                        TextFont {
+                            font,
+                            ..style.clone()
+                        },
+                        *color,
+                    ));
+                }
+            });
+    }
+}
+
+// 颜色改变了的按字体拆分的文本
+type RecoloredText = (With<FallbackText>, Changed<TextColor>);
+
+/// 拆分出的 `TextSpan` 跟随所在文本的颜色
+fn sync_span_colors(
+    texts: Query<(&TextColor, &Children), RecoloredText>,
+    mut spans: Query<&mut TextColor, (With<TextSpan>, Without<FallbackText>)>,
+) {
+    for (color, children) in texts.iter() {
+        let mut iter = spans.iter_many_mut(children);
+        while let Some(mut span) = iter.fetch_next() {
+            *span = *color;
+        }
+    }
+}
+
+/// Bevy 不能指定字体集合（ttc）中的字形，先取出需要的字形（路径指定时为第一个）再加载
+fn load_font(source: &FontSource, index: Option<&FontIndex>) -> Result<Font, FontError> {
+    let (path, face_index) = match source {
+        FontSource::Path(path) => (path.clone(), 0),
+        FontSource::Family { name, weight } => index
+            .and_then(|index| index.query(name, *weight))
+            .map(|face| (face.path.clone(), face.index))
+            .ok_or_else(|| FontError::NotFound(name.clone()))?,
+    };
+
+    let bytes = std::fs::read(&path).map_err(|source| FontError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let invalid = |reason: String| FontError::Invalid {
+        path: path.clone(),
+        reason,
+    };
+    let bytes = extract_face(&bytes, face_index)
+        .ok_or_else(|| invalid(format!("no face {face_index} in collection")))?;
+    Font::try_from_bytes(bytes).map_err(|err| invalid(err.to_string()))
+}
+
+/// 从字体集合中取出一个字形，表复制为单独的字体文件；不是集合时原样返回
+fn extract_face(data: &[u8], index: u32) -> Option<Vec<u8>> {
+    let Some(count) = ttf_parser::fonts_in_collection(data) else {
+        return (index == 0).then(|| data.to_vec());
+    };
+    if index >= count {
+        return None;
+    }
+    let u16_at = |pos: usize| Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?));
+    let u32_at = |pos: usize| Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?));
+
+    // 集合头之后是各字形的偏移表，表记录为标签、校验和、偏移、长度
+    let offset = u32_at(12 + 4 * index as usize)? as usize;
+    let num_tables = u16_at(offset + 4)? as usize;
+    let header_len = 12 + 16 * num_tables;
+    let mut font = data.get(offset..offset + 12)?.to_vec();
+    let mut tables = Vec::new();
+    for i in 0..num_tables {
+        let record = offset + 12 + 16 * i;
+        let table = u32_at(record + 8)? as usize;
+        let len = u32_at(record + 12)? as usize;
+        font.extend(data.get(record..record + 8)?);
+        font.extend(((header_len + tables.len()) as u32).to_be_bytes());
+        font.extend((len as u32).to_be_bytes());
+        tables.extend(data.get(table..table + len)?);
+        tables.resize(tables.len().next_multiple_of(4), 0);
+    }
+    font.extend(tables);
+    Some(font)
+}
+
+fn bundled_font() -> Result<Font, FontError> {
+    Font::try_from_bytes(BUNDLED_FONT.to_vec()).map_err(|err| FontError::Invalid {
+        path: PathBuf::from("<bundled>"),
+        reason: err.to_string(),
+    })
+}
+
+fn parse_weight(weight: &str) -> Option<u16> {
+    let weight = weight.trim().to_ascii_lowercase();
+    let weight = match weight.as_str() {
+        "thin" => 100,
+        "extralight" | "ultralight" => 200,
+        "light" => 300,
+        "regular" | "normal" => 400,
+        "medium" => 500,
+        "semibold" | "demibold" => 600,
+        "bold" => 700,
+        "extrabold" | "ultrabold" => 800,
+        "black" | "heavy" => 900,
+        _ => weight.parse().ok()?,
+    };
+    Some(weight)
+}
+
+#[test]
+fn bundled_font_coverage() {
+    let coverage = Coverage::new(&bundled_font().unwrap());
+    assert!(coverage.contains('1'));
+    assert!(coverage.contains(':'));
+    assert!(coverage.contains(' '));
+    assert!(!coverage.contains('周'));
+}
+
+#[test]
+fn extract_face_from_collection() {
+    // 两个字形的集合：第一个没有任何表，第二个为内置字体
+    let empty: &[u8] = &[0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let num_tables = u16::from_be_bytes([BUNDLED_FONT[4], BUNDLED_FONT[5]]) as usize;
+    let mut collection = b"ttcf\0\x01\0\0\0\0\0\x02".to_vec();
+    collection.extend(20u32.to_be_bytes());
+    collection.extend(32u32.to_be_bytes());
+    collection.extend(empty);
+    let start = collection.len();
+    collection.extend(&BUNDLED_FONT[..12 + 16 * num_tables]);
+    // 表紧跟在偏移表之后，偏移整体后移
+    let shift = start as u32;
+    for i in 0..num_tables {
+        let at = start + 12 + 16 * i + 8;
+        let offset = u32::from_be_bytes(collection[at..at + 4].try_into().unwrap()) + shift;
+        collection[at..at + 4].copy_from_slice(&offset.to_be_bytes());
+    }
+    collection.extend(&BUNDLED_FONT[12 + 16 * num_tables..]);
+
+    assert_eq!(ttf_parser::fonts_in_collection(&collection), Some(2));
+    let face = extract_face(&collection, 1).unwrap();
+    let font = Font::try_from_bytes(face).unwrap();
+    assert!(Coverage::new(&font).contains('1'));
+    assert!(Face::parse(&extract_face(&collection, 0).unwrap(), 0).is_err());
+    assert!(extract_face(&collection, 2).is_none());
+    assert_eq!(extract_face(BUNDLED_FONT, 0).as_deref(), Some(BUNDLED_FONT));
+    assert!(extract_face(BUNDLED_FONT, 1).is_none());
+}
@@ -0,0 +1,192 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use bevy::log::{info, warn};
+use serde::{Deserialize, Serialize};
+use ttf_parser::{name_id, Face};
+
+/// 系统字体目录
+#[cfg(target_os = "windows")]
+const SYSTEM_FONT_DIRS: &[&str] = &["C:\\Windows\\Fonts"];
+#[cfg(target_os = "macos")]
+const SYSTEM_FONT_DIRS: &[&str] = &["/System/Library/Fonts", "/Library/Fonts"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SYSTEM_FONT_DIRS: &[&str] = &["/usr/share/fonts", "/usr/local/share/fonts"];
+
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "otc"];
+
+/// 字体索引中的一个字形（face）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaceEntry {
+    pub path: PathBuf,
+    /// 在字体集合（ttc）中的序号
+    pub index: u32,
+    /// 名称表中的所有家族名（含本地化名称）
+    pub families: Vec<String>,
+    pub weight: u16,
+    pub italic: bool,
+}
+
+/// 系统字体索引，缓存在磁盘上，字体目录有变化时重建
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FontIndex {
+    /// 扫描过的目录及其修改时间，用于判断缓存是否过期
+    dirs: Vec<(PathBuf, u64)>,
+    faces: Vec<FaceEntry>,
+}
+
+impl FontIndex {
+    /// 读取缓存的索引，过期或不存在时重新扫描并写回缓存
+    pub fn load_or_build() -> Self {
+        let roots = font_dirs();
+        let cache = cache_path();
+
+        if let Some(index) = cache.as_deref().and_then(Self::read_cache) {
+            if index.dirs == scan_dirs(&roots) {
+                return index;
+            }
+        }
+
+        let index = Self::build(&roots);
+        info!("indexed {} font faces", index.faces.len());
+        if let Some(cache) = cache {
+            if let Err(err) = index.write_cache(&cache) {
+                warn!("failed to write font index cache {cache:?}: {err}");
+            }
+        }
+        index
+    }
+
+    pub fn build(roots: &[PathBuf]) -> Self {
+        let dirs = scan_dirs(roots);
+        let faces = dirs
+            .iter()
+            .filter_map(|(dir, _)| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && is_font_file(path))
+            .flat_map(|path| read_faces(&path))
+            .collect();
+        Self { dirs, faces }
+    }
+
+    /// 按家族名（忽略大小写）查找字重最接近的字形，同等接近时优先正体
+    pub fn query(&self, family: &str, weight: u16) -> Option<&FaceEntry> {
+        self.faces
+            .iter()
+            .filter(|face| {
+                face.families
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(family))
+            })
+            .min_by_key(|face| (face.weight.abs_diff(weight), face.italic, face.index))
+    }
+
+    fn read_cache(path: &Path) -> Option<Self> {
+        let bytes = fs::read(path).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn write_cache(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+}
+
+fn font_dirs() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = SYSTEM_FONT_DIRS.iter().map(PathBuf::from).collect();
+    if cfg!(target_os = "windows") {
+        roots.extend(dirs::data_local_dir().map(|dir| dir.join("Microsoft\\Windows\\Fonts")));
+    } else {
+        roots.extend(dirs::font_dir());
+    }
+    roots
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("time-fly").join("font-index.json"))
+}
+
+/// 递归列出所有字体目录及其修改时间
+fn scan_dirs(roots: &[PathBuf]) -> Vec<(PathBuf, u64)> {
+    let mut dirs = Vec::new();
+    let mut pending: Vec<PathBuf> = roots.to_vec();
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let modified = fs::metadata(&dir)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_secs());
+        pending.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir()),
+        );
+        dirs.push((dir, modified));
+    }
+    dirs.sort();
+    dirs
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| FONT_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+fn read_faces(path: &Path) -> Vec<FaceEntry> {
+    let Ok(data) = fs::read(path) else {
+        return Vec::new();
+    };
+    let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+    (0..count)
+        .filter_map(|index| {
+            let face = Face::parse(&data, index).ok()?;
+            let mut families: Vec<String> = face
+                .names()
+                .into_iter()
+                .filter(|name| {
+                    name.name_id == name_id::TYPOGRAPHIC_FAMILY || name.name_id == name_id::FAMILY
+                })
+                .filter_map(|name| name.to_string())
+                .collect();
+            families.sort();
+            families.dedup();
+            (!families.is_empty()).then(|| FaceEntry {
+                path: path.to_path_buf(),
+                index,
+                families,
+                weight: face.weight().to_number(),
+                italic: face.is_italic(),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn query_prefers_closest_weight() {
+    let entry = |weight, italic| FaceEntry {
+        path: PathBuf::from("test.ttc"),
+        index: 0,
+        families: vec!["Noto Sans CJK SC".to_string()],
+        weight,
+        italic,
+    };
+    let index = FontIndex {
+        dirs: Vec::new(),
+        faces: vec![entry(400, false), entry(700, true), entry(700, false)],
+    };
+
+    let face = index.query("noto sans cjk sc", 700).unwrap();
+    assert_eq!((face.weight, face.italic), (700, false));
+    assert_eq!(index.query("Noto Sans CJK SC", 300).unwrap().weight, 400);
+    assert!(index.query("Fira Sans", 400).is_none());
+}
@@ -1,13 +1,35 @@
+use accessibility::AccessibilityPlugin;
 use bevy::{
     app::{Plugin, Startup},
     core_pipeline::{bloom::Bloom, tonemapping::Tonemapping},
     prelude::*,
-    window::{Monitor, PrimaryMonitor, PrimaryWindow, WindowResolution},
+    window::PrimaryWindow,
 };
 use cube::CubePlugin;
+use daylight::{Daylight, DaylightPlugin};
+use theme::{CurrentTheme, ThemePlugin};
+use view::ViewConfig;
 
+use crate::action::Action;
+
+#[cfg(all(target_os = "linux", feature = "layer-shell"))]
+pub use cube::Placement;
+pub use cube::{
+    parse_moves, render_snapshot, CubeState, MoveFinished, MoveLog, Order, PuzzleKind,
+    RotationPaused,
+};
+pub use status::STATUS_FILE_PREFIX;
+pub use theme::Themes;
+pub use time::{ClockMode, TimerExpired};
+
+mod accessibility;
 mod cube;
+mod daylight;
+mod status;
+mod sticker;
+mod theme;
 mod time;
+mod view;
 
 pub struct GraphicsPlugin;
 
@@ -20,25 +42,37 @@ const CAMERA_TRANFOMER: Transform = Transform {
 impl Plugin for GraphicsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ClearColor(Color::NONE))
-            .add_plugins(CubePlugin)
-            .add_systems(Startup, (setup_window, setup_camera));
+            .insert_resource(ViewConfig::from_env())
+            .add_plugins((AccessibilityPlugin, ThemePlugin, DaylightPlugin, CubePlugin))
+            .add_systems(Startup, setup_camera)
+            .add_systems(Update, window_actions)
+            .add_systems(
+                Update,
+                apply_bloom
+                    .run_if(resource_changed::<CurrentTheme>.or(resource_changed::<Daylight>)),
+            );
     }
 }
 
-fn setup_window(
-    monitor: Single<&Monitor, With<PrimaryMonitor>>,
+fn window_actions(
+    mut actions: EventReader<Action>,
     mut window: Single<&mut Window, With<PrimaryWindow>>,
 ) {
-    // 完全等于屏幕大小会进入全屏模式，窗口背景变黑
-    let width = monitor.physical_width as f32 - 0.1;
-    let height = monitor.physical_height as f32 - 0.1;
-    let scale_factor = monitor.scale_factor as f32;
-    window.resolution =
-        WindowResolution::new(width, height).with_scale_factor_override(scale_factor);
-    window.position = WindowPosition::Centered(MonitorSelection::Primary);
+    for action in actions.read() {
+        match action {
+            Action::Show => window.visible = true,
+            Action::Hide => window.visible = false,
+            Action::ToggleVisibility => window.visible = !window.visible,
+            Action::ToggleClickThrough => {
+                let hit_test = !window.cursor_options.hit_test;
+                window.cursor_options.hit_test = hit_test;
+            }
+            _ => {}
+        }
+    }
 }
 
-fn setup_camera(mut commands: Commands) {
+fn setup_camera(mut commands: Commands, view: Res<ViewConfig>) {
     // 添加相机
     commands.spawn((
         Camera3d::default(),
@@ -48,6 +82,14 @@ fn setup_camera(mut commands: Commands) {
         },
         Tonemapping::TonyMcMapface,
         CAMERA_TRANFOMER,
+        view.projection(),
         Bloom::NATURAL,
     ));
 }
+
+fn apply_bloom(theme: Res<CurrentTheme>, daylight: Res<Daylight>, mut blooms: Query<&mut Bloom>) {
+    for mut bloom in blooms.iter_mut() {
+        bloom.intensity = theme.bloom.intensity * daylight.bloom_scale();
+        bloom.low_frequency_boost = theme.bloom.low_frequency_boost;
+    }
+}
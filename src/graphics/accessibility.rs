@@ -0,0 +1,164 @@
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+
+use super::theme::Theme;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use self::linux as platform;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub fn prefers_reduced_motion() -> Option<bool> {
+        None
+    }
+}
+
+/// 高对比度下的文字颜色，与内置 `high-contrast` 主题相同
+const CONTRAST_TEXT: Color = Color::srgb(1., 1., 0.);
+/// 高对比度下文字的放大倍数
+const CONTRAST_TEXT_SCALE: f32 = 1.4;
+/// 减少动态效果时指定转动的速度倍数
+const REDUCED_TURN_SPEED: f32 = 0.5;
+
+/// 无障碍插件，需要在 [`ThemePlugin`](super::theme::ThemePlugin) 之前加入
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        let (accessibility, follow_desktop) = Accessibility::from_env();
+        app.insert_resource(accessibility);
+        if follow_desktop {
+            // 桌面设置可能要经 D-Bus 查询，放到后台，结果返回前不减少动态效果
+            let task =
+                AsyncComputeTaskPool::get().spawn(async { platform::prefers_reduced_motion() });
+            app.insert_resource(DesktopMotion(task)).add_systems(
+                Update,
+                apply_desktop_motion.run_if(resource_exists::<DesktopMotion>),
+            );
+        }
+    }
+}
+
+/// 正在查询的桌面动画设置
+#[derive(Resource)]
+struct DesktopMotion(Task<Option<bool>>);
+
+/// 无障碍设置
+///
+/// `TIME_FLY_ACCESSIBILITY=on` 去掉泛光与透明、放大文字并使用高对比度颜色，同时减少动态效果；
+/// `TIME_FLY_REDUCED_MOTION` 为 `on`、`off` 或 `auto`（默认），`auto` 时跟随桌面设置
+/// （GNOME 的 `enable-animations`、KDE 的动画速度，经设置门户读取；Windows 的客户区动画）
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Accessibility {
+    /// 不再随机转动，指定的转动放慢，环境动画与渐变直接跳到结果
+    pub reduced_motion: bool,
+    /// 去掉泛光与透明，文字放大并使用高对比度颜色
+    pub high_contrast: bool,
+}
+
+impl Accessibility {
+    /// 第二项表示减少动态效果需要跟随桌面设置，桌面设置由 [`AccessibilityPlugin`] 在后台查询
+    pub fn from_env() -> (Self, bool) {
+        let high_contrast = match std::env::var("TIME_FLY_ACCESSIBILITY") {
+            Ok(value) => parse_switch(&value).unwrap_or_else(|| {
+                warn!("invalid TIME_FLY_ACCESSIBILITY `{value}`, using `off`");
+                false
+            }),
+            Err(_) => false,
+        };
+        let configured = match std::env::var("TIME_FLY_REDUCED_MOTION") {
+            Ok(value) if value.trim() == "auto" => None,
+            Ok(value) => parse_switch(&value).or_else(|| {
+                warn!("invalid TIME_FLY_REDUCED_MOTION `{value}`, using `auto`");
+                None
+            }),
+            Err(_) => None,
+        };
+        let reduced_motion = configured.unwrap_or(high_contrast);
+        if reduced_motion {
+            info!("reduced motion enabled");
+        }
+        let accessibility = Self {
+            reduced_motion,
+            high_contrast,
+        };
+        (accessibility, configured.is_none() && !high_contrast)
+    }
+
+    /// 指定转动的速度倍数
+    pub fn turn_speed(&self) -> f32 {
+        if self.reduced_motion {
+            REDUCED_TURN_SPEED
+        } else {
+            1.
+        }
+    }
+
+    /// 文字的放大倍数
+    pub fn text_scale(&self) -> f32 {
+        if self.high_contrast {
+            CONTRAST_TEXT_SCALE
+        } else {
+            1.
+        }
+    }
+
+    /// 高对比度时覆盖主题中的泛光、透明度与文字颜色
+    pub fn apply(&self, theme: &mut Theme) {
+        if !self.high_contrast {
+            return;
+        }
+        theme.bloom.intensity = 0.;
+        theme.bloom.low_frequency_boost = 0.;
+        theme.piece.vertex_alpha = 1.;
+        theme.text.color = CONTRAST_TEXT;
+    }
+}
+
+fn apply_desktop_motion(
+    mut commands: Commands,
+    mut query: ResMut<DesktopMotion>,
+    mut accessibility: ResMut<Accessibility>,
+) {
+    let Some(reduced_motion) = block_on(future::poll_once(&mut query.0)) else {
+        return;
+    };
+    commands.remove_resource::<DesktopMotion>();
+    if reduced_motion == Some(true) {
+        info!("reduced motion enabled");
+        accessibility.reduced_motion = true;
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.trim() {
+        "on" | "1" | "true" => Some(true),
+        "off" | "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+#[test]
+fn high_contrast_theme() {
+    let themes = super::Themes::builtin();
+    let mut theme = themes.get("glass").unwrap().clone();
+    Accessibility::default().apply(&mut theme);
+    assert!(theme.bloom.intensity > 0.);
+
+    let accessibility = Accessibility {
+        reduced_motion: true,
+        high_contrast: true,
+    };
+    accessibility.apply(&mut theme);
+    assert_eq!(theme.bloom.intensity, 0.);
+    assert_eq!(theme.piece.vertex_alpha, 1.);
+    assert_eq!(theme.text.color, CONTRAST_TEXT);
+    assert_eq!(accessibility.turn_speed(), REDUCED_TURN_SPEED);
+}
@@ -0,0 +1,44 @@
+use crate::dbus::{Connection, Message, Value};
+
+const PORTAL: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SETTINGS: &str = "org.freedesktop.portal.Settings";
+
+/// 经 `org.freedesktop.portal.Settings` 读取桌面的动画设置，先查 GNOME，再查 KDE
+pub fn prefers_reduced_motion() -> Option<bool> {
+    let mut connection = Connection::session().ok()?;
+    if let Some(Value::Bool(enabled)) = read(
+        &mut connection,
+        "org.gnome.desktop.interface",
+        "enable-animations",
+    ) {
+        return Some(!enabled);
+    }
+    match read(
+        &mut connection,
+        "org.kde.kdeglobals.KDE",
+        "AnimationDurationFactor",
+    ) {
+        Some(Value::Double(factor)) => Some(factor == 0.),
+        _ => None,
+    }
+}
+
+fn read(connection: &mut Connection, namespace: &str, key: &str) -> Option<Value> {
+    let body = vec![Value::str(namespace), Value::str(key)];
+    // ReadOne 从第 2 版接口开始提供，旧版的 Read 在结果外多包一层变体
+    let reply = connection
+        .call(
+            Message::method_call(PORTAL, PORTAL_PATH, SETTINGS, "ReadOne").with_body(body.clone()),
+        )
+        .or_else(|_| {
+            connection
+                .call(Message::method_call(PORTAL, PORTAL_PATH, SETTINGS, "Read").with_body(body))
+        })
+        .ok()?;
+    let mut value = reply.into_iter().next()?;
+    while let Value::Variant(inner) = value {
+        value = *inner;
+    }
+    Some(value)
+}
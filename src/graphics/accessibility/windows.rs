@@ -0,0 +1,21 @@
+use std::ffi::c_void;
+
+use windows::Win32::UI::WindowsAndMessaging::{
+    SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+};
+
+/// 系统设置中“显示动画”关闭时客户区动画也被关闭
+pub fn prefers_reduced_motion() -> Option<bool> {
+    // 结果写入 BOOL
+    let mut enabled: i32 = 1;
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut enabled as *mut i32 as *mut c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .ok()?;
+    Some(enabled == 0)
+}
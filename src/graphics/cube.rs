@@ -1,80 +1,153 @@
-use std::{f32::consts::PI, time::Duration};
+use std::time::Duration;
 
 use bevy::{
     asset::RenderAssetUsages,
     prelude::*,
     render::{
-        camera::RenderTarget,
         mesh::VertexAttributeValues,
         render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
     },
-    time::common_conditions::on_timer,
+    time::common_conditions::on_real_timer,
     window::{Monitor, PrimaryMonitor},
 };
 use bevy_prng::WyRand;
-use bevy_rand::prelude::Entropy;
-use rand_core::RngCore;
+use bevy_rand::prelude::{Entropy, ForkableRng, GlobalEntropy};
 
-use super::time::{TimePlugin, TimeSpan};
+use crate::{
+    action::Action,
+    power::{Animating, ScreenInactive},
+};
+
+use super::{
+    accessibility::Accessibility,
+    daylight::Daylight,
+    status::StatusPlugin,
+    theme::{CurrentTheme, PieceStyle, Theme},
+    time::TimePlugin,
+    view::{ViewAngle, ViewConfig, CUBE_DEPTH},
+};
+use ambient::{AmbientPlugin, BasePose};
+use autohide::AutoHidePlugin;
+use face::FacePlugin;
+use persist::{InitialState, PersistPlugin};
+use placement::PlacementPlugin;
+use puzzle::Puzzle;
+use replay::{MoveQueue, MoveSource, ReplayMoves, ReplayPlugin};
+use scramble::Scrambler;
+use state::{Face, Move, Turn};
+
+pub use placement::Placement;
+pub use puzzle::PuzzleKind;
+pub use replay::{MoveFinished, MoveLog};
+pub use snapshot::render_snapshot;
+pub use state::{parse_moves, CubeState, Order};
+
+mod ambient;
+mod autohide;
+mod face;
+mod persist;
+mod placement;
+mod puzzle;
+mod replay;
+mod scramble;
+mod snapshot;
+mod state;
 
 const CUBE_PIECE_SIZE: f32 = 1.0;
 const CUBE_PIECE_OFFSET: f32 = CUBE_PIECE_SIZE * 1.1;
 const CUBE_SIZE: f32 = CUBE_PIECE_SIZE * 3. + (CUBE_PIECE_OFFSET - CUBE_PIECE_SIZE) * 2.;
 // 定义立方体的角
 const LOCAL_CORNER: Vec3 = Vec3::new(CUBE_SIZE / 2., -CUBE_SIZE / 2., -CUBE_SIZE / 2.);
+// 光源沿对称轴距离中心的距离
+const LIGHT_OFFSET: f32 = 5.0;
+// 报时纹理的边长与字号
+const TEXTURE_SIZE: u32 = 512;
+const TEXT_SIZE: f32 = 50.;
 
+/// 时钟本体，默认为三阶魔方，也可以是其他转动谜题，见 [`PuzzleKind`]
 pub struct CubePlugin;
 
 impl Plugin for CubePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(TimePlugin)
+        let puzzle = PuzzleKind::from_env();
+        app.insert_resource(puzzle)
+            .add_plugins((
+                PersistPlugin,
+                TimePlugin,
+                StatusPlugin,
+                FacePlugin,
+                ReplayPlugin,
+                AutoHidePlugin,
+                PlacementPlugin,
+                AmbientPlugin,
+            ))
             .insert_resource(RotationState {
                 is_rotating: false,
-                current_face: Face::Front,
-                rotation_axis: Vec3::Z,
-                rotation_direction: 0.5,
+                current_move: Move::new(Face::Front, Turn::Quarter),
+                current_source: MoveSource::Random,
                 progress: 0.,
             })
+            .insert_resource(Scrambler::from_env(puzzle))
+            .init_resource::<RotationPaused>()
             .add_systems(Startup, setup)
             .add_systems(Update, set_cube_position.run_if(run_once))
             .add_systems(
-                FixedUpdate,
-                auto_rotate.run_if(on_timer(Duration::from_secs(1))),
+                Update,
+                apply_theme
+                    .run_if(resource_changed::<CurrentTheme>.or(resource_changed::<Daylight>)),
             )
-            .add_systems(FixedUpdate, rotate_face);
+            .add_systems(
+                Update,
+                (
+                    queue_actions,
+                    (
+                        replay_moves,
+                        auto_rotate.run_if(on_real_timer(Duration::from_secs(1)).and(
+                            |accessibility: Res<Accessibility>| !accessibility.reduced_motion,
+                        )),
+                        rotate_face,
+                    )
+                        .run_if(
+                            |paused: Res<RotationPaused>, screen: Res<ScreenInactive>| {
+                                !paused.0 && !screen.0
+                            },
+                        ),
+                )
+                    .chain(),
+            );
     }
 }
 
+/// 暂停时停在当前位置，不再开始新的转动；锁屏等 [`ScreenInactive`] 时同样暂停
+#[derive(Resource, Default)]
+pub struct RotationPaused(pub bool);
+
 #[derive(Component)]
-#[require(Visibility)]
+#[require(Transform, Visibility)]
 struct Cube;
 
 #[derive(Component, Debug)]
 #[require(Mesh3d)]
 struct CubePiece {
-    x: i32,
-    y: i32,
-    z: i32,
+    position: IVec3,
+    // 逻辑朝向，贴纸随方块转动
+    orientation: Quat,
 }
 
-// 旋转面枚举
-#[derive(PartialEq, Copy, Clone, Debug)]
-enum Face {
-    Front,
-    Back,
-    Left,
-    Right,
-    Up,
-    Down,
-}
+/// 方块的初始逻辑坐标，颜色随方块移动
+#[derive(Component, Deref)]
+struct PieceHome(IVec3);
+
+/// 渐变外观下所有方块共用的网格
+#[derive(Resource)]
+struct GradientMesh(Handle<Mesh>);
 
 // 旋转状态资源
 #[derive(Resource)]
 struct RotationState {
     is_rotating: bool,
-    current_face: Face,
-    rotation_axis: Vec3,
-    rotation_direction: f32,
+    current_move: Move,
+    current_source: MoveSource,
     progress: f32,
 }
 
@@ -82,110 +155,30 @@ fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut images: ResMut<Assets<Image>>,
-    asset_server: Res<AssetServer>,
+    theme: Res<CurrentTheme>,
+    mut entropy: GlobalEntropy<WyRand>,
+    initial: Res<InitialState>,
 ) {
-    let cube_texture = images.add(cube_texture());
-    let texture_camera = commands
-        .spawn((
-            Camera2d,
-            Camera {
-                target: RenderTarget::Image(cube_texture.clone()),
-                ..default()
-            },
-        ))
-        .id();
-
-    commands
-        .spawn((
-            Node {
-                // Cover the whole image
-                width: Val::Percent(100.),
-                height: Val::Percent(100.),
-                flex_direction: FlexDirection::Column,
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                ..default()
-            },
-            BackgroundColor(Color::NONE),
-            TargetCamera(texture_camera),
-        ))
-        .with_children(|parent| {
-            parent.spawn((
-                TimeSpan,
-                TextFont {
-                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                    font_size: 50.,
-                    ..default()
-                },
-                Transform::default().with_rotation(Quat::from_rotation_z(PI / 4.)),
-            ));
-        });
-
-    let cube_handle = meshes.add(Cuboid::from_length(CUBE_SIZE));
-
-    // This material has the texture that has been rendered.
-    let time_material_handle = materials.add(StandardMaterial {
-        base_color_texture: Some(cube_texture),
-        alpha_mode: AlphaMode::Blend,
-        ..default()
-    });
-
-    // 创建水晶材质
-    let glass_material = StandardMaterial {
-        // 材质表面属性
-        perceptual_roughness: 0.5, // 非常光滑的表面
-        metallic: 0.8,             // 高金属感模拟冰面反射
-        reflectance: 0.9,          // 增强反射强度
-
-        // 透明度设置
-        alpha_mode: AlphaMode::Blend, // 启用透明混合
-
-        ..Default::default()
-    };
+    let colorful_cube = meshes.add(gradient_mesh(theme.piece.vertex_alpha));
+    commands.insert_resource(GradientMesh(colorful_cube.clone()));
+    let puzzle = initial.0.puzzle.puzzle();
 
     commands
-        .spawn((
-            Mesh3d(cube_handle),
-            MeshMaterial3d(time_material_handle),
-            Cube,
-            Entropy::<WyRand>::default(),
-        ))
+        .spawn((Cube, entropy.fork_rng()))
         .with_children(|commands| {
-            let mut colorful_cube = Mesh::from(Cuboid::from_length(CUBE_PIECE_SIZE));
-            if let Some(VertexAttributeValues::Float32x3(positions)) =
-                colorful_cube.attribute(Mesh::ATTRIBUTE_POSITION)
-            {
-                let colors: Vec<[f32; 4]> = positions
-                    .iter()
-                    .map(|[r, g, b]| [(1. - *r) / 2., (1. - *g) / 2., (1. - *b) / 2., 0.3])
-                    .collect();
-                colorful_cube.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
-            }
-            let colorful_cube = meshes.add(colorful_cube);
-
-            // 生成3x3x3魔方
-            for x in -1..=1 {
-                for y in -1..=1 {
-                    for z in -1..=1 {
-                        if x == 0 && y == 0 && z == 0 {
-                            continue;
-                        }
-
-                        let mut glass_material = glass_material.clone();
-                        glass_material.base_color = pos2color(x, y, z);
-                        commands.spawn((
-                            Mesh3d(colorful_cube.clone()),
-                            MeshMaterial3d(materials.add(glass_material)),
-                            Transform::from_xyz(
-                                x as f32 * CUBE_PIECE_OFFSET,
-                                y as f32 * CUBE_PIECE_OFFSET,
-                                z as f32 * CUBE_PIECE_OFFSET,
-                            ),
-                            CubePiece { x, y, z },
-                        ));
-                    }
-                }
+            for piece in &initial.0.pieces {
+                let mesh = piece_mesh(&theme, puzzle, piece.home)
+                    .map_or_else(|| colorful_cube.clone(), |mesh| meshes.add(mesh));
+                commands.spawn((
+                    Mesh3d(mesh),
+                    MeshMaterial3d(materials.add(piece_material(&theme, puzzle, piece.home))),
+                    puzzle.piece_transform(piece.position, piece.orientation),
+                    CubePiece {
+                        position: piece.position,
+                        orientation: piece.orientation,
+                    },
+                    PieceHome(piece.home),
+                ));
             }
         });
 
@@ -196,146 +189,166 @@ fn setup(
 }
 
 fn set_cube_position(
+    mut commands: Commands,
     camera: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
     monitor: Single<&Monitor, With<PrimaryMonitor>>,
-    mut cube: Query<&mut Transform, With<Cube>>,
+    placement: Res<Placement>,
+    view: Res<ViewConfig>,
+    mut cube: Query<(Entity, &mut Transform), With<Cube>>,
     mut light: Query<&mut Transform, (With<PointLight>, Without<Cube>)>,
 ) {
     let (camera, camera_transform) = *camera;
 
-    let viewport_position = Vec2::new(
-        monitor.physical_width as f32 - CUBE_SIZE * 70.0,
-        monitor.physical_height as f32 - CUBE_SIZE * 70.0,
-    ) / monitor.scale_factor as f32;
+    let viewport_position = placement.cube_viewport_position(&monitor, &view);
 
     // Calculate a ray pointing from the camera into the world based on the cursor's position.
     let Ok(ray) = camera.viewport_to_world(camera_transform, viewport_position) else {
         return;
     };
 
-    // Calculate if and where the ray is hitting the ground plane.
-    let Some(distance) = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y)) else {
+    // 魔方放在与相机正对、距离固定的平面上，任何角落、两种投影下大小都一致
+    let plane_origin = camera_transform.translation() + camera_transform.forward() * CUBE_DEPTH;
+    let Some(distance) =
+        ray.intersect_plane(plane_origin, InfinitePlane3d::new(camera_transform.back()))
+    else {
         return;
     };
     let cube_pos = ray.get_point(distance);
-    let cube_rotation = rotation_of_cube(&cube_pos, camera_transform);
+    // 正交投影下视线都与相机前方平行
+    let view_dir = if view.orthographic {
+        *camera_transform.forward()
+    } else {
+        cube_pos - camera_transform.translation()
+    };
+    let cube_rotation = rotation_of_cube(view_dir, view.angle);
 
-    for mut transform in cube.iter_mut() {
+    for (entity, mut transform) in cube.iter_mut() {
         transform.translation = cube_pos;
         transform.rotation = cube_rotation;
+        transform.scale = Vec3::splat(view.scale);
+        commands.entity(entity).insert(BasePose {
+            transform: *transform,
+            axis: -view_dir.normalize(),
+            up: *camera_transform.up(),
+        });
     }
 
-    // 计算旋转后的对称轴方向（原局部坐标系中的对角线方向）
-    let world_symmetry_axis = cube_rotation * -LOCAL_CORNER.normalize(); // 转换到世界坐标系
-
-    // 设置光源沿对称轴方向偏移（距离根据正方体大小调整）
-    let light_offset_distance = 5.0; // 光源距离中心的距离
-    let light_position = cube_pos + world_symmetry_axis * light_offset_distance;
-
+    // 光源在魔方朝向相机的一侧，距离随魔方缩放
+    let light_position = cube_pos - view_dir.normalize() * LIGHT_OFFSET * view.scale;
     for mut transform in light.iter_mut() {
         transform.translation = light_position;
     }
 }
 
-fn rotation_of_cube(cube_pos: &Vec3, camera_transform: &GlobalTransform) -> Quat {
-    let camera_pos = camera_transform.translation();
-
-    // 计算正方体中心到相机的方向
-    let target_dir = cube_pos - camera_pos;
-    let target_dir_normalized = target_dir.normalize();
-
-    // 正方体边长为1.0，角点初始局部坐标为(0.5, 0.5, 0.5)
-    let initial_dir = LOCAL_CORNER.normalize(); // 初始方向向量
-
-    // 计算旋转轴和角度
-    let cross = initial_dir.cross(target_dir_normalized);
-    let rotation_axis = cross.normalize();
-    let cos_theta = initial_dir.dot(target_dir_normalized);
-    let theta = cos_theta.acos();
-
-    // 构造四元数并应用旋转
-    Quat::from_axis_angle(rotation_axis, theta)
+/// 让 `angle` 指定的部分正对视线 `view_dir`（从相机指向魔方）
+///
+/// 局部的背向方向转到视线方向；两者平行或相反时 `from_rotation_arc` 也有定义
+fn rotation_of_cube(view_dir: Vec3, angle: ViewAngle) -> Quat {
+    let target_dir = view_dir.try_normalize().unwrap_or(Vec3::NEG_Z);
+    Quat::from_rotation_arc(angle.away_from_camera(), target_dir) * angle.local_rotation()
 }
 
 fn auto_rotate(
     mut rng: Single<&mut Entropy<WyRand>, With<Cube>>,
-    mut rotation_state: ResMut<RotationState>,
+    rotation_state: Res<RotationState>,
+    mut scrambler: ResMut<Scrambler>,
+    mut queue: ResMut<MoveQueue>,
+    replay: Res<ReplayMoves>,
 ) {
-    if rotation_state.is_rotating {
+    if rotation_state.is_rotating || !queue.is_empty() || !replay.is_empty() {
         return;
     }
 
-    let rotate_index = rng.next_u32() % 6;
+    let moves = scrambler.next_moves(&mut **rng);
+    queue.extend(moves.into_iter().map(|m| (m, MoveSource::Random)));
+}
 
-    let (face, axis, dir) = match rotate_index {
-        0 => (Face::Front, Vec3::Z, 1.),
-        5 => (Face::Back, Vec3::Z, -1.),
-        2 => (Face::Left, Vec3::X, -1.),
-        4 => (Face::Right, Vec3::X, 1.),
-        1 => (Face::Up, Vec3::Y, 1.),
-        3 => (Face::Down, Vec3::Y, -1.),
-        _ => return,
-    };
+// 重放记录时随机转动照常生成，让随机数与原来的运行同步，执行的是记录中的转动
+fn replay_moves(
+    mut rng: Single<&mut Entropy<WyRand>, With<Cube>>,
+    mut scrambler: ResMut<Scrambler>,
+    mut queue: ResMut<MoveQueue>,
+    mut replay: ResMut<ReplayMoves>,
+) {
+    if replay.is_empty() || !queue.is_empty() {
+        return;
+    }
 
-    rotation_state.is_rotating = true;
-    rotation_state.current_face = face;
-    rotation_state.rotation_axis = axis;
-    rotation_state.rotation_direction = dir;
-    rotation_state.progress = 0.;
+    let moves = replay.next_batch(&mut scrambler, &mut **rng);
+    queue.extend(moves);
+}
+
+// 外部指定的转动排在随机转动之前
+fn queue_actions(
+    puzzle: Res<PuzzleKind>,
+    mut actions: EventReader<Action>,
+    mut queue: ResMut<MoveQueue>,
+    mut paused: ResMut<RotationPaused>,
+) {
+    for action in actions.read() {
+        match action {
+            Action::Move { seq } => match puzzle.puzzle().parse_moves(seq) {
+                Ok(moves) => queue.extend(moves.into_iter().map(|m| (m, MoveSource::External))),
+                Err(err) => warn!("{err}"),
+            },
+            Action::Pause => paused.0 = true,
+            Action::Resume => paused.0 = false,
+            _ => {}
+        }
+    }
 }
 
 // 面旋转动画系统
 fn rotate_face(
-    time: Res<Time>,
+    (time, accessibility): (Res<Time>, Res<Accessibility>),
+    puzzle: Res<PuzzleKind>,
     mut state: ResMut<RotationState>,
+    mut queue: ResMut<MoveQueue>,
+    mut finished: EventWriter<MoveFinished>,
+    mut animating: ResMut<Animating>,
     mut query: Query<(&mut Transform, &mut CubePiece)>,
 ) {
+    // 开始下一个转动，空闲后第一帧的时间间隔较长，从下一帧开始计时
     if !state.is_rotating {
+        let Some((next, source)) = queue.pop_front() else {
+            return;
+        };
+        state.is_rotating = true;
+        state.current_move = next;
+        state.current_source = source;
+        state.progress = 0.;
+        animating.0 = true;
         return;
     }
+    animating.0 = true;
 
-    let delta = time.delta_secs(); // 旋转速度
+    let delta = time.delta_secs() * accessibility.turn_speed(); // 旋转速度
     state.progress += delta;
 
-    // 计算旋转中心
-    let center_position = CUBE_PIECE_SIZE * CUBE_PIECE_OFFSET;
-    let center = match state.current_face {
-        Face::Front => Vec3::new(0., 0., center_position),
-        Face::Back => Vec3::new(0., 0., -center_position),
-        Face::Left => Vec3::new(-center_position, 0., 0.),
-        Face::Right => Vec3::new(center_position, 0., 0.),
-        Face::Up => Vec3::new(0., center_position, 0.),
-        Face::Down => Vec3::new(0., -center_position, 0.),
-    };
-
-    // 应用旋转动画
+    // 各层都绕穿过中心的轴转动
+    let puzzle = puzzle.puzzle();
+    let current = state.current_move;
+    let rotation = Quat::from_axis_angle(puzzle.axis(current), delta * puzzle.angle(current));
     for (mut transform, cube_piece) in query.iter_mut() {
-        if is_piece_on_face(&cube_piece, state.current_face) {
-            // 计算相对位置
-            let rel_pos = transform.translation - center;
-
-            // 创建旋转四元数
-            let angle = delta * state.rotation_direction * PI / 2.;
-            let rotation = Quat::from_axis_angle(state.rotation_axis, angle);
-
-            // 更新位置和旋转
-            transform.translation = center + rotation * rel_pos;
+        if puzzle.contains(current, cube_piece.position) {
+            transform.translation = rotation * transform.translation;
             transform.rotate(rotation);
         }
     }
 
     // 完成旋转后更新逻辑坐标
     if state.progress >= 1. {
-        update_cube_positions(&mut query, state.current_face);
+        update_cube_positions(&mut query, puzzle, current);
         state.is_rotating = false;
+        finished.send(MoveFinished(current, state.current_source));
     }
 }
 
 fn cube_texture() -> Image {
     let mut image = Image::new_fill(
         Extent3d {
-            width: 512,
-            height: 512,
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
             ..default()
         },
         TextureDimension::D2,
@@ -348,49 +361,129 @@ fn cube_texture() -> Image {
     image
 }
 
-// 判断方块是否在目标面上
-fn is_piece_on_face(piece: &CubePiece, face: Face) -> bool {
-    match face {
-        Face::Front => piece.z == 1,
-        Face::Back => piece.z == -1,
-        Face::Left => piece.x == -1,
-        Face::Right => piece.x == 1,
-        Face::Up => piece.y == 1,
-        Face::Down => piece.y == -1,
-    }
-}
-
 // 更新立方体逻辑坐标
-fn update_cube_positions(query: &mut Query<(&mut Transform, &mut CubePiece)>, face: Face) {
+fn update_cube_positions(
+    query: &mut Query<(&mut Transform, &mut CubePiece)>,
+    puzzle: &dyn Puzzle,
+    m: Move,
+) {
     for (mut transform, mut cube_piece) in query.iter_mut() {
-        if is_piece_on_face(&cube_piece, face) {
-            // 根据旋转面更新坐标
-            let (x, y, z) = match face {
-                Face::Front => (-cube_piece.y, cube_piece.x, cube_piece.z),
-                Face::Back => (cube_piece.y, -cube_piece.x, cube_piece.z),
-                Face::Left => (cube_piece.x, cube_piece.z, -cube_piece.y),
-                Face::Right => (cube_piece.x, -cube_piece.z, cube_piece.y),
-                Face::Up => (cube_piece.z, cube_piece.y, -cube_piece.x),
-                Face::Down => (-cube_piece.z, cube_piece.y, cube_piece.x),
-            };
-
+        if puzzle.contains(m, cube_piece.position) {
             // 更新逻辑坐标
-            cube_piece.x = x;
-            cube_piece.y = y;
-            cube_piece.z = z;
+            cube_piece.position = puzzle.turn_position(m, cube_piece.position);
+            cube_piece.orientation = (puzzle.rotation(m) * cube_piece.orientation).normalize();
 
             // 重置物理位置
-            transform.translation = Vec3::new(
-                x as f32 * CUBE_PIECE_OFFSET,
-                y as f32 * CUBE_PIECE_OFFSET,
-                z as f32 * CUBE_PIECE_OFFSET,
-            );
-            transform.rotation = Quat::IDENTITY;
+            *transform = puzzle.piece_transform(cube_piece.position, cube_piece.orientation);
         }
     }
 }
 
-fn pos2color(x: i32, y: i32, z: i32) -> Color {
-    let mapper = |i: i32| 0.3 * (i + 1) as f32 + 0.2;
-    Color::srgba(mapper(x), mapper(y), mapper(z), 1.)
+// 按初始位置着色的方块网格，各顶点颜色由坐标决定
+fn gradient_mesh(alpha: f32) -> Mesh {
+    let mut mesh = Mesh::from(Cuboid::from_length(CUBE_PIECE_SIZE));
+    if let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    {
+        let colors: Vec<[f32; 4]> = positions
+            .iter()
+            .map(|[r, g, b]| [(1. - *r) / 2., (1. - *g) / 2., (1. - *b) / 2., alpha])
+            .collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+    mesh
+}
+
+// 按主题创建方块材质
+fn piece_material(theme: &Theme, puzzle: &dyn Puzzle, home: IVec3) -> StandardMaterial {
+    let base_color = match theme.piece.style {
+        PieceStyle::Gradient => theme.piece.color(puzzle.gradient_position(home)),
+        // 贴纸颜色由顶点色决定
+        PieceStyle::Stickers => Color::WHITE,
+    };
+    StandardMaterial {
+        base_color,
+        perceptual_roughness: theme.piece.roughness,
+        metallic: theme.piece.metallic,
+        reflectance: theme.piece.reflectance,
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    }
+}
+
+/// 按主题创建方块网格，为 `None` 时使用共用的渐变网格
+fn piece_mesh(theme: &Theme, puzzle: &dyn Puzzle, home: IVec3) -> Option<Mesh> {
+    let alpha = theme.piece.vertex_alpha;
+    match theme.piece.style {
+        PieceStyle::Gradient => puzzle.gradient_mesh(home, alpha),
+        PieceStyle::Stickers => Some(puzzle.sticker_mesh(home, &theme.sticker, alpha)),
+    }
+}
+
+// 主题变化时更新方块材质与网格，日光变化时只更新材质；
+// 贴纸的颜色来自顶点色，与材质颜色相乘，一起按日光偏暖
+fn apply_theme(
+    (theme, daylight): (Res<CurrentTheme>, Res<Daylight>),
+    puzzle: Res<PuzzleKind>,
+    gradient_mesh: Res<GradientMesh>,
+    mut pieces: Query<(&mut Mesh3d, &MeshMaterial3d<StandardMaterial>, &PieceHome)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut current_style: Local<Option<PieceStyle>>,
+) {
+    let style = theme.piece.style;
+    let style_changed = current_style.replace(style) != Some(style);
+    let puzzle = puzzle.puzzle();
+
+    for (mut mesh, material, home) in pieces.iter_mut() {
+        if let Some(material) = materials.get_mut(material) {
+            *material = piece_material(&theme, puzzle, **home);
+            material.base_color = daylight.tint(material.base_color);
+        }
+        if !theme.is_changed() {
+            continue;
+        }
+
+        match piece_mesh(&theme, puzzle, **home) {
+            None if style_changed => mesh.0 = gradient_mesh.0.clone(),
+            None => (),
+            Some(new_mesh) if style_changed => mesh.0 = meshes.add(new_mesh),
+            Some(new_mesh) => {
+                if let Some(mesh) = meshes.get_mut(&*mesh) {
+                    *mesh = new_mesh;
+                }
+            }
+        }
+    }
+
+    if !theme.is_changed() {
+        return;
+    }
+    if let Some(VertexAttributeValues::Float32x4(colors)) = meshes
+        .get_mut(&gradient_mesh.0)
+        .and_then(|mesh| mesh.attribute_mut(Mesh::ATTRIBUTE_COLOR))
+    {
+        for color in colors.iter_mut() {
+            color[3] = theme.piece.vertex_alpha;
+        }
+    }
+}
+
+#[test]
+fn rotation_of_cube_degenerate() {
+    // 角已经背向相机或正对相机时，旋转轴退化
+    let away = ViewAngle::Corner.away_from_camera();
+    assert!(away.abs_diff_eq(LOCAL_CORNER.normalize(), 1e-6));
+    for view_dir in [away * 10., -away * 10.] {
+        let rotation = rotation_of_cube(view_dir, ViewAngle::Corner);
+        assert!(rotation.is_finite());
+        assert!((rotation * away).abs_diff_eq(view_dir.normalize(), 1e-5));
+    }
+    assert!(rotation_of_cube(away, ViewAngle::Corner).abs_diff_eq(Quat::IDENTITY, 1e-6));
+
+    let face = rotation_of_cube(Vec3::NEG_Z, ViewAngle::Face);
+    assert!(face.abs_diff_eq(Quat::IDENTITY, 1e-6));
+    let euler = rotation_of_cube(Vec3::NEG_Z, ViewAngle::Euler(Vec3::new(0., 0., 1.)));
+    assert!(euler.abs_diff_eq(Quat::from_rotation_z(1.), 1e-6));
+    assert!(rotation_of_cube(Vec3::ZERO, ViewAngle::Edge).is_finite());
 }
@@ -0,0 +1,146 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{
+    graphics::accessibility::Accessibility,
+    power::{Animating, ScreenInactive},
+};
+
+use super::{set_cube_position, Cube, CUBE_SIZE};
+
+/// 自转一周的时长（秒）
+const SPIN_PERIOD: f32 = 120.;
+/// 上下浮动的周期（秒）与幅度（相对魔方边长）
+const BOB_PERIOD: f32 = 6.;
+const BOB_HEIGHT: f32 = 0.03;
+/// 呼吸缩放的周期（秒）与幅度
+const BREATHE_PERIOD: f32 = 4.;
+const BREATHE_SCALE: f32 = 0.02;
+
+/// 整个魔方的环境动画，叠加在 `set_cube_position` 确定的基础姿态上
+///
+/// `TIME_FLY_AMBIENT` 为逗号分隔的 `spin`（绕朝向相机的对称轴缓慢自转）、`bob`（上下浮动）、
+/// `breathe`（呼吸般缩放），或 `all`、`off`（默认）；`TIME_FLY_AMBIENT_SPEED` 为速度倍数，默认 1。
+/// 默认关闭，开启后重绘一直保持在动画帧率；减少动态效果时不生效
+pub struct AmbientPlugin;
+
+impl Plugin for AmbientPlugin {
+    fn build(&self, app: &mut App) {
+        let mut ambient = match std::env::var("TIME_FLY_AMBIENT") {
+            Ok(value) => Ambient::parse(&value).unwrap_or_else(|| {
+                warn!("invalid TIME_FLY_AMBIENT `{value}`, using `off`");
+                Ambient::default()
+            }),
+            Err(_) => Ambient::default(),
+        };
+        if let Ok(value) = std::env::var("TIME_FLY_AMBIENT_SPEED") {
+            match value.trim().parse::<f32>() {
+                Ok(speed) if speed.is_finite() && speed > 0. => ambient.speed = speed,
+                _ => warn!("invalid TIME_FLY_AMBIENT_SPEED `{value}`, using 1"),
+            }
+        }
+
+        app.insert_resource(ambient).add_systems(
+            Update,
+            animate.after(set_cube_position).run_if(
+                |ambient: Res<Ambient>,
+                 accessibility: Res<Accessibility>,
+                 screen: Res<ScreenInactive>| {
+                    ambient.enabled() && !accessibility.reduced_motion && !screen.0
+                },
+            ),
+        );
+    }
+}
+
+/// 开启的环境动画
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Ambient {
+    pub spin: bool,
+    pub bob: bool,
+    pub breathe: bool,
+    pub speed: f32,
+}
+
+impl Default for Ambient {
+    fn default() -> Self {
+        Self {
+            spin: false,
+            bob: false,
+            breathe: false,
+            speed: 1.,
+        }
+    }
+}
+
+impl Ambient {
+    fn parse(value: &str) -> Option<Self> {
+        let mut ambient = Self::default();
+        for part in value.split(',').map(str::trim) {
+            match part {
+                "off" | "" => {}
+                "all" => {
+                    ambient.spin = true;
+                    ambient.bob = true;
+                    ambient.breathe = true;
+                }
+                "spin" => ambient.spin = true,
+                "bob" => ambient.bob = true,
+                "breathe" => ambient.breathe = true,
+                _ => return None,
+            }
+        }
+        Some(ambient)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.spin || self.bob || self.breathe
+    }
+}
+
+/// 魔方不含环境动画的姿态，由 `set_cube_position` 设置
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BasePose {
+    pub transform: Transform,
+    /// 朝向相机的对称轴（世界坐标）
+    pub axis: Vec3,
+    /// 相机的上方
+    pub up: Vec3,
+}
+
+fn animate(
+    ambient: Res<Ambient>,
+    time: Res<Time>,
+    mut animating: ResMut<Animating>,
+    cube: Single<(&mut Transform, &BasePose), With<Cube>>,
+) {
+    let (mut transform, pose) = cube.into_inner();
+    // 锁屏时不运行，虚拟时间也已暂停，恢复后从原来的位置继续
+    let elapsed = time.elapsed_secs() * ambient.speed;
+    let wave = |period: f32| (elapsed * TAU / period).sin();
+
+    let mut animated = pose.transform;
+    if ambient.spin {
+        let spin = Quat::from_axis_angle(pose.axis, elapsed * TAU / SPIN_PERIOD);
+        animated.rotation = spin * animated.rotation;
+    }
+    if ambient.bob {
+        animated.translation +=
+            pose.up * CUBE_SIZE * animated.scale.y * BOB_HEIGHT * wave(BOB_PERIOD);
+    }
+    if ambient.breathe {
+        animated.scale *= 1. + BREATHE_SCALE * wave(BREATHE_PERIOD);
+    }
+    *transform = animated;
+    animating.0 = true;
+}
+
+#[test]
+fn parse_ambient() {
+    let ambient = Ambient::parse("spin, breathe").unwrap();
+    assert!(ambient.spin && ambient.breathe && !ambient.bob);
+    assert!(Ambient::parse("all").unwrap().bob);
+    assert!(!Ambient::parse("off").unwrap().enabled());
+    assert_eq!(Ambient::parse("wobble"), None);
+}
@@ -0,0 +1,224 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    window::{Monitor, PrimaryMonitor, PrimaryWindow},
+    winit::{EventLoopProxyWrapper, WakeUp},
+};
+
+use crate::{
+    graphics::{accessibility::Accessibility, daylight::Daylight, theme::CurrentTheme},
+    power::Animating,
+};
+
+use super::{apply_theme, Cube, CUBE_SIZE};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use self::linux as platform;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use bevy::math::Vec2;
+
+    pub struct CursorWatcher;
+
+    impl CursorWatcher {
+        pub fn new() -> Option<Self> {
+            None
+        }
+
+        pub fn position(&mut self) -> Option<Vec2> {
+            None
+        }
+    }
+}
+
+/// 默认的靠近距离（物理像素）
+const DEFAULT_RADIUS: f32 = 32.;
+/// 查询光标位置的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// 淡出与恢复的时长（秒）
+const FADE_SECS: f32 = 0.25;
+
+/// 光标靠近时淡出魔方，离开后恢复
+///
+/// 后台线程轮询全局光标位置，窗口始终不接收鼠标事件；
+/// `TIME_FLY_AUTOHIDE` 为光标距魔方屏幕范围多少像素内淡出（默认 32），设为 `off` 关闭。
+/// Wayland 下无法获取全局光标位置，不会自动隐藏
+pub struct AutoHidePlugin;
+
+impl Plugin for AutoHidePlugin {
+    fn build(&self, app: &mut App) {
+        let radius = match std::env::var("TIME_FLY_AUTOHIDE") {
+            Ok(value) if value.trim() == "off" => return,
+            Ok(value) => match value.trim().parse() {
+                Ok(radius) if radius >= 0. => radius,
+                _ => {
+                    warn!("invalid TIME_FLY_AUTOHIDE `{value}`, using {DEFAULT_RADIUS}");
+                    DEFAULT_RADIUS
+                }
+            },
+            Err(_) => DEFAULT_RADIUS,
+        };
+
+        app.insert_resource(CursorZone {
+            radius,
+            bounds: default(),
+            near: default(),
+        })
+        .insert_resource(Opacity(1.))
+        .add_systems(Startup, watch_cursor)
+        .add_systems(Update, (update_bounds, fade.after(apply_theme)));
+    }
+}
+
+/// 魔方在屏幕上的范围与光标是否靠近，与轮询线程共享
+#[derive(Resource)]
+struct CursorZone {
+    radius: f32,
+    /// 屏幕坐标（物理像素）
+    bounds: Arc<Mutex<Option<Rect>>>,
+    near: Arc<AtomicBool>,
+}
+
+/// 魔方当前的不透明度
+#[derive(Resource)]
+struct Opacity(f32);
+
+fn watch_cursor(zone: Res<CursorZone>, proxy: Option<Res<EventLoopProxyWrapper<WakeUp>>>) {
+    let (bounds, near, radius) = (zone.bounds.clone(), zone.near.clone(), zone.radius);
+    let proxy = proxy.map(|proxy| (**proxy).clone());
+    let spawned = std::thread::Builder::new()
+        .name("cursor-watcher".into())
+        .spawn(move || {
+            let Some(mut cursor) = platform::CursorWatcher::new() else {
+                info!("global cursor position unavailable, auto-hide disabled");
+                return;
+            };
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let Some(position) = cursor.position() else {
+                    continue;
+                };
+                let inside = bounds
+                    .lock()
+                    .ok()
+                    .and_then(|bounds| *bounds)
+                    .is_some_and(|bounds| distance(bounds, position) <= radius);
+                if near.swap(inside, Ordering::Relaxed) != inside {
+                    // 空闲时事件循环要等到下一次唤醒才会处理
+                    if let Some(proxy) = &proxy {
+                        let _ = proxy.send_event(WakeUp);
+                    }
+                }
+            }
+        });
+    if let Err(err) = spawned {
+        warn!("failed to watch cursor: {err}");
+    }
+}
+
+/// 点到矩形的距离，在矩形内为 0
+fn distance(rect: Rect, point: Vec2) -> f32 {
+    (rect.min - point)
+        .max(point - rect.max)
+        .max(Vec2::ZERO)
+        .length()
+}
+
+/// 将魔方包围盒的八个角投影到屏幕上
+fn update_bounds(
+    zone: Res<CursorZone>,
+    cube: Single<&GlobalTransform, With<Cube>>,
+    camera: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    monitor: Single<&Monitor, With<PrimaryMonitor>>,
+) {
+    let (camera, camera_transform) = *camera;
+    let origin = match window.position {
+        WindowPosition::At(position) => position.as_vec2(),
+        // 尚未收到移动事件时按居中计算
+        _ => {
+            let monitor_size = UVec2::new(monitor.physical_width, monitor.physical_height);
+            monitor.physical_position.as_vec2()
+                + (monitor_size.as_vec2() - window.physical_size().as_vec2()) / 2.
+        }
+    };
+
+    let mut bounds: Option<Rect> = None;
+    for i in 0..8 {
+        let sign = |bit: i32| if i & bit == 0 { -1. } else { 1. };
+        let corner = Vec3::new(sign(1), sign(2), sign(4)) * CUBE_SIZE / 2.;
+        let Ok(point) = camera.world_to_viewport(camera_transform, cube.transform_point(corner))
+        else {
+            return;
+        };
+        let point = origin + point * window.scale_factor();
+        bounds = Some(bounds.map_or(Rect::from_corners(point, point), |bounds| {
+            bounds.union_point(point)
+        }));
+    }
+    if let Ok(mut shared) = zone.bounds.lock() {
+        *shared = bounds;
+    }
+}
+
+fn fade(
+    zone: Res<CursorZone>,
+    (time, accessibility): (Res<Time>, Res<Accessibility>),
+    (theme, daylight): (Res<CurrentTheme>, Res<Daylight>),
+    mut opacity: ResMut<Opacity>,
+    mut animating: ResMut<Animating>,
+    handles: Query<&MeshMaterial3d<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let target = if zone.near.load(Ordering::Relaxed) {
+        0.
+    } else {
+        1.
+    };
+    let previous = opacity.0;
+    // 减少动态效果时直接隐藏或恢复
+    let step = if accessibility.reduced_motion {
+        1.
+    } else {
+        time.delta_secs() / FADE_SECS
+    };
+    opacity.0 = if target > previous {
+        (previous + step).min(target)
+    } else {
+        (previous - step).max(target)
+    };
+    if opacity.0 != target {
+        animating.0 = true;
+    }
+    // 切换主题与日光变化会重建材质，需要重新应用
+    if opacity.0 == previous && !theme.is_changed() && !daylight.is_changed() {
+        return;
+    }
+
+    for handle in &handles {
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color.set_alpha(opacity.0);
+        }
+    }
+}
+
+#[test]
+fn distance_to_bounds() {
+    let rect = Rect::new(10., 10., 20., 20.);
+    assert_eq!(distance(rect, Vec2::new(15., 12.)), 0.);
+    assert_eq!(distance(rect, Vec2::new(25., 15.)), 5.);
+    assert_eq!(distance(rect, Vec2::new(23., 24.)), 5.);
+}
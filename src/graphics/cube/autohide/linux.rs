@@ -0,0 +1,34 @@
+use bevy::math::Vec2;
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{ConnectionExt, Window},
+    rust_connection::RustConnection,
+};
+
+/// 通过 X11 查询根窗口中的光标位置；Wayland 不向应用提供全局光标位置
+pub struct CursorWatcher {
+    connection: RustConnection,
+    root: Window,
+}
+
+impl CursorWatcher {
+    pub fn new() -> Option<Self> {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return None;
+        }
+        std::env::var_os("DISPLAY")?;
+        let (connection, screen) = x11rb::connect(None).ok()?;
+        let root = connection.setup().roots.get(screen)?.root;
+        Some(Self { connection, root })
+    }
+
+    pub fn position(&mut self) -> Option<Vec2> {
+        let reply = self
+            .connection
+            .query_pointer(self.root)
+            .ok()?
+            .reply()
+            .ok()?;
+        Some(Vec2::new(reply.root_x.into(), reply.root_y.into()))
+    }
+}
@@ -0,0 +1,17 @@
+use bevy::math::Vec2;
+use windows::Win32::{Foundation::POINT, UI::WindowsAndMessaging::GetCursorPos};
+
+pub struct CursorWatcher;
+
+impl CursorWatcher {
+    pub fn new() -> Option<Self> {
+        Some(Self)
+    }
+
+    /// 进程声明了 DPI 感知，得到的是物理像素
+    pub fn position(&mut self) -> Option<Vec2> {
+        let mut point = POINT::default();
+        unsafe { GetCursorPos(&mut point) }.ok()?;
+        Some(Vec2::new(point.x as f32, point.y as f32))
+    }
+}
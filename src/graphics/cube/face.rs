@@ -0,0 +1,378 @@
+use std::{collections::HashMap, f32::consts::PI, time::Duration};
+
+use bevy::{
+    prelude::*,
+    render::{camera::RenderTarget, mesh::Indices},
+    time::common_conditions::on_timer,
+};
+use time::{macros::format_description, OffsetDateTime};
+
+#[cfg(target_os = "windows")]
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+
+#[cfg(target_os = "windows")]
+use crate::ime::{IMEControl, InputMode};
+use crate::{
+    font::FallbackText,
+    graphics::{
+        accessibility::Accessibility, daylight::Daylight, status::StatusList, theme::CurrentTheme,
+        time::TimeSpan,
+    },
+};
+
+use super::{cube_texture, puzzle::PuzzleKind, setup, state::Face, Cube, CUBE_SIZE, TEXT_SIZE};
+use event::{next_event, Events};
+
+mod event;
+
+/// 立方体网格中各面的顺序
+pub(super) const MESH_FACES: [Face; 6] = [
+    Face::Front,
+    Face::Back,
+    Face::Right,
+    Face::Left,
+    Face::Up,
+    Face::Down,
+];
+/// 较长文字（日程）相对报时的字号
+const SMALL_SCALE: f32 = 0.6;
+
+/// 外层立方体每个面显示的内容
+///
+/// `TIME_FLY_FACES=front=time,right=date,up=event,left=ime,back=blank` 指定各面的内容，
+/// 未指定的面显示时间；显示相同内容的面共用一个渲染目标。
+/// 其他谜题的面按 [`Puzzle::face_transform`] 贴合外形，金字塔没有 `front`、`right` 两面
+///
+/// [`Puzzle::face_transform`]: super::puzzle::Puzzle::face_transform
+pub struct FacePlugin;
+
+impl Plugin for FacePlugin {
+    fn build(&self, app: &mut App) {
+        let widgets = std::env::var("TIME_FLY_FACES")
+            .map(|value| FaceWidgets::parse(&value))
+            .unwrap_or_default();
+
+        app.insert_resource(widgets)
+            .insert_resource(Events::from_env())
+            .add_systems(Startup, spawn_faces.after(setup))
+            .add_systems(
+                Update,
+                (
+                    update_date,
+                    update_event,
+                    #[cfg(target_os = "windows")]
+                    query_ime,
+                )
+                    .run_if(on_timer(Duration::from_secs(1))),
+            )
+            .add_systems(
+                Update,
+                apply_theme
+                    .run_if(resource_changed::<CurrentTheme>.or(resource_changed::<Daylight>)),
+            );
+        #[cfg(target_os = "windows")]
+        app.init_resource::<ImeQuery>()
+            .add_systems(Update, update_ime);
+    }
+}
+
+/// 一个面上的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Widget {
+    /// 时间与状态行
+    Time,
+    /// 日期与星期
+    Date,
+    /// 下一个日程
+    Event,
+    /// 输入法中英文状态
+    Ime,
+    /// 不显示
+    Blank,
+}
+
+impl Widget {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "time" => Some(Widget::Time),
+            "date" => Some(Widget::Date),
+            "event" => Some(Widget::Event),
+            "ime" => Some(Widget::Ime),
+            "blank" => Some(Widget::Blank),
+            _ => None,
+        }
+    }
+}
+
+/// 各面的内容，按 [`Face`] 索引
+#[derive(Resource, Debug, Clone, PartialEq)]
+struct FaceWidgets(HashMap<Face, Widget>);
+
+impl Default for FaceWidgets {
+    fn default() -> Self {
+        Self(
+            Face::ALL
+                .into_iter()
+                .map(|face| (face, Widget::Time))
+                .collect(),
+        )
+    }
+}
+
+impl FaceWidgets {
+    fn parse(value: &str) -> Self {
+        let mut widgets = Self::default();
+        for item in value.split(',').filter(|item| !item.trim().is_empty()) {
+            let parsed = item.split_once('=').and_then(|(face, widget)| {
+                Some((face_from_name(face.trim())?, Widget::parse(widget)?))
+            });
+            match parsed {
+                Some((face, widget)) => {
+                    widgets.0.insert(face, widget);
+                }
+                None => warn!("invalid face mapping `{item}`"),
+            }
+        }
+        widgets
+    }
+
+    fn get(&self, face: Face) -> Widget {
+        self.0.get(&face).copied().unwrap_or(Widget::Time)
+    }
+}
+
+fn face_from_name(name: &str) -> Option<Face> {
+    match name {
+        "front" => Some(Face::Front),
+        "back" => Some(Face::Back),
+        "left" => Some(Face::Left),
+        "right" => Some(Face::Right),
+        "up" => Some(Face::Up),
+        "down" => Some(Face::Down),
+        _ => None,
+    }
+}
+
+/// 日期、日程、输入法等文字，颜色随主题变化
+#[derive(Component)]
+#[require(FallbackText)]
+struct WidgetText;
+
+#[derive(Component)]
+struct DateText;
+
+#[derive(Component)]
+struct EventText;
+
+#[derive(Component)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct ImeText;
+
+fn spawn_faces(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    (widgets, puzzle): (Res<FaceWidgets>, Res<PuzzleKind>),
+    accessibility: Res<Accessibility>,
+    cube: Single<Entity, With<Cube>>,
+) {
+    let mut widget_materials = HashMap::new();
+    for (index, face) in MESH_FACES.into_iter().enumerate() {
+        let widget = widgets.get(face);
+        // 金字塔只有四个面
+        let Some(transform) = puzzle.puzzle().face_transform(face) else {
+            continue;
+        };
+        if widget == Widget::Blank {
+            continue;
+        }
+
+        let material = widget_materials
+            .entry(widget)
+            .or_insert_with(|| {
+                let texture = images.add(cube_texture());
+                spawn_widget(&mut commands, widget, texture.clone(), &accessibility);
+                materials.add(StandardMaterial {
+                    base_color_texture: Some(texture),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })
+            })
+            .clone();
+
+        commands.entity(*cube).with_child((
+            Mesh3d(meshes.add(face_mesh(index))),
+            MeshMaterial3d(material),
+            transform,
+        ));
+    }
+}
+
+/// 外层立方体的一个面，保留 [`Cuboid`] 的纹理坐标
+pub(super) fn face_mesh(index: usize) -> Mesh {
+    let indices = [0, 1, 2, 2, 3, 0].map(|i| (index * 4 + i) as u32);
+    Mesh::from(Cuboid::from_length(CUBE_SIZE)).with_inserted_indices(Indices::U32(indices.into()))
+}
+
+/// 渲染到纹理的界面
+fn spawn_widget(
+    commands: &mut Commands,
+    widget: Widget,
+    texture: Handle<Image>,
+    accessibility: &Accessibility,
+) {
+    let text_size = TEXT_SIZE * accessibility.text_scale();
+    let camera = commands
+        .spawn((
+            Camera2d,
+            Camera {
+                target: RenderTarget::Image(texture),
+                ..default()
+            },
+        ))
+        .id();
+
+    commands
+        .spawn((
+            Node {
+                // Cover the whole image
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::NONE),
+            TargetCamera(camera),
+        ))
+        .with_children(|parent| {
+            // 文字与其下方的状态行一起旋转
+            let mut content = parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                Transform::default().with_rotation(Quat::from_rotation_z(PI / 4.)),
+            ));
+            let font = |scale: f32| TextFont {
+                font_size: text_size * scale,
+                ..default()
+            };
+            content.with_children(|parent| match widget {
+                Widget::Time => {
+                    parent.spawn((TimeSpan, font(1.)));
+                    parent.spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        StatusList {
+                            font_size: text_size,
+                        },
+                    ));
+                }
+                Widget::Date => {
+                    parent.spawn((WidgetText, DateText, font(1.)));
+                }
+                Widget::Event => {
+                    parent.spawn((WidgetText, EventText, font(SMALL_SCALE)));
+                }
+                Widget::Ime => {
+                    parent.spawn((WidgetText, ImeText, FallbackText("—".into()), font(1.)));
+                }
+                Widget::Blank => {}
+            });
+        });
+}
+
+fn set_text(text: &mut FallbackText, value: String) {
+    if text.0 != value {
+        text.0 = value;
+    }
+}
+
+fn update_date(mut texts: Query<&mut FallbackText, With<DateText>>) {
+    let Ok(now) = OffsetDateTime::now_local() else {
+        return;
+    };
+    let Ok(date) = now.format(format_description!("[month]-[day] [weekday repr:short]")) else {
+        return;
+    };
+    for mut text in texts.iter_mut() {
+        set_text(&mut text, date.clone());
+    }
+}
+
+fn update_event(mut events: ResMut<Events>, mut texts: Query<&mut FallbackText, With<EventText>>) {
+    if texts.is_empty() {
+        return;
+    }
+    let Ok(now) = OffsetDateTime::now_local() else {
+        return;
+    };
+    events.reload();
+    let value = next_event(&events.entries, now).unwrap_or_else(|| "—".into());
+    for mut text in texts.iter_mut() {
+        set_text(&mut text, value.clone());
+    }
+}
+
+/// 正在后台进行的输入法查询
+#[cfg(target_os = "windows")]
+#[derive(Resource, Default)]
+struct ImeQuery(Option<Task<&'static str>>);
+
+/// 查询要等待输入法窗口响应，放到后台，上一次还没有结束时跳过
+#[cfg(target_os = "windows")]
+fn query_ime(mut query: ResMut<ImeQuery>, texts: Query<(), With<ImeText>>) {
+    if texts.is_empty() || query.0.is_some() {
+        return;
+    }
+    let task = AsyncComputeTaskPool::get().spawn(async {
+        match IMEControl::new(500, false).get_input_mode() {
+            Ok(response) if response.is_cn => "中",
+            Ok(_) => "EN",
+            Err(_) => "—",
+        }
+    });
+    query.0 = Some(task);
+}
+
+#[cfg(target_os = "windows")]
+fn update_ime(mut query: ResMut<ImeQuery>, mut texts: Query<&mut FallbackText, With<ImeText>>) {
+    let Some(task) = &mut query.0 else {
+        return;
+    };
+    let Some(value) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    query.0 = None;
+    for mut text in texts.iter_mut() {
+        set_text(&mut text, value.to_string());
+    }
+}
+
+fn apply_theme(
+    theme: Res<CurrentTheme>,
+    daylight: Res<Daylight>,
+    mut colors: Query<&mut TextColor, With<WidgetText>>,
+) {
+    let text_color = daylight.tint(theme.text.color);
+    for mut color in colors.iter_mut() {
+        color.0 = text_color;
+    }
+}
+
+#[test]
+fn parse_face_widgets() {
+    let widgets = FaceWidgets::parse("front=date, up=ime,left=nothing,back=blank");
+    assert_eq!(widgets.get(Face::Front), Widget::Date);
+    assert_eq!(widgets.get(Face::Up), Widget::Ime);
+    assert_eq!(widgets.get(Face::Left), Widget::Time);
+    assert_eq!(widgets.get(Face::Back), Widget::Blank);
+    assert_eq!(widgets.get(Face::Right), Widget::Time);
+}
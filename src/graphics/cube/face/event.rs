@@ -0,0 +1,139 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use time::{macros::format_description, Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+/// 日程文件 `<配置目录>/time-fly/events.txt`，可由 `TIME_FLY_EVENTS_FILE` 指定
+///
+/// 每行一个日程：`2026-10-18 14:00 评审` 为单次日程，`09:30 站会` 为每天重复，`#` 开头为注释
+#[derive(Resource, Default)]
+pub struct Events {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    pub entries: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// 为空时每天重复
+    date: Option<Date>,
+    time: Time,
+    title: String,
+}
+
+impl Events {
+    pub fn from_env() -> Self {
+        let path = std::env::var_os("TIME_FLY_EVENTS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| dirs::config_dir().map(|dir| dir.join("time-fly").join("events.txt")));
+        Self { path, ..default() }
+    }
+
+    /// 文件修改后重新读取
+    pub fn reload(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let modified = std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok();
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        self.entries = read_events(path);
+    }
+}
+
+fn read_events(path: &Path) -> Vec<Event> {
+    let Ok(source) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let event = parse_event(line);
+            if event.is_none() {
+                warn!("invalid event `{line}` in {path:?}");
+            }
+            event
+        })
+        .collect()
+}
+
+fn parse_event(line: &str) -> Option<Event> {
+    let (first, rest) = line.split_once(char::is_whitespace)?;
+    let (date, rest) = match Date::parse(first, format_description!("[year]-[month]-[day]")) {
+        Ok(date) => (Some(date), rest.trim_start()),
+        Err(_) => (None, line),
+    };
+    let (time, title) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let time = Time::parse(time, format_description!("[hour]:[minute]")).ok()?;
+    Some(Event {
+        date,
+        time,
+        title: title.trim().to_string(),
+    })
+}
+
+/// 此刻之后最近的日程，不在今天时带上日期
+pub fn next_event(events: &[Event], now: OffsetDateTime) -> Option<String> {
+    let now = PrimitiveDateTime::new(now.date(), now.time());
+    let (at, event) = events
+        .iter()
+        .filter_map(|event| {
+            let at = match event.date {
+                Some(date) => date.with_time(event.time),
+                None => {
+                    let today = now.date().with_time(event.time);
+                    if today > now {
+                        today
+                    } else {
+                        now.date().next_day()?.with_time(event.time)
+                    }
+                }
+            };
+            (at > now).then_some((at, event))
+        })
+        .min_by_key(|(at, _)| *at)?;
+
+    let time = at.format(format_description!("[hour]:[minute]")).ok()?;
+    let text = if at.date() == now.date() {
+        format!("{time} {}", event.title)
+    } else {
+        let date = at.format(format_description!("[month]-[day]")).ok()?;
+        format!("{date} {time} {}", event.title)
+    };
+    Some(text.trim_end().to_string())
+}
+
+#[test]
+fn next_event_after_now() {
+    use time::macros::datetime;
+
+    let events: Vec<Event> = [
+        "09:30 站会",
+        "2026-10-18 14:00 评审",
+        "2026-10-17 10:00 过期",
+    ]
+    .into_iter()
+    .filter_map(parse_event)
+    .collect();
+    assert_eq!(events.len(), 3);
+
+    let morning = datetime!(2026-10-18 08:00 UTC);
+    assert_eq!(next_event(&events, morning).as_deref(), Some("09:30 站会"));
+    let noon = datetime!(2026-10-18 12:00 UTC);
+    assert_eq!(next_event(&events, noon).as_deref(), Some("14:00 评审"));
+    let evening = datetime!(2026-10-18 20:00 UTC);
+    assert_eq!(
+        next_event(&events, evening).as_deref(),
+        Some("10-19 09:30 站会")
+    );
+    assert_eq!(parse_event("soon 站会"), None);
+}
@@ -0,0 +1,264 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    puzzle::PuzzleKind,
+    replay::{MoveQueue, MoveSource},
+    state::{CubeState, Order, PieceState},
+    CubePiece, PieceHome, RotationState,
+};
+use crate::graphics::time::ClockMode;
+
+/// 定期保存的间隔，避免崩溃时丢失状态
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 保存与恢复魔方状态、待执行的转动和显示模式，需要在 `TimePlugin` 与 `ReplayPlugin` 之前、
+/// 插入 [`PuzzleKind`] 之后加入
+///
+/// 退出时及每 30 秒写入 `<数据目录>/time-fly/state.json`，启动时读取，谜题或阶数不同、块的位置不合理时不恢复；
+/// 重放转动记录（`TIME_FLY_REPLAY`）时从记录的初始状态开始，不读取保存的状态
+pub struct PersistPlugin;
+
+impl Plugin for PersistPlugin {
+    fn build(&self, app: &mut App) {
+        let puzzle = *app.world().resource::<PuzzleKind>();
+        let saved = if std::env::var_os("TIME_FLY_REPLAY").is_some() {
+            None
+        } else {
+            state_path().and_then(|path| load(&path, puzzle))
+        };
+        let cube = match saved {
+            Some((cube, queue, mode)) => {
+                app.insert_resource(queue).insert_resource(mode);
+                cube
+            }
+            None => CubeState::solved_with(puzzle),
+        };
+
+        app.insert_resource(InitialState(cube))
+            .add_systems(Update, save.run_if(on_timer(SAVE_INTERVAL)))
+            .add_systems(Last, save.run_if(on_event::<AppExit>));
+    }
+}
+
+/// 启动时魔方的逻辑状态
+#[derive(Resource)]
+pub struct InitialState(pub CubeState);
+
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    /// 谜题名称，旧文件中没有该字段，均为魔方
+    #[serde(default = "default_puzzle")]
+    puzzle: String,
+    /// 魔方阶数，旧文件中没有该字段，均为三阶
+    #[serde(default = "default_order")]
+    order: u32,
+    pieces: Vec<SavedPiece>,
+    /// 待执行的转动，正在进行的转动排在最前
+    queue: String,
+    mode: ClockMode,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPiece {
+    home: [i32; 3],
+    position: [i32; 3],
+    orientation: [f32; 4],
+}
+
+fn default_puzzle() -> String {
+    PuzzleKind::default().id().to_string()
+}
+
+fn default_order() -> u32 {
+    Order::default().get()
+}
+
+fn state_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("time-fly").join("state.json"))
+}
+
+/// 读取保存的状态，块的位置不合理时忽略整个文件，从还原状态开始
+fn load(path: &Path, puzzle: PuzzleKind) -> Option<(CubeState, MoveQueue, ClockMode)> {
+    let source = std::fs::read_to_string(path).ok()?;
+    let saved: SavedState = serde_json::from_str(&source)
+        .inspect_err(|err| warn!("failed to parse {path:?}: {err}"))
+        .ok()?;
+    let saved_puzzle =
+        Order::new(saved.order).and_then(|order| PuzzleKind::from_id(&saved.puzzle, order));
+    if saved_puzzle != Some(puzzle) {
+        info!(
+            "ignoring {path:?}: saved {} (order {}), configured {}",
+            saved.puzzle,
+            saved.order,
+            puzzle.puzzle().name()
+        );
+        return None;
+    }
+
+    let cube = CubeState {
+        puzzle,
+        pieces: saved
+            .pieces
+            .iter()
+            .map(|piece| PieceState {
+                home: IVec3::from_array(piece.home),
+                position: IVec3::from_array(piece.position),
+                orientation: Quat::from_array(piece.orientation).normalize(),
+            })
+            .collect(),
+    };
+    if let Err(err) = check_pieces(&cube) {
+        warn!("ignoring {path:?}: {err}");
+        return None;
+    }
+    let queue = puzzle
+        .puzzle()
+        .parse_moves(&saved.queue)
+        .inspect_err(|err| warn!("ignoring saved moves: {err}"))
+        .unwrap_or_default();
+    // 恢复的转动不再消耗随机数，记录为外部转动
+    let queue = queue.into_iter().map(|m| (m, MoveSource::External));
+    Some((cube, MoveQueue(queue.collect()), saved.mode))
+}
+
+/// 块的初始位置需要与还原状态的相同，当前位置是这些位置的一个排列
+fn check_pieces(cube: &CubeState) -> Result<(), &'static str> {
+    let mut expected: Vec<[i32; 3]> = CubeState::solved_with(cube.puzzle)
+        .pieces
+        .iter()
+        .map(|piece| piece.home.to_array())
+        .collect();
+    expected.sort_unstable();
+    let sorted = |position: fn(&PieceState) -> IVec3| {
+        let mut positions: Vec<[i32; 3]> = cube
+            .pieces
+            .iter()
+            .map(|piece| position(piece).to_array())
+            .collect();
+        positions.sort_unstable();
+        positions
+    };
+    if sorted(|piece| piece.home) != expected {
+        return Err("piece homes do not match the puzzle");
+    }
+    if sorted(|piece| piece.position) != expected {
+        return Err("piece positions are not a permutation of the homes");
+    }
+    Ok(())
+}
+
+fn save(
+    puzzle: Res<PuzzleKind>,
+    pieces: Query<(&CubePiece, &PieceHome)>,
+    queue: Res<MoveQueue>,
+    rotation: Res<RotationState>,
+    mode: Res<ClockMode>,
+) {
+    let Some(path) = state_path() else {
+        return;
+    };
+
+    // 转动完成前逻辑坐标不变，正在进行的转动需要重新执行
+    let current = rotation.is_rotating.then_some(rotation.current_move);
+    let queue: Vec<String> = current
+        .iter()
+        .chain(queue.iter().map(|(m, _)| m))
+        .map(|m| puzzle.puzzle().format_move(*m))
+        .collect();
+    let order = match *puzzle {
+        PuzzleKind::Cube(order) => order,
+        _ => Order::default(),
+    };
+    let saved = SavedState {
+        puzzle: puzzle.id().to_string(),
+        order: order.get(),
+        pieces: pieces
+            .iter()
+            .map(|(piece, home)| SavedPiece {
+                home: home.to_array(),
+                position: piece.position.to_array(),
+                orientation: piece.orientation.to_array(),
+            })
+            .collect(),
+        queue: queue.join(" "),
+        mode: *mode,
+    };
+
+    // 先写入临时文件再替换，避免写到一半时退出
+    let temp = path.with_extension("json.tmp");
+    let result = std::fs::create_dir_all(path.parent().unwrap_or(&path))
+        .and_then(|_| std::fs::write(&temp, serde_json::to_vec_pretty(&saved)?))
+        .and_then(|_| std::fs::rename(&temp, &path));
+    if let Err(err) = result {
+        warn!("failed to save state to {path:?}: {err}");
+    }
+}
+
+#[test]
+fn saved_state_format() {
+    let saved: SavedState = serde_json::from_str(
+        r#"{"pieces": [], "queue": "R U2", "mode": {"name": "countdown", "end": 100}}"#,
+    )
+    .unwrap();
+    assert_eq!(saved.mode, ClockMode::Countdown { end: 100 });
+    assert_eq!(saved.order, 3);
+    assert_eq!(saved.puzzle, "cube");
+    assert_eq!(super::state::parse_moves(&saved.queue).unwrap().len(), 2);
+    let json = serde_json::to_string(&SavedState {
+        mode: ClockMode::Clock,
+        ..saved
+    })
+    .unwrap();
+    assert!(json.contains(r#""mode":{"name":"clock"}"#));
+}
+
+#[test]
+fn corrupted_state_is_ignored() {
+    let puzzle = PuzzleKind::default();
+    let solved = CubeState::solved_with(puzzle);
+    let path = std::env::temp_dir().join(format!("time-fly-state-{}.json", std::process::id()));
+    let load_with = |edit: fn(&mut Vec<SavedPiece>)| {
+        let mut pieces: Vec<SavedPiece> = solved
+            .pieces
+            .iter()
+            .map(|piece| SavedPiece {
+                home: piece.home.to_array(),
+                position: piece.position.to_array(),
+                orientation: piece.orientation.to_array(),
+            })
+            .collect();
+        edit(&mut pieces);
+        let saved = SavedState {
+            puzzle: puzzle.id().to_string(),
+            order: 3,
+            pieces,
+            queue: "R U".into(),
+            mode: ClockMode::Clock,
+        };
+        std::fs::write(&path, serde_json::to_vec(&saved).unwrap()).unwrap();
+        load(&path, puzzle)
+    };
+
+    // 交换两块仍然合理
+    let (cube, queue, _) = load_with(|pieces| {
+        let position = pieces[0].position;
+        pieces[0].position = pieces[1].position;
+        pieces[1].position = position;
+    })
+    .unwrap();
+    assert_eq!(cube.pieces.len(), solved.pieces.len());
+    assert_eq!(queue.len(), 2);
+    // 两块在同一位置
+    assert!(load_with(|pieces| pieces[1].position = pieces[0].position).is_none());
+    // 不存在的初始位置
+    assert!(load_with(|pieces| pieces[0].home = [5, 5, 5]).is_none());
+    // 缺少块
+    assert!(load_with(|pieces| pieces.truncate(1)).is_none());
+    std::fs::remove_file(&path).unwrap();
+}
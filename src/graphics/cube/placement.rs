@@ -0,0 +1,231 @@
+use bevy::{
+    prelude::*,
+    render::camera::{CameraUpdateSystem, SubCameraView},
+    transform::TransformSystem,
+    window::{Monitor, PrimaryMonitor, PrimaryWindow, WindowResolution},
+};
+
+use super::{Cube, CUBE_SIZE};
+use crate::graphics::view::ViewConfig;
+
+/// 魔方中心到相邻屏幕边缘的距离与魔方外接球投影半径之比
+const MARGIN: f32 = 1.25;
+/// 紧凑窗口在魔方投影范围外留出的比例，容纳泛光
+const PADDING: f32 = 0.15;
+
+/// 魔方所在的屏幕角落与窗口大小
+///
+/// `TIME_FLY_ANCHOR` 为 `bottom-right`（默认）、`bottom-left`、`top-right` 或 `top-left`；
+/// `TIME_FLY_WINDOW=compact` 时窗口只包住魔方，相机只渲染全屏画面中窗口所在的部分，
+/// 外观与默认的全屏透明窗口（`screen`）一致
+pub struct PlacementPlugin;
+
+impl Plugin for PlacementPlugin {
+    fn build(&self, app: &mut App) {
+        let placement = Placement::from_env();
+        app.insert_resource(placement)
+            .add_systems(Startup, setup_window)
+            .add_systems(PostStartup, frame_camera.before(CameraUpdateSystem));
+        if placement.compact {
+            // 魔方在第一帧放好，之后按它的投影收紧窗口
+            app.add_systems(
+                PostUpdate,
+                fit_window
+                    .after(TransformSystem::TransformPropagate)
+                    .run_if(run_once),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Corner {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "top-left" => Some(Corner::TopLeft),
+            "top-right" => Some(Corner::TopRight),
+            "bottom-left" => Some(Corner::BottomLeft),
+            "bottom-right" => Some(Corner::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Placement {
+    pub anchor: Corner,
+    /// 窗口只包住魔方
+    pub compact: bool,
+    /// 窗口在显示器上覆盖的范围（物理像素）
+    view: Rect,
+}
+
+impl Placement {
+    pub fn from_env() -> Self {
+        let anchor = match std::env::var("TIME_FLY_ANCHOR") {
+            Ok(value) => Corner::parse(&value).unwrap_or_else(|| {
+                warn!("unknown TIME_FLY_ANCHOR `{value}`, using `bottom-right`");
+                Corner::default()
+            }),
+            Err(_) => Corner::default(),
+        };
+        let compact = match std::env::var("TIME_FLY_WINDOW") {
+            Ok(value) if value.trim() == "compact" => true,
+            Ok(value) if value.trim() == "screen" => false,
+            Ok(value) => {
+                warn!("unknown TIME_FLY_WINDOW `{value}`, using `screen`");
+                false
+            }
+            Err(_) => false,
+        };
+        Self {
+            anchor,
+            compact,
+            view: Rect::default(),
+        }
+    }
+
+    /// 魔方中心在显示器上的位置（物理像素）
+    fn cube_center(&self, monitor: &Monitor, view: &ViewConfig) -> Vec2 {
+        let size = monitor_size(monitor);
+        let margin = margin(monitor, view);
+        let (left, top) = match self.anchor {
+            Corner::TopLeft => (true, true),
+            Corner::TopRight => (false, true),
+            Corner::BottomLeft => (true, false),
+            Corner::BottomRight => (false, false),
+        };
+        Vec2::new(
+            if left { margin } else { size.x - margin },
+            if top { margin } else { size.y - margin },
+        )
+    }
+
+    /// 魔方中心在窗口中的位置（逻辑像素），用于从相机发出射线
+    pub fn cube_viewport_position(&self, monitor: &Monitor, view: &ViewConfig) -> Vec2 {
+        (self.cube_center(monitor, view) - self.view.min) / monitor.scale_factor as f32
+    }
+}
+
+/// 魔方中心到相邻两条屏幕边缘的距离（物理像素），随魔方缩放与屏幕大小变化
+fn margin(monitor: &Monitor, view: &ViewConfig) -> f32 {
+    let scale_factor = monitor.scale_factor as f32;
+    let logical_height = monitor.physical_height as f32 / scale_factor;
+    let radius = CUBE_SIZE * 3f32.sqrt() / 2. * view.scale;
+    radius * view.pixels_per_unit(logical_height) * scale_factor * MARGIN
+}
+
+fn monitor_size(monitor: &Monitor) -> Vec2 {
+    Vec2::new(
+        monitor.physical_width as f32,
+        monitor.physical_height as f32,
+    )
+}
+
+fn setup_window(
+    monitor: Single<&Monitor, With<PrimaryMonitor>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut placement: ResMut<Placement>,
+    view: Res<ViewConfig>,
+) {
+    placement.view = if placement.compact {
+        // 先取以魔方为中心、不超出屏幕的正方形
+        Rect::from_center_half_size(
+            placement.cube_center(&monitor, &view),
+            Vec2::splat(margin(&monitor, &view)),
+        )
+    } else {
+        Rect::from_corners(Vec2::ZERO, monitor_size(&monitor))
+    };
+    resize_window(&mut window, &monitor, &placement);
+}
+
+fn resize_window(window: &mut Window, monitor: &Monitor, placement: &Placement) {
+    let scale_factor = monitor.scale_factor as f32;
+    let view = placement.view;
+    if placement.compact {
+        window.resolution = WindowResolution::new(view.width(), view.height())
+            .with_scale_factor_override(scale_factor);
+        window.position = WindowPosition::At(monitor.physical_position + view.min.as_ivec2());
+    } else {
+        // 完全等于屏幕大小会进入全屏模式，窗口背景变黑
+        window.resolution = WindowResolution::new(view.width() - 0.1, view.height() - 0.1)
+            .with_scale_factor_override(scale_factor);
+        window.position = WindowPosition::Centered(MonitorSelection::Primary);
+    }
+}
+
+/// 紧凑窗口下相机只渲染全屏画面中窗口所在的部分
+fn frame_camera(
+    placement: Res<Placement>,
+    monitor: Single<&Monitor, With<PrimaryMonitor>>,
+    mut cameras: Query<&mut Camera, With<Camera3d>>,
+) {
+    if !placement.compact {
+        return;
+    }
+    for mut camera in &mut cameras {
+        camera.sub_camera_view = Some(sub_view(&monitor, placement.view));
+    }
+}
+
+fn sub_view(monitor: &Monitor, view: Rect) -> SubCameraView {
+    SubCameraView {
+        full_size: UVec2::new(monitor.physical_width, monitor.physical_height),
+        offset: view.min,
+        size: view.size().as_uvec2(),
+    }
+}
+
+/// 按魔方外接球的投影收紧窗口，转动中的层也不会超出
+fn fit_window(
+    mut placement: ResMut<Placement>,
+    cube: Single<&GlobalTransform, With<Cube>>,
+    camera: Single<(&mut Camera, &GlobalTransform), With<Camera3d>>,
+    monitor: Single<&Monitor, With<PrimaryMonitor>>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+) {
+    let (mut camera, camera_transform) = camera.into_inner();
+    let radius = CUBE_SIZE * 3f32.sqrt() / 2. * cube.scale().max_element();
+    let axes = [
+        camera_transform.right(),
+        camera_transform.up(),
+        camera_transform.back(),
+    ]
+    .map(|axis| *axis * radius);
+
+    // 包住外接球、与相机对齐的立方体的八个角
+    let mut bounds: Option<Rect> = None;
+    for i in 0..8 {
+        let sign = |bit: usize| if i & bit == 0 { -1. } else { 1. };
+        let corner = cube.translation() + axes[0] * sign(1) + axes[1] * sign(2) + axes[2] * sign(4);
+        let Ok(point) = camera.world_to_viewport(camera_transform, corner) else {
+            return;
+        };
+        let point = placement.view.min + point * monitor.scale_factor as f32;
+        bounds = Some(bounds.map_or(Rect::from_corners(point, point), |bounds| {
+            bounds.union_point(point)
+        }));
+    }
+    let Some(bounds) = bounds else {
+        return;
+    };
+
+    let padding = bounds.size() * PADDING;
+    let view = Rect {
+        min: (bounds.min - padding).floor(),
+        max: (bounds.max + padding).ceil(),
+    }
+    .intersect(Rect::from_corners(Vec2::ZERO, monitor_size(&monitor)));
+    placement.view = view;
+    resize_window(&mut window, &monitor, &placement);
+    camera.sub_camera_view = Some(sub_view(&monitor, view));
+}
@@ -0,0 +1,258 @@
+use bevy::prelude::*;
+use rand_core::RngCore;
+
+use super::state::{parse_moves, Face, Move, NotationError, Order, Turn};
+use crate::graphics::theme::StickerTheme;
+use domino::Domino;
+use pyraminx::Pyraminx;
+use skewb::Skewb;
+
+mod cube;
+mod domino;
+mod pyraminx;
+mod skewb;
+
+/// 可以作为时钟本体的转动谜题：几何、转动轴与逻辑状态
+///
+/// 方块的逻辑坐标为整数，所有转动都绕穿过中心的轴进行，把逻辑坐标映射为逻辑坐标；
+/// 谜题的外接球与三阶魔方相同，摆放与自动隐藏不需要区分
+pub trait Puzzle {
+    /// 用于提示的名称
+    fn name(&self) -> String;
+
+    /// 还原状态下各方块的逻辑坐标
+    fn homes(&self) -> Vec<IVec3>;
+
+    /// 能否执行该转动
+    fn supports(&self, m: Move) -> bool;
+
+    /// 转动的旋转轴（局部坐标中的单位向量）
+    fn axis(&self, m: Move) -> Vec3;
+
+    /// 绕 [`Puzzle::axis`] 旋转的角度
+    fn angle(&self, m: Move) -> f32;
+
+    /// 位于 `position` 的方块是否随转动移动
+    fn contains(&self, m: Move, position: IVec3) -> bool;
+
+    /// 随机转动，`history` 为最近两次转动的面
+    fn random_move(&self, history: [Option<Face>; 2], rng: &mut dyn RngCore) -> Move;
+
+    /// 方块在谜题中的变换
+    fn piece_transform(&self, position: IVec3, orientation: Quat) -> Transform;
+
+    /// 渐变外观下决定方块颜色的位置，各分量在 -1 到 1 之间
+    fn gradient_position(&self, home: IVec3) -> Vec3;
+
+    /// 带贴纸的方块网格
+    fn sticker_mesh(&self, home: IVec3, theme: &StickerTheme, alpha: f32) -> Mesh;
+
+    /// 渐变外观下的方块网格，为 `None` 时使用所有方块共用的立方体网格
+    fn gradient_mesh(&self, _home: IVec3, _alpha: f32) -> Option<Mesh> {
+        None
+    }
+
+    /// 把外层立方体的面移到谜题上对应的面，谜题没有该面时为 `None`
+    fn face_transform(&self, _face: Face) -> Option<Transform> {
+        Some(Transform::IDENTITY)
+    }
+
+    /// 转动的记号
+    fn format_move(&self, m: Move) -> String {
+        m.to_string()
+    }
+
+    /// 解析转动序列，并检查每个转动都能执行
+    fn parse_moves(&self, notation: &str) -> Result<Vec<Move>, NotationError> {
+        let moves = parse_moves(notation)?;
+        match moves.iter().find(|m| !self.supports(**m)) {
+            Some(m) => Err(NotationError::Unsupported(
+                self.format_move(*m),
+                self.name(),
+            )),
+            None => Ok(moves),
+        }
+    }
+
+    fn rotation(&self, m: Move) -> Quat {
+        Quat::from_axis_angle(self.axis(m), self.angle(m))
+    }
+
+    /// 转动后方块的逻辑坐标
+    fn turn_position(&self, m: Move, position: IVec3) -> IVec3 {
+        (self.rotation(m) * position.as_vec3()).round().as_ivec3()
+    }
+}
+
+/// 时钟本体
+///
+/// `TIME_FLY_PUZZLE` 为 `cube`（默认）、`domino`（3x3x2 长方体）、`skewb` 或 `pyraminx`；
+/// 魔方的阶数由 `TIME_FLY_CUBE_ORDER` 指定（2 到 7，默认 3），阶数越高方块越小，整体大小不变
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PuzzleKind {
+    Cube(Order),
+    Domino,
+    Skewb,
+    Pyraminx,
+}
+
+impl Default for PuzzleKind {
+    fn default() -> Self {
+        PuzzleKind::Cube(Order::default())
+    }
+}
+
+impl PuzzleKind {
+    pub fn from_env() -> Self {
+        let order = order_from_env();
+        let Ok(value) = std::env::var("TIME_FLY_PUZZLE") else {
+            return PuzzleKind::Cube(order);
+        };
+        Self::from_id(value.trim(), order).unwrap_or_else(|| {
+            warn!("unknown TIME_FLY_PUZZLE `{value}`, using `cube`");
+            PuzzleKind::Cube(order)
+        })
+    }
+
+    /// 由名称与魔方阶数得到谜题，阶数只对魔方有效
+    pub fn from_id(id: &str, order: Order) -> Option<Self> {
+        match id {
+            "cube" => Some(PuzzleKind::Cube(order)),
+            "domino" | "3x3x2" => Some(PuzzleKind::Domino),
+            "skewb" => Some(PuzzleKind::Skewb),
+            "pyraminx" => Some(PuzzleKind::Pyraminx),
+            _ => None,
+        }
+    }
+
+    /// `TIME_FLY_PUZZLE`、存档与转动记录中使用的名称
+    pub fn id(self) -> &'static str {
+        match self {
+            PuzzleKind::Cube(_) => "cube",
+            PuzzleKind::Domino => "domino",
+            PuzzleKind::Skewb => "skewb",
+            PuzzleKind::Pyraminx => "pyraminx",
+        }
+    }
+
+    pub fn puzzle(&self) -> &dyn Puzzle {
+        match self {
+            PuzzleKind::Cube(order) => order,
+            PuzzleKind::Domino => &Domino,
+            PuzzleKind::Skewb => &Skewb,
+            PuzzleKind::Pyraminx => &Pyraminx,
+        }
+    }
+}
+
+fn order_from_env() -> Order {
+    let Ok(value) = std::env::var("TIME_FLY_CUBE_ORDER") else {
+        return Order::default();
+    };
+    value
+        .trim()
+        .parse()
+        .ok()
+        .and_then(Order::new)
+        .unwrap_or_else(|| {
+            warn!(
+                "invalid TIME_FLY_CUBE_ORDER `{value}`, expected {} to {}, using 3",
+                Order::MIN,
+                Order::MAX
+            );
+            Order::default()
+        })
+}
+
+/// 从 `faces` 中随机选取一个面：不转动上一次的面；上两次转动的面可以交换（`commute`）时，
+/// 也不转动与它们可以交换的面，避免转动重复或抵消
+fn random_face(
+    faces: &[Face],
+    history: [Option<Face>; 2],
+    commute: fn(Face, Face) -> bool,
+    rng: &mut dyn RngCore,
+) -> Face {
+    let candidates: Vec<Face> = faces
+        .iter()
+        .copied()
+        .filter(|face| follows(history, *face, commute))
+        .collect();
+    candidates[rng.next_u32() as usize % candidates.len()]
+}
+
+/// `face` 能否接在 `history` 之后转动，规则见 [`random_face`]
+pub(super) fn follows(
+    history: [Option<Face>; 2],
+    face: Face,
+    commute: fn(Face, Face) -> bool,
+) -> bool {
+    match history {
+        [_, None] => true,
+        [Some(first), Some(last)] if commute(first, last) => !commute(face, last),
+        [_, Some(last)] => face != last,
+    }
+}
+
+fn random_turn(turns: &[Turn], rng: &mut dyn RngCore) -> Turn {
+    turns[rng.next_u32() as usize % turns.len()]
+}
+
+#[test]
+fn puzzles_restore() {
+    use super::state::CubeState;
+
+    // 每个谜题执行一段转动后逆序转回，方块都回到原位
+    for (kind, sequence) in [
+        (PuzzleKind::Domino, "U R2 D' 2F2 Uw L2"),
+        (PuzzleKind::Skewb, "R U' L B R' U"),
+        (PuzzleKind::Pyraminx, "U L' r B u' R"),
+    ] {
+        let puzzle = kind.puzzle();
+        let moves = puzzle.parse_moves(sequence).unwrap();
+        let mut state = CubeState::solved_with(kind);
+        for m in &moves {
+            state.apply(*m);
+        }
+        assert_ne!(state, CubeState::solved_with(kind), "{sequence}");
+        for m in moves.iter().rev() {
+            state.apply(m.inverse());
+        }
+        for piece in &state.pieces {
+            assert_eq!(piece.position, piece.home, "{sequence}");
+        }
+    }
+
+    assert!(matches!(
+        PuzzleKind::Domino.puzzle().parse_moves("R"),
+        Err(NotationError::Unsupported(..))
+    ));
+    assert!(PuzzleKind::Skewb.puzzle().parse_moves("F").is_err());
+    let tips = PuzzleKind::Pyraminx.puzzle().parse_moves("u' R").unwrap();
+    let text: Vec<String> = tips
+        .iter()
+        .map(|m| PuzzleKind::Pyraminx.puzzle().format_move(*m))
+        .collect();
+    assert_eq!(text, ["u'", "R"]);
+}
+
+#[test]
+fn random_moves_supported() {
+    use rand_core::SeedableRng;
+
+    let mut rng = bevy_prng::WyRand::seed_from_u64(3);
+    for kind in [
+        PuzzleKind::Cube(Order::new(5).unwrap()),
+        PuzzleKind::Domino,
+        PuzzleKind::Skewb,
+        PuzzleKind::Pyraminx,
+    ] {
+        let puzzle = kind.puzzle();
+        let mut history = [None, None];
+        for _ in 0..200 {
+            let m = puzzle.random_move(history, &mut rng);
+            assert!(puzzle.supports(m), "{m} on {}", puzzle.name());
+            assert_ne!(history[1], Some(m.face));
+            history = [history[1], Some(m.face)];
+        }
+    }
+}
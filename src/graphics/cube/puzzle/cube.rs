@@ -0,0 +1,85 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use rand_core::RngCore;
+
+use super::{random_face, random_turn, Puzzle};
+use crate::graphics::{
+    cube::{
+        state::{Face, Move, Order, Turn},
+        CUBE_PIECE_OFFSET, CUBE_PIECE_SIZE,
+    },
+    sticker::sticker_mesh,
+    theme::StickerTheme,
+};
+
+/// N 阶魔方，只包含外表面上的方块
+impl Puzzle for Order {
+    fn name(&self) -> String {
+        let n = self.get();
+        format!("{n}x{n}x{n} cube")
+    }
+
+    fn homes(&self) -> Vec<IVec3> {
+        let mut homes = Vec::new();
+        for x in self.coords() {
+            for y in self.coords() {
+                for z in self.coords() {
+                    let home = IVec3::new(x, y, z);
+                    if home.abs().max_element() == self.max() {
+                        homes.push(home);
+                    }
+                }
+            }
+        }
+        homes
+    }
+
+    fn supports(&self, m: Move) -> bool {
+        m.depth <= self.get()
+    }
+
+    /// 面的外法线
+    fn axis(&self, m: Move) -> Vec3 {
+        m.face.normal().as_vec3()
+    }
+
+    fn angle(&self, m: Move) -> f32 {
+        -m.turn.steps() as f32 * PI / 2.
+    }
+
+    fn contains(&self, m: Move, position: IVec3) -> bool {
+        let layer = self.layer(position.dot(m.face.normal()));
+        layer == m.depth || (m.wide && layer < m.depth)
+    }
+
+    /// 里侧的一半由相对的面转动，高阶魔方与 WCA 打乱一样混用外层转动与 `Rw`、`3Rw` 等宽层转动
+    fn random_move(&self, history: [Option<Face>; 2], rng: &mut dyn RngCore) -> Move {
+        let face = random_face(&Face::ALL, history, Face::same_axis, rng);
+        let turn = random_turn(&Turn::ALL, rng);
+        // 二、三阶只有外层，不额外取随机数
+        let depths = self.get() / 2;
+        let depth = match depths {
+            0 | 1 => 1,
+            _ => 1 + rng.next_u32() % depths,
+        };
+        Move::new(face, turn).with_layers(depth, depth > 1)
+    }
+
+    /// 阶数越高方块越小，整个魔方的大小不变
+    fn piece_transform(&self, position: IVec3, orientation: Quat) -> Transform {
+        let scale = 3. / self.get() as f32;
+        let translation = position.as_vec3() * self.spacing() * CUBE_PIECE_OFFSET * scale;
+        Transform::from_translation(translation)
+            .with_rotation(orientation)
+            .with_scale(Vec3::splat(scale))
+    }
+
+    fn gradient_position(&self, home: IVec3) -> Vec3 {
+        home.as_vec3() / self.max() as f32
+    }
+
+    fn sticker_mesh(&self, home: IVec3, theme: &StickerTheme, alpha: f32) -> Mesh {
+        sticker_mesh(CUBE_PIECE_SIZE, self.outer(home), theme, alpha)
+    }
+}
@@ -0,0 +1,108 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use rand_core::RngCore;
+
+use super::{random_face, random_turn, Puzzle};
+use crate::graphics::{
+    cube::{
+        state::{Face, Move, Turn},
+        CUBE_PIECE_OFFSET, CUBE_PIECE_SIZE, CUBE_SIZE,
+    },
+    sticker::sticker_mesh,
+    theme::StickerTheme,
+};
+
+/// 上下两层的总高度
+const HEIGHT: f32 = CUBE_PIECE_SIZE + CUBE_PIECE_OFFSET;
+
+/// 3x3x2 长方体：三阶魔方去掉中间一层，`U`、`D` 可以转 90°，侧面截面不是正方形，只能转 180°
+///
+/// 逻辑坐标的 x、z 为 -1..=1，y 为 ±1
+pub struct Domino;
+
+impl Domino {
+    /// 沿该面法线方向的层数
+    fn layers(face: Face) -> u32 {
+        if face.normal().y == 0 {
+            3
+        } else {
+            2
+        }
+    }
+}
+
+impl Puzzle for Domino {
+    fn name(&self) -> String {
+        "3x3x2 cuboid".to_string()
+    }
+
+    fn homes(&self) -> Vec<IVec3> {
+        let mut homes = Vec::new();
+        for x in -1..=1 {
+            for y in [-1, 1] {
+                for z in -1..=1 {
+                    homes.push(IVec3::new(x, y, z));
+                }
+            }
+        }
+        homes
+    }
+
+    fn supports(&self, m: Move) -> bool {
+        m.depth <= Self::layers(m.face) && (m.face.normal().y != 0 || m.turn == Turn::Half)
+    }
+
+    fn axis(&self, m: Move) -> Vec3 {
+        m.face.normal().as_vec3()
+    }
+
+    fn angle(&self, m: Move) -> f32 {
+        -m.turn.steps() as f32 * PI / 2.
+    }
+
+    fn contains(&self, m: Move, position: IVec3) -> bool {
+        let coord = position.dot(m.face.normal());
+        // 上下方向的坐标间隔为 2
+        let layer = match Self::layers(m.face) {
+            2 => (1 - coord) / 2 + 1,
+            _ => 2 - coord,
+        } as u32;
+        layer == m.depth || (m.wide && layer < m.depth)
+    }
+
+    fn random_move(&self, history: [Option<Face>; 2], rng: &mut dyn RngCore) -> Move {
+        let face = random_face(&Face::ALL, history, Face::same_axis, rng);
+        let turn = match face.normal().y {
+            0 => Turn::Half,
+            _ => random_turn(&Turn::ALL, rng),
+        };
+        Move::new(face, turn)
+    }
+
+    fn piece_transform(&self, position: IVec3, orientation: Quat) -> Transform {
+        let translation = position.as_vec3() * Vec3::new(1., 0.5, 1.) * CUBE_PIECE_OFFSET;
+        Transform::from_translation(translation).with_rotation(orientation)
+    }
+
+    fn gradient_position(&self, home: IVec3) -> Vec3 {
+        home.as_vec3()
+    }
+
+    fn sticker_mesh(&self, home: IVec3, theme: &StickerTheme, alpha: f32) -> Mesh {
+        sticker_mesh(CUBE_PIECE_SIZE, home, theme, alpha)
+    }
+
+    /// 上下两面移到实际的高度，侧面缩小到长方体的高度
+    fn face_transform(&self, face: Face) -> Option<Transform> {
+        let normal = face.normal().as_vec3();
+        let transform = if normal.y == 0. {
+            let scale = HEIGHT / CUBE_SIZE;
+            Transform::from_translation(normal * (1. - scale) * CUBE_SIZE / 2.)
+                .with_scale(Vec3::splat(scale))
+        } else {
+            Transform::from_translation(normal * (HEIGHT - CUBE_SIZE) / 2.)
+        };
+        Some(transform)
+    }
+}
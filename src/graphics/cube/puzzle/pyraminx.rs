@@ -0,0 +1,207 @@
+use std::f32::consts::{SQRT_2, TAU};
+
+use bevy::prelude::*;
+use rand_core::RngCore;
+
+use super::{random_face, random_turn, Puzzle};
+use crate::graphics::{
+    cube::{
+        state::{Face, Move, Turn},
+        CUBE_SIZE,
+    },
+    sticker::{gradient_polyhedron_mesh, polyhedron_mesh},
+    theme::StickerTheme,
+};
+
+/// 可以转动的顶点，与 WCA 记号相同；默认视角下 `U` 正对相机
+const VERTICES: [(Face, IVec3); 4] = [
+    (Face::Up, IVec3::new(-1, 1, 1)),
+    (Face::Left, IVec3::NEG_ONE),
+    (Face::Right, IVec3::new(1, -1, 1)),
+    (Face::Back, IVec3::new(1, 1, -1)),
+];
+/// 随机转动顶角的概率的倒数
+const TIP_CHANCE: u32 = 4;
+
+/// 金字塔：每次绕一个顶点转动 120°
+///
+/// 按 WCA 记号，`U`、`L`、`R`、`B` 转动顶点连同下面一层，小写字母只转动顶角；
+/// 四面体的顶点在立方体的四个角上，顶角、中心块、棱块的逻辑坐标分别为顶点的 2 倍、顶点本身与两个顶点之和
+pub struct Pyraminx;
+
+fn vertex(face: Face) -> Option<IVec3> {
+    VERTICES
+        .iter()
+        .find(|(letter, _)| *letter == face)
+        .map(|(_, vertex)| *vertex)
+}
+
+/// 与顶点相对的面，决定贴纸颜色与报时的位置
+///
+/// 默认视角下看到的三个面使用 [`Cuboid`] 中纹理不镜像的 `up`、`left`、`back` 三面
+fn opposite(face: Face) -> Face {
+    match face {
+        Face::Up => Face::Down,
+        Face::Left => Face::Up,
+        Face::Right => Face::Left,
+        _ => Face::Back,
+    }
+}
+
+/// 只转动顶角，通用记号把小写字母解析为 `Uw`
+fn is_tip(m: Move) -> bool {
+    m.depth == 2 && m.wide
+}
+
+/// 方块的顶点
+fn piece_points(home: IVec3) -> Vec<Vec3> {
+    let vertices = VERTICES.map(|(_, vertex)| vertex.as_vec3());
+    let home = home.as_vec3();
+    // 以立方体的半边长为单位，四面体三等分后由小四面体与八面体组成
+    let points: Vec<Vec3> = if home.abs() == Vec3::ONE {
+        // 中心块：顶角下方的八面体
+        let others: Vec<Vec3> = vertices.into_iter().filter(|v| *v != home).collect();
+        others
+            .iter()
+            .map(|other| (2. * home + *other) / 3.)
+            .chain(others.iter().map(|other| -*other / 3.))
+            .collect()
+    } else {
+        // 顶角与棱块：边长为三分之一的小四面体
+        vertices.iter().map(|v| (home + *v) / 3.).collect()
+    };
+    points
+        .into_iter()
+        .map(|point| point * CUBE_SIZE / 2.)
+        .collect()
+}
+
+/// [`Cuboid`] 网格中纹理 u、v 坐标增大的方向
+fn texture_axes(face: Face) -> (Vec3, Vec3) {
+    match face {
+        Face::Front => (Vec3::X, Vec3::Y),
+        Face::Back => (Vec3::NEG_X, Vec3::NEG_Y),
+        Face::Right => (Vec3::Y, Vec3::Z),
+        Face::Left => (Vec3::NEG_Y, Vec3::NEG_Z),
+        Face::Up => (Vec3::X, Vec3::Z),
+        Face::Down => (Vec3::NEG_X, Vec3::NEG_Z),
+    }
+}
+
+/// 外表面到中心的距离
+fn face_distance() -> f32 {
+    CUBE_SIZE / 2. / 3f32.sqrt()
+}
+
+impl Puzzle for Pyraminx {
+    fn name(&self) -> String {
+        "pyraminx".to_string()
+    }
+
+    fn homes(&self) -> Vec<IVec3> {
+        let mut homes = Vec::new();
+        for (i, (_, vertex)) in VERTICES.iter().enumerate() {
+            homes.extend([*vertex * 2, *vertex]);
+            homes.extend(VERTICES[i + 1..].iter().map(|(_, other)| *vertex + *other));
+        }
+        homes
+    }
+
+    fn supports(&self, m: Move) -> bool {
+        vertex(m.face).is_some() && ((m.depth == 1 && !m.wide) || is_tip(m))
+    }
+
+    /// 从中心指向转动的顶点，不能转动的字母不会执行
+    fn axis(&self, m: Move) -> Vec3 {
+        vertex(m.face).unwrap_or(IVec3::ONE).as_vec3().normalize()
+    }
+
+    fn angle(&self, m: Move) -> f32 {
+        -m.turn.steps() as f32 * TAU / 3.
+    }
+
+    /// 顶角、中心块与相邻棱块到顶点方向的投影依次为 6、3、2，其余方块为负数
+    fn contains(&self, m: Move, position: IVec3) -> bool {
+        let threshold = if is_tip(m) { 3 } else { 1 };
+        vertex(m.face).is_some_and(|vertex| vertex.dot(position) > threshold)
+    }
+
+    fn random_move(&self, history: [Option<Face>; 2], rng: &mut dyn RngCore) -> Move {
+        let faces = VERTICES.map(|(face, _)| face);
+        let face = random_face(&faces, history, |a, b| a == b, rng);
+        let m = Move::new(face, random_turn(&[Turn::Quarter, Turn::Prime], rng));
+        if rng.next_u32().is_multiple_of(TIP_CHANCE) {
+            m.with_layers(2, true)
+        } else {
+            m
+        }
+    }
+
+    /// 顶角的转动写成小写字母
+    fn format_move(&self, m: Move) -> String {
+        if is_tip(m) {
+            m.with_layers(1, false).to_string().to_lowercase()
+        } else {
+            m.to_string()
+        }
+    }
+
+    /// 网格的顶点已经是整个金字塔中的坐标
+    fn piece_transform(&self, _position: IVec3, orientation: Quat) -> Transform {
+        Transform::from_rotation(orientation)
+    }
+
+    fn gradient_position(&self, home: IVec3) -> Vec3 {
+        home.as_vec3() / home.abs().max_element() as f32
+    }
+
+    fn sticker_mesh(&self, home: IVec3, theme: &StickerTheme, alpha: f32) -> Mesh {
+        // 外表面与顶点方向相反，切面离中心更近或朝向不同
+        let sticker = |normal: Vec3, distance: f32| {
+            let (face, _) = VERTICES.iter().find(|(_, vertex)| {
+                normal.dot(-vertex.as_vec3().normalize()) > 0.999
+                    && (distance - face_distance()).abs() < 1e-3
+            })?;
+            Some(theme.color(opposite(*face).normal()))
+        };
+        polyhedron_mesh(&piece_points(home), sticker, theme, alpha)
+    }
+
+    fn gradient_mesh(&self, home: IVec3, alpha: f32) -> Option<Mesh> {
+        Some(gradient_polyhedron_mesh(
+            &piece_points(home),
+            CUBE_SIZE,
+            alpha,
+        ))
+    }
+
+    /// `up`、`left`、`back`、`down` 四个面，报时的面缩小到三角形的内切圆，文字朝向 `U`
+    /// （`down` 面朝向 `B`）
+    fn face_transform(&self, face: Face) -> Option<Transform> {
+        let (letter, opposite_vertex) = VERTICES
+            .iter()
+            .find(|(letter, _)| opposite(*letter) == face)?;
+        let normal = -opposite_vertex.as_vec3().normalize();
+        let center = normal * face_distance();
+        let apex = match letter {
+            Face::Up => vertex(Face::Back),
+            _ => vertex(Face::Up),
+        }?;
+        let apex = apex.as_vec3() * CUBE_SIZE / 2.;
+        let up = (apex - center).reject_from(normal).normalize();
+        // 纹理中的文字转过了 45°，文字的上方为 u 增大、v 减小的方向
+        let (u, v) = texture_axes(face);
+        let source = face.normal().as_vec3();
+        let text_up = (u - v) / SQRT_2;
+        let frame = |up: Vec3, normal: Vec3| {
+            Quat::from_mat3(&Mat3::from_cols(up.cross(normal), up, normal))
+        };
+        let rotation = frame(up, normal) * frame(text_up, source).inverse();
+        let scale = (2f32 / 3.).sqrt();
+        Some(Transform {
+            translation: center - normal * CUBE_SIZE / 2. * scale,
+            rotation,
+            scale: Vec3::splat(scale),
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand_core::RngCore;
+
+use super::{random_face, random_turn, Puzzle};
+use crate::graphics::{
+    cube::{
+        state::{Face, Move, Turn},
+        CUBE_SIZE,
+    },
+    sticker::{gradient_polyhedron_mesh, polyhedron_mesh},
+    theme::StickerTheme,
+};
+
+/// 可以转动的角，与 WCA 记号相同
+const CORNERS: [Face; 4] = [Face::Right, Face::Up, Face::Left, Face::Back];
+
+/// 斜转魔方：切面都穿过中心，每次绕一根体对角线把半个魔方转动 120°
+///
+/// 按 WCA 记号，`R`、`U`、`L`、`B` 分别转动右下后、左上后、左下前、左下后的角，右上前的角不动；
+/// 角块的逻辑坐标为 (±1, ±1, ±1)，中心块为 (±2, 0, 0) 等
+pub struct Skewb;
+
+/// 字母对应的角，其余字母不能转动
+fn corner(face: Face) -> Option<IVec3> {
+    match face {
+        Face::Right => Some(IVec3::new(1, -1, -1)),
+        Face::Up => Some(IVec3::new(-1, 1, -1)),
+        Face::Left => Some(IVec3::new(-1, -1, 1)),
+        Face::Back => Some(IVec3::NEG_ONE),
+        _ => None,
+    }
+}
+
+/// 方块的顶点
+fn piece_points(home: IVec3) -> Vec<Vec3> {
+    let home = home.as_vec3();
+    // 以魔方的半边长为单位
+    let points = if home.abs().min_element() > 0. {
+        // 角块：角与相邻三条棱的中点，以及中心
+        let mut points = vec![Vec3::ZERO, home];
+        for axis in 0..3 {
+            let mut point = home;
+            point[axis] = 0.;
+            points.push(point);
+        }
+        points
+    } else {
+        // 中心块：面上转过 45° 的正方形与中心组成的四棱锥
+        let normal = home / 2.;
+        let axis = (0..3).find(|axis| normal[*axis] != 0.).unwrap_or(0);
+        let (u, v) = (Vec3::AXES[(axis + 1) % 3], Vec3::AXES[(axis + 2) % 3]);
+        vec![Vec3::ZERO, normal + u, normal + v, normal - u, normal - v]
+    };
+    points
+        .into_iter()
+        .map(|point| point * CUBE_SIZE / 2.)
+        .collect()
+}
+
+impl Puzzle for Skewb {
+    fn name(&self) -> String {
+        "skewb".to_string()
+    }
+
+    fn homes(&self) -> Vec<IVec3> {
+        let mut homes = Vec::new();
+        for x in [-1, 1] {
+            for y in [-1, 1] {
+                for z in [-1, 1] {
+                    homes.push(IVec3::new(x, y, z));
+                }
+            }
+        }
+        homes.extend(Face::ALL.map(|face| face.normal() * 2));
+        homes
+    }
+
+    fn supports(&self, m: Move) -> bool {
+        corner(m.face).is_some() && m.depth == 1 && !m.wide
+    }
+
+    /// 从中心指向转动的角，不能转动的字母不会执行
+    fn axis(&self, m: Move) -> Vec3 {
+        corner(m.face).unwrap_or(IVec3::ONE).as_vec3().normalize()
+    }
+
+    fn angle(&self, m: Move) -> f32 {
+        -m.turn.steps() as f32 * TAU / 3.
+    }
+
+    fn contains(&self, m: Move, position: IVec3) -> bool {
+        corner(m.face).is_some_and(|corner| corner.dot(position) > 0)
+    }
+
+    fn random_move(&self, history: [Option<Face>; 2], rng: &mut dyn RngCore) -> Move {
+        let face = random_face(&CORNERS, history, |a, b| a == b, rng);
+        Move::new(face, random_turn(&[Turn::Quarter, Turn::Prime], rng))
+    }
+
+    /// 网格的顶点已经是整个魔方中的坐标
+    fn piece_transform(&self, _position: IVec3, orientation: Quat) -> Transform {
+        Transform::from_rotation(orientation)
+    }
+
+    fn gradient_position(&self, home: IVec3) -> Vec3 {
+        home.as_vec3() / home.abs().max_element() as f32
+    }
+
+    fn sticker_mesh(&self, home: IVec3, theme: &StickerTheme, alpha: f32) -> Mesh {
+        // 外表面与坐标轴垂直，切面都穿过中心
+        let sticker = |normal: Vec3, distance: f32| {
+            (distance > CUBE_SIZE / 4.).then(|| theme.color(normal.round().as_ivec3()))
+        };
+        polyhedron_mesh(&piece_points(home), sticker, theme, alpha)
+    }
+
+    fn gradient_mesh(&self, home: IVec3, alpha: f32) -> Option<Mesh> {
+        Some(gradient_polyhedron_mesh(
+            &piece_points(home),
+            CUBE_SIZE,
+            alpha,
+        ))
+    }
+}
@@ -0,0 +1,413 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::plugin::EntropyPlugin;
+use rand_core::RngCore;
+use thiserror::Error;
+
+use super::{
+    persist::InitialState,
+    puzzle::PuzzleKind,
+    scramble::Scrambler,
+    state::{parse_moves, CubeState, Move, NotationError, Order, PieceState},
+};
+
+/// 随机数种子与转动记录
+///
+/// `TIME_FLY_SEED` 指定种子（十进制或 `0x` 开头的十六进制），未指定时随机生成，启动时写入日志；
+/// 每次转动连同来源追加到 `<数据目录>/time-fly/moves.log`，上一次运行的记录改名为 `moves.log.1`。
+/// `TIME_FLY_REPLAY=<记录文件>` 使用记录中的种子，从记录的初始状态开始依次重放其中的转动，
+/// 重放随机转动时照常生成随机转动但不执行，外部指定的转动直接执行，之后的随机转动与原来的运行相同；
+/// 记录的谜题与阶数需与当前相同，需要在 `PersistPlugin` 之后加入
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let puzzle = *app.world().resource::<PuzzleKind>();
+        let replay = std::env::var_os("TIME_FLY_REPLAY").and_then(|path| {
+            MoveLog::load(&path)
+                .inspect_err(|err| warn!("failed to load move log {path:?}: {err}"))
+                .ok()
+                .filter(|log| {
+                    let matches = log.puzzle == puzzle;
+                    if !matches {
+                        warn!(
+                            "move log {path:?} is for a {}, not a {}",
+                            log.puzzle.puzzle().name(),
+                            puzzle.puzzle().name()
+                        );
+                    }
+                    matches
+                })
+        });
+        let seed = match &replay {
+            Some(log) => log.seed,
+            None => std::env::var("TIME_FLY_SEED")
+                .ok()
+                .and_then(|value| parse_seed(&value).inspect_err(|err| warn!("{err}")).ok())
+                .unwrap_or_else(random_seed),
+        };
+        info!("entropy seed: {seed:#018x}");
+
+        if let Some(log) = replay {
+            app.insert_resource(InitialState(log.initial))
+                .insert_resource(ReplayMoves(log.moves.into()));
+        }
+        let recorder = MoveRecorder::create(seed, &app.world().resource::<InitialState>().0);
+        app.add_plugins(EntropyPlugin::<WyRand>::with_seed(seed.to_le_bytes()))
+            .add_event::<MoveFinished>()
+            .init_resource::<MoveQueue>()
+            .init_resource::<ReplayMoves>()
+            .insert_resource(recorder)
+            .add_systems(Update, record_moves);
+    }
+}
+
+/// 转动的来源，只有随机转动消耗随机数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveSource {
+    /// 由 [`Scrambler`] 生成
+    Random,
+    /// 来自控制命令、托盘、快捷键或上次运行保存的队列
+    External,
+}
+
+/// 等待执行的转动，为空时才会生成随机转动
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct MoveQueue(pub VecDeque<(Move, MoveSource)>);
+
+/// 记录中还没有重放的转动，随机转动代替同样数量的随机转动执行
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct ReplayMoves(pub VecDeque<(Move, MoveSource)>);
+
+impl ReplayMoves {
+    /// 取出下一批要执行的转动：开头的外部转动直接取出；随机转动照常生成一批随机转动，
+    /// 取出同样数量的记录，使随机数与原来的运行同步
+    pub fn next_batch(
+        &mut self,
+        scrambler: &mut Scrambler,
+        rng: &mut impl RngCore,
+    ) -> Vec<(Move, MoveSource)> {
+        let leading = |source| {
+            self.iter()
+                .take_while(|(_, other)| *other == source)
+                .count()
+        };
+        let count = match self.front() {
+            None => 0,
+            Some((_, MoveSource::External)) => leading(MoveSource::External),
+            Some((_, MoveSource::Random)) => scrambler
+                .next_moves(rng)
+                .len()
+                .min(leading(MoveSource::Random)),
+        };
+        self.drain(..count).collect()
+    }
+}
+
+/// 一次转动完成
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MoveFinished(pub Move, pub MoveSource);
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("failed to read move log: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid seed `{0}`")]
+    InvalidSeed(String),
+    #[error("missing seed")]
+    MissingSeed,
+    #[error("invalid order `{0}`")]
+    InvalidOrder(String),
+    #[error("unknown puzzle `{0}`")]
+    UnknownPuzzle(String),
+    #[error("invalid piece `{0}`")]
+    InvalidPiece(String),
+    #[error("expected {expected} pieces, found {found}")]
+    PieceCount { expected: usize, found: usize },
+    #[error(transparent)]
+    Notation(#[from] NotationError),
+}
+
+/// 转动记录：第一行为 `seed <种子>`，可选的 `puzzle <名称>`（默认魔方）与 `order <阶数>`（默认三阶），
+/// 从保存的状态恢复时每个方块一行 `piece <初始坐标> <坐标> <朝向四元数>`（没有时为还原状态），
+/// 之后每行为 `random R` 或 `external U2` 形式的转动及其来源，没有来源的旧记录视为随机转动
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveLog {
+    pub seed: u64,
+    pub puzzle: PuzzleKind,
+    /// 第一次转动前的状态
+    pub initial: CubeState,
+    pub moves: Vec<(Move, MoveSource)>,
+}
+
+impl MoveLog {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ReplayError> {
+        let mut seed = None;
+        let mut order = Order::default();
+        let mut id = None;
+        let mut pieces = Vec::new();
+        let mut moves = Vec::new();
+        for line in source.lines().map(str::trim) {
+            if let Some(value) = line.strip_prefix("seed ") {
+                seed = Some(parse_seed(value)?);
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("order ") {
+                order = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .and_then(Order::new)
+                    .ok_or_else(|| ReplayError::InvalidOrder(value.trim().to_string()))?;
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("puzzle ") {
+                id = Some(value.trim().to_string());
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("piece ") {
+                pieces.push(parse_piece(value)?);
+                continue;
+            }
+            let (source, line) = match line.strip_prefix("external ") {
+                Some(line) => (MoveSource::External, line),
+                None => (
+                    MoveSource::Random,
+                    line.strip_prefix("random ").unwrap_or(line),
+                ),
+            };
+            moves.extend(parse_moves(line)?.into_iter().map(|m| (m, source)));
+        }
+        let puzzle = match id {
+            Some(id) => PuzzleKind::from_id(&id, order).ok_or(ReplayError::UnknownPuzzle(id))?,
+            None => PuzzleKind::Cube(order),
+        };
+        let kind = puzzle.puzzle();
+        if let Some((m, _)) = moves.iter().find(|(m, _)| !kind.supports(*m)) {
+            return Err(NotationError::Unsupported(kind.format_move(*m), kind.name()).into());
+        }
+        let solved = CubeState::solved_with(puzzle);
+        let initial = if pieces.is_empty() {
+            solved
+        } else if pieces.len() != solved.pieces.len() {
+            return Err(ReplayError::PieceCount {
+                expected: solved.pieces.len(),
+                found: pieces.len(),
+            });
+        } else {
+            CubeState { puzzle, pieces }
+        };
+        Ok(Self {
+            seed: seed.ok_or(ReplayError::MissingSeed)?,
+            puzzle,
+            initial,
+            moves,
+        })
+    }
+
+    /// 从初始状态依次执行所有转动
+    pub fn replay(&self) -> CubeState {
+        let mut state = self.initial.clone();
+        for (m, _) in &self.moves {
+            state.apply(*m);
+        }
+        state
+    }
+}
+
+/// 将转动写入记录文件
+#[derive(Resource)]
+struct MoveRecorder {
+    file: Option<File>,
+    puzzle: PuzzleKind,
+}
+
+impl MoveRecorder {
+    fn create(seed: u64, initial: &CubeState) -> Self {
+        let puzzle = initial.puzzle;
+        let mut header = match puzzle {
+            PuzzleKind::Cube(order) => format!("order {}", order.get()),
+            _ => format!("puzzle {}", puzzle.id()),
+        };
+        if *initial != CubeState::solved_with(puzzle) {
+            for piece in &initial.pieces {
+                header.push('\n');
+                header.push_str(&format_piece(piece));
+            }
+        }
+        let file = log_path().and_then(|path| {
+            let file = std::fs::create_dir_all(path.parent()?)
+                .and_then(|_| rotate(&path))
+                .and_then(|_| File::create(&path))
+                .and_then(|mut file| writeln!(file, "seed {seed:#018x}\n{header}").map(|_| file));
+            file.inspect_err(|err| warn!("failed to create move log {path:?}: {err}"))
+                .ok()
+        });
+        Self { file, puzzle }
+    }
+}
+
+fn record_moves(mut events: EventReader<MoveFinished>, mut recorder: ResMut<MoveRecorder>) {
+    for MoveFinished(m, source) in events.read() {
+        let source = match source {
+            MoveSource::Random => "random",
+            MoveSource::External => "external",
+        };
+        let text = format!("{source} {}", recorder.puzzle.puzzle().format_move(*m));
+        let Some(file) = &mut recorder.file else {
+            continue;
+        };
+        if let Err(err) = writeln!(file, "{text}") {
+            warn!("failed to record move: {err}");
+            recorder.file = None;
+        }
+    }
+}
+
+fn log_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("time-fly").join("moves.log"))
+}
+
+/// 保留上一次运行的记录
+fn rotate(path: &Path) -> std::io::Result<()> {
+    match std::fs::rename(path, path.with_extension("log.1")) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// `piece` 行：初始坐标、坐标各三个整数，朝向四个浮点数
+fn format_piece(piece: &PieceState) -> String {
+    let [hx, hy, hz] = piece.home.to_array();
+    let [x, y, z] = piece.position.to_array();
+    let [qx, qy, qz, qw] = piece.orientation.to_array();
+    format!("piece {hx} {hy} {hz} {x} {y} {z} {qx} {qy} {qz} {qw}")
+}
+
+fn parse_piece(value: &str) -> Result<PieceState, ReplayError> {
+    let invalid = || ReplayError::InvalidPiece(value.trim().to_string());
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    let [hx, hy, hz, x, y, z, qx, qy, qz, qw] = fields[..] else {
+        return Err(invalid());
+    };
+    let int = |field: &str| field.parse::<i32>().map_err(|_| invalid());
+    let float = |field: &str| field.parse::<f32>().map_err(|_| invalid());
+    Ok(PieceState {
+        home: IVec3::new(int(hx)?, int(hy)?, int(hz)?),
+        position: IVec3::new(int(x)?, int(y)?, int(z)?),
+        orientation: Quat::from_xyzw(float(qx)?, float(qy)?, float(qz)?, float(qw)?).normalize(),
+    })
+}
+
+fn parse_seed(value: &str) -> Result<u64, ReplayError> {
+    let value = value.trim();
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|_| ReplayError::InvalidSeed(value.to_string()))
+}
+
+fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64);
+    nanos ^ (std::process::id() as u64).rotate_left(32)
+}
+
+#[test]
+fn move_log_round_trip() {
+    let log = MoveLog::parse("seed 0x00000000000004d2\nrandom F\nexternal R' U2\n").unwrap();
+    assert_eq!(log.seed, 1234);
+    let sources = [
+        MoveSource::Random,
+        MoveSource::External,
+        MoveSource::External,
+    ];
+    let moves: Vec<_> = parse_moves("F R' U2")
+        .unwrap()
+        .into_iter()
+        .zip(sources)
+        .collect();
+    assert_eq!(log.moves, moves);
+
+    let mut state = CubeState::solved();
+    state.apply_sequence("FR'U2").unwrap();
+    assert_eq!(log.replay(), state);
+    // 没有来源的旧记录视为随机转动
+    let log = MoveLog::parse("seed 1\nF R'").unwrap();
+    assert!(log
+        .moves
+        .iter()
+        .all(|(_, source)| *source == MoveSource::Random));
+    assert!(matches!(
+        MoveLog::parse("seed 1\nX"),
+        Err(ReplayError::Notation(_))
+    ));
+
+    let log = MoveLog::parse("seed 1\norder 4\n3Rw U").unwrap();
+    assert_eq!(log.puzzle, PuzzleKind::Cube(Order::new(4).unwrap()));
+    assert_eq!(log.replay().pieces.len(), 56);
+    assert!(matches!(
+        MoveLog::parse("seed 1\n4Rw"),
+        Err(ReplayError::Notation(NotationError::Unsupported(..)))
+    ));
+
+    let log = MoveLog::parse("seed 1\npuzzle pyraminx\nU l' B").unwrap();
+    assert_eq!(log.puzzle, PuzzleKind::Pyraminx);
+    assert!(matches!(
+        MoveLog::parse("seed 1\npuzzle megaminx"),
+        Err(ReplayError::UnknownPuzzle(_))
+    ));
+
+    // 恢复的状态写在开头，重放从该状态开始
+    let mut initial = CubeState::solved();
+    initial.apply_sequence("RU").unwrap();
+    let header: Vec<String> = initial.pieces.iter().map(format_piece).collect();
+    let log = MoveLog::parse(&format!("seed 1\n{}\nU'R'", header.join("\n"))).unwrap();
+    assert_eq!(log.initial, initial);
+    assert_eq!(log.replay(), CubeState::solved());
+    assert!(matches!(
+        MoveLog::parse(&format!("seed 1\n{}", header[0])),
+        Err(ReplayError::PieceCount { .. })
+    ));
+}
+
+#[test]
+fn replay_mixed_sources() {
+    use rand_core::SeedableRng;
+
+    // 原来的运行：两批随机转动之间执行了外部转动，外部转动不消耗随机数
+    let mut rng = WyRand::seed_from_u64(42);
+    let mut scrambler = Scrambler::default();
+    let mut moves = Vec::new();
+    for _ in 0..3 {
+        let batch = scrambler.next_moves(&mut rng);
+        moves.extend(batch.into_iter().map(|m| (m, MoveSource::Random)));
+        let external = parse_moves("R U R'").unwrap();
+        moves.extend(external.into_iter().map(|m| (m, MoveSource::External)));
+    }
+    let expected = scrambler.next_moves(&mut rng);
+
+    let mut rng = WyRand::seed_from_u64(42);
+    let mut scrambler = Scrambler::default();
+    let mut replay = ReplayMoves(moves.clone().into());
+    let mut replayed = Vec::new();
+    while !replay.is_empty() {
+        replayed.extend(replay.next_batch(&mut scrambler, &mut rng));
+    }
+    assert_eq!(replayed, moves);
+    assert_eq!(scrambler.next_moves(&mut rng), expected);
+}
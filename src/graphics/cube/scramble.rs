@@ -0,0 +1,134 @@
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use bevy_prng::WyRand;
+use rand_core::{RngCore, SeedableRng};
+
+use super::{
+    puzzle::PuzzleKind,
+    state::{Face, Move},
+};
+use solver::CubieCube;
+
+mod solver;
+
+/// 打乱方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScrambleMode {
+    /// 逐个生成随机转动
+    #[default]
+    Moves,
+    /// 先均匀随机地选取一个状态，再求出到达它的转动序列，与 WCA 比赛的打乱方式相同
+    RandomState,
+}
+
+/// 随机转动生成器，避免与前面的转动重复或抵消
+///
+/// `TIME_FLY_SCRAMBLE=random-state` 时按随机状态成批生成，只支持三阶魔方，
+/// 建表与求解在后台线程进行；其他谜题的随机转动见 [`Puzzle::random_move`](super::puzzle::Puzzle::random_move)
+#[derive(Resource, Default)]
+pub struct Scrambler {
+    mode: ScrambleMode,
+    puzzle: PuzzleKind,
+    /// 最近的两次转动
+    history: [Option<Face>; 2],
+    /// 正在求解的随机状态
+    task: Option<Task<Vec<Move>>>,
+}
+
+impl Scrambler {
+    pub fn from_env(puzzle: PuzzleKind) -> Self {
+        let mode = match std::env::var("TIME_FLY_SCRAMBLE").as_deref() {
+            Ok("random-state") if puzzle != PuzzleKind::default() => {
+                warn!("random-state scrambles need a 3x3x3 cube, using `moves`");
+                ScrambleMode::Moves
+            }
+            Ok("random-state") => ScrambleMode::RandomState,
+            Ok("moves") | Err(_) => ScrambleMode::Moves,
+            Ok(other) => {
+                warn!("unknown scramble mode `{other}`, using `moves`");
+                ScrambleMode::Moves
+            }
+        };
+        Self {
+            mode,
+            puzzle,
+            ..default()
+        }
+    }
+
+    /// 生成下一批转动，随机状态还没有求出时返回空
+    pub fn next_moves(&mut self, rng: &mut impl RngCore) -> Vec<Move> {
+        let moves = match self.mode {
+            ScrambleMode::Moves => vec![self.random_move(rng)],
+            ScrambleMode::RandomState => {
+                let history = self.history;
+                let task = self.task.get_or_insert_with(|| {
+                    // 子生成器的种子取自 `rng`，结果只由种子决定
+                    let mut rng = WyRand::seed_from_u64(rng.next_u64());
+                    AsyncComputeTaskPool::get()
+                        .spawn(async move { random_state(&mut rng, history) })
+                });
+                let Some(moves) = block_on(future::poll_once(task)) else {
+                    return Vec::new();
+                };
+                self.task = None;
+                moves
+            }
+        };
+        for m in &moves {
+            self.history = [self.history[1], Some(m.face)];
+        }
+        moves
+    }
+
+    /// 由谜题生成，不转动上一次的面，也不与前两次转动抵消
+    fn random_move(&self, rng: &mut impl RngCore) -> Move {
+        self.puzzle.puzzle().random_move(self.history, rng)
+    }
+}
+
+/// WCA 式随机状态打乱：对随机状态的逆求解，解法即从还原状态到达该状态的转动序列
+///
+/// 解法的开头接在 `history` 之后，不与前面的转动重复或抵消，不需要删去转动
+pub fn random_state(rng: &mut impl RngCore, history: [Option<Face>; 2]) -> Vec<Move> {
+    let target = CubieCube::random(rng);
+    solver::solve(&target.inverse(), history)
+}
+
+#[test]
+fn moves_do_not_cancel() {
+    use rand_core::SeedableRng;
+
+    let mut rng = bevy_prng::WyRand::seed_from_u64(7);
+    let mut scrambler = Scrambler::default();
+    let mut moves: Vec<Move> = (0..500)
+        .flat_map(|_| scrambler.next_moves(&mut rng))
+        .collect();
+
+    // 随机状态在后台求解，前后两批之间也不重复或抵消
+    bevy::tasks::AsyncComputeTaskPool::get_or_init(bevy::tasks::TaskPool::default);
+    let mut scrambler = Scrambler {
+        mode: ScrambleMode::RandomState,
+        history: scrambler.history,
+        ..default()
+    };
+    let mut batches = 0;
+    while batches < 3 {
+        let batch = scrambler.next_moves(&mut rng);
+        if batch.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            continue;
+        }
+        moves.extend(batch);
+        batches += 1;
+    }
+    for pair in moves.windows(2) {
+        assert_ne!(pair[0].face, pair[1].face);
+    }
+    for triple in moves.windows(3) {
+        let same_axis = triple[0].face.same_axis(triple[1].face);
+        assert!(!(same_axis && triple[1].face.same_axis(triple[2].face)));
+    }
+}
@@ -0,0 +1,631 @@
+use std::{collections::VecDeque, sync::OnceLock};
+
+use rand_core::RngCore;
+
+use crate::graphics::cube::{
+    puzzle::follows,
+    state::{Face, Move, Turn},
+};
+
+const N_TWIST: usize = 2187;
+const N_FLIP: usize = 2048;
+const N_SLICE: usize = 495;
+const N_PERM8: usize = 40320;
+const N_PERM4: usize = 24;
+const N_PERM12: usize = 479_001_600;
+const N_MOVES: usize = 18;
+/// 第二阶段可用的转动：U、D 任意，其余面只能转 180°
+const PHASE2_MOVES: [usize; 10] = [0, 1, 2, 4, 7, 9, 10, 11, 13, 16];
+/// 解法总长度上限，第一阶段最多 12 步、第二阶段最多 18 步
+const MAX_LENGTH: usize = 30;
+
+/// 以块为单位的魔方状态，面的顺序为 U R F D L B
+///
+/// 角块位置依次为 URF UFL ULB UBR DFR DLF DBL DRB，
+/// 棱块位置依次为 UR UF UL UB DR DF DL DB FR FL BL BR；
+/// `cp[i]` 为位于第 i 个位置的角块，`co[i]` 为其朝向，棱块同理
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CubieCube {
+    cp: [u8; 8],
+    co: [u8; 8],
+    ep: [u8; 12],
+    eo: [u8; 12],
+}
+
+/// 六个面顺时针转动 90° 对应的状态
+const BASIC_MOVES: [CubieCube; 6] = [
+    // U
+    CubieCube {
+        cp: [3, 0, 1, 2, 4, 5, 6, 7],
+        co: [0; 8],
+        ep: [3, 0, 1, 2, 4, 5, 6, 7, 8, 9, 10, 11],
+        eo: [0; 12],
+    },
+    // R
+    CubieCube {
+        cp: [4, 1, 2, 0, 7, 5, 6, 3],
+        co: [2, 0, 0, 1, 1, 0, 0, 2],
+        ep: [8, 1, 2, 3, 11, 5, 6, 7, 4, 9, 10, 0],
+        eo: [0; 12],
+    },
+    // F
+    CubieCube {
+        cp: [1, 5, 2, 3, 0, 4, 6, 7],
+        co: [1, 2, 0, 0, 2, 1, 0, 0],
+        ep: [0, 9, 2, 3, 4, 8, 6, 7, 1, 5, 10, 11],
+        eo: [0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 0],
+    },
+    // D
+    CubieCube {
+        cp: [0, 1, 2, 3, 5, 6, 7, 4],
+        co: [0; 8],
+        ep: [0, 1, 2, 3, 5, 6, 7, 4, 8, 9, 10, 11],
+        eo: [0; 12],
+    },
+    // L
+    CubieCube {
+        cp: [0, 2, 6, 3, 4, 1, 5, 7],
+        co: [0, 1, 2, 0, 0, 2, 1, 0],
+        ep: [0, 1, 10, 3, 4, 5, 9, 7, 8, 2, 6, 11],
+        eo: [0; 12],
+    },
+    // B
+    CubieCube {
+        cp: [0, 1, 3, 7, 4, 5, 2, 6],
+        co: [0, 0, 1, 2, 0, 0, 2, 1],
+        ep: [0, 1, 2, 11, 4, 5, 6, 10, 8, 9, 3, 7],
+        eo: [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1],
+    },
+];
+
+impl CubieCube {
+    pub const SOLVED: CubieCube = CubieCube {
+        cp: [0, 1, 2, 3, 4, 5, 6, 7],
+        co: [0; 8],
+        ep: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        eo: [0; 12],
+    };
+
+    /// 均匀随机的可还原状态
+    pub fn random(rng: &mut impl RngCore) -> Self {
+        let mut random = |n: usize| (rng.next_u64() % n as u64) as usize;
+        let mut cube = Self::SOLVED;
+        cube.set_twist(random(N_TWIST));
+        cube.set_flip(random(N_FLIP));
+        let corners = perm_from_index(random(N_PERM8), 8);
+        let mut edges = perm_from_index(random(N_PERM12), 12);
+        // 角块与棱块排列的奇偶性必须相同
+        if parity(&corners) != parity(&edges) {
+            edges.swap(10, 11);
+        }
+        cube.cp.copy_from_slice(&corners);
+        cube.ep.copy_from_slice(&edges);
+        cube
+    }
+
+    /// 逆状态，与原状态相乘得到还原状态
+    pub fn inverse(&self) -> Self {
+        let mut inverse = Self::SOLVED;
+        for i in 0..8 {
+            let piece = self.cp[i] as usize;
+            inverse.cp[piece] = i as u8;
+            inverse.co[piece] = (3 - self.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let piece = self.ep[i] as usize;
+            inverse.ep[piece] = i as u8;
+            inverse.eo[piece] = self.eo[i];
+        }
+        inverse
+    }
+
+    #[cfg(test)]
+    fn apply(&mut self, m: Move) {
+        *self = self.multiply(&tables().moves[move_index(m)]);
+    }
+
+    fn multiply(&self, other: &CubieCube) -> CubieCube {
+        let mut result = CubieCube::SOLVED;
+        for i in 0..8 {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let from = other.ep[i] as usize;
+            result.ep[i] = self.ep[from];
+            result.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+        result
+    }
+
+    fn twist(&self) -> usize {
+        self.co[..7].iter().fold(0, |t, &o| t * 3 + o as usize)
+    }
+
+    fn set_twist(&mut self, mut twist: usize) {
+        let mut sum = 0;
+        for i in (0..7).rev() {
+            self.co[i] = (twist % 3) as u8;
+            sum += self.co[i];
+            twist /= 3;
+        }
+        self.co[7] = (3 - sum % 3) % 3;
+    }
+
+    fn flip(&self) -> usize {
+        self.eo[..11].iter().fold(0, |f, &o| f * 2 + o as usize)
+    }
+
+    fn set_flip(&mut self, mut flip: usize) {
+        let mut sum = 0;
+        for i in (0..11).rev() {
+            self.eo[i] = (flip % 2) as u8;
+            sum += self.eo[i];
+            flip /= 2;
+        }
+        self.eo[11] = sum % 2;
+    }
+
+    /// 中层棱块 FR FL BL BR 所在的位置（不计顺序）
+    fn slice(&self) -> usize {
+        let mut index = 0;
+        let mut count = 0;
+        for j in (0..12).rev() {
+            if self.ep[j] >= 8 {
+                index += binomial(11 - j, count + 1);
+                count += 1;
+            }
+        }
+        index
+    }
+
+    fn set_slice(&mut self, mut index: usize) {
+        let mut count = 4;
+        let (mut slice_edge, mut other_edge) = (8, 0);
+        for j in 0..12 {
+            if count > 0 && index >= binomial(11 - j, count) {
+                index -= binomial(11 - j, count);
+                count -= 1;
+                self.ep[j] = slice_edge;
+                slice_edge += 1;
+            } else {
+                self.ep[j] = other_edge;
+                other_edge += 1;
+            }
+        }
+    }
+
+    fn corners(&self) -> usize {
+        perm_index(&self.cp)
+    }
+
+    fn set_corners(&mut self, index: usize) {
+        self.cp.copy_from_slice(&perm_from_index(index, 8));
+    }
+
+    /// 第二阶段中 U、D 层八个棱块的排列
+    fn ud_edges(&self) -> usize {
+        perm_index(&self.ep[..8])
+    }
+
+    fn set_ud_edges(&mut self, index: usize) {
+        self.ep[..8].copy_from_slice(&perm_from_index(index, 8));
+    }
+
+    /// 第二阶段中中层四个棱块的排列
+    fn slice_perm(&self) -> usize {
+        perm_index(&self.ep[8..])
+    }
+
+    fn set_slice_perm(&mut self, index: usize) {
+        for (edge, i) in self.ep[8..].iter_mut().zip(perm_from_index(index, 4)) {
+            *edge = i + 8;
+        }
+    }
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |c, i| c * (n - i) / (i + 1))
+}
+
+/// 排列的字典序编号，只比较相对大小
+fn perm_index(perm: &[u8]) -> usize {
+    (0..perm.len()).fold(0, |index, i| {
+        let smaller = perm[i + 1..].iter().filter(|&&p| p < perm[i]).count();
+        index * (perm.len() - i) + smaller
+    })
+}
+
+fn perm_from_index(mut index: usize, n: usize) -> Vec<u8> {
+    let mut digits = vec![0; n];
+    for i in (0..n).rev() {
+        digits[i] = index % (n - i);
+        index /= n - i;
+    }
+    let mut available: Vec<u8> = (0..n as u8).collect();
+    digits.into_iter().map(|d| available.remove(d)).collect()
+}
+
+fn parity(perm: &[u8]) -> bool {
+    let inversions: usize = (0..perm.len())
+        .map(|i| perm[i + 1..].iter().filter(|&&p| p < perm[i]).count())
+        .sum();
+    inversions % 2 == 1
+}
+
+/// 转动编号为 面 × 3 + 幅度，面与幅度的顺序同 [`Face::ALL`] 与 [`Turn::ALL`]
+#[cfg(test)]
+fn move_index(m: Move) -> usize {
+    let face = Face::ALL.iter().position(|f| *f == m.face).unwrap_or(0);
+    let turn = Turn::ALL.iter().position(|t| *t == m.turn).unwrap_or(0);
+    face * 3 + turn
+}
+
+fn index_move(index: usize) -> Move {
+    Move::new(Face::ALL[index / 3], Turn::ALL[index % 3])
+}
+
+/// 坐标的转动表与剪枝表，首次求解时生成
+struct Tables {
+    moves: Vec<CubieCube>,
+    twist: Vec<[u16; N_MOVES]>,
+    flip: Vec<[u16; N_MOVES]>,
+    slice: Vec<[u16; N_MOVES]>,
+    corners: Vec<[u16; N_MOVES]>,
+    ud_edges: Vec<[u16; N_MOVES]>,
+    slice_perm: Vec<[u16; N_MOVES]>,
+    twist_slice: Vec<u8>,
+    flip_slice: Vec<u8>,
+    corners_slice_perm: Vec<u8>,
+    ud_edges_slice_perm: Vec<u8>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+impl Tables {
+    fn new() -> Self {
+        let moves: Vec<CubieCube> = (0..N_MOVES)
+            .map(|m| {
+                let basic = &BASIC_MOVES[m / 3];
+                let mut cube = basic.clone();
+                for _ in 0..m % 3 {
+                    cube = cube.multiply(basic);
+                }
+                cube
+            })
+            .collect();
+        let all: Vec<usize> = (0..N_MOVES).collect();
+
+        let table = |size: usize,
+                     allowed: &[usize],
+                     set: fn(&mut CubieCube, usize),
+                     get: fn(&CubieCube) -> usize| {
+            (0..size)
+                .map(|i| {
+                    let mut cube = CubieCube::SOLVED;
+                    set(&mut cube, i);
+                    let mut row = [0; N_MOVES];
+                    for &m in allowed {
+                        row[m] = get(&cube.multiply(&moves[m])) as u16;
+                    }
+                    row
+                })
+                .collect::<Vec<_>>()
+        };
+        let twist = table(N_TWIST, &all, CubieCube::set_twist, CubieCube::twist);
+        let flip = table(N_FLIP, &all, CubieCube::set_flip, CubieCube::flip);
+        let slice = table(N_SLICE, &all, CubieCube::set_slice, CubieCube::slice);
+        let corners = table(
+            N_PERM8,
+            &PHASE2_MOVES,
+            CubieCube::set_corners,
+            CubieCube::corners,
+        );
+        let ud_edges = table(
+            N_PERM8,
+            &PHASE2_MOVES,
+            CubieCube::set_ud_edges,
+            CubieCube::ud_edges,
+        );
+        let slice_perm = table(
+            N_PERM4,
+            &PHASE2_MOVES,
+            CubieCube::set_slice_perm,
+            CubieCube::slice_perm,
+        );
+
+        Self {
+            twist_slice: pruning_table(&twist, &slice, &all),
+            flip_slice: pruning_table(&flip, &slice, &all),
+            corners_slice_perm: pruning_table(&corners, &slice_perm, &PHASE2_MOVES),
+            ud_edges_slice_perm: pruning_table(&ud_edges, &slice_perm, &PHASE2_MOVES),
+            moves,
+            twist,
+            flip,
+            slice,
+            corners,
+            ud_edges,
+            slice_perm,
+        }
+    }
+}
+
+/// 两个坐标组合后到达还原状态所需的最少步数，广度优先搜索生成
+fn pruning_table(a: &[[u16; N_MOVES]], b: &[[u16; N_MOVES]], moves: &[usize]) -> Vec<u8> {
+    let mut depth = vec![u8::MAX; a.len() * b.len()];
+    let mut queue = VecDeque::from([0]);
+    depth[0] = 0;
+    while let Some(index) = queue.pop_front() {
+        let (x, y) = (index / b.len(), index % b.len());
+        for &m in moves {
+            let next = a[x][m] as usize * b.len() + b[y][m] as usize;
+            if depth[next] == u8::MAX {
+                depth[next] = depth[index] + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    depth
+}
+
+/// Kociemba 两阶段算法求解，结果不保证最短
+///
+/// 解法接在最近两次转动 `history` 之后，开头不转动上一次的面，也不与前两次转动抵消
+pub fn solve(cube: &CubieCube, history: [Option<Face>; 2]) -> Vec<Move> {
+    let mut search = Search {
+        tables: tables(),
+        cube: cube.clone(),
+        history,
+        path: Vec::new(),
+    };
+    for depth in 0..=MAX_LENGTH {
+        if search.phase1(cube.twist(), cube.flip(), cube.slice(), depth) {
+            break;
+        }
+    }
+    search.path.into_iter().map(index_move).collect()
+}
+
+struct Search<'a> {
+    tables: &'a Tables,
+    cube: CubieCube,
+    history: [Option<Face>; 2],
+    path: Vec<usize>,
+}
+
+impl Search<'_> {
+    /// 同一面不连续转动，相对的两面只按一种顺序转动；前两步还要能接在 `history` 之后
+    fn allowed(&self, m: usize) -> bool {
+        let face = m / 3;
+        if let Some(last) = self.path.last().map(|last| last / 3) {
+            if face == last || (face % 3 == last % 3 && face < last) {
+                return false;
+            }
+        }
+        let previous = match self.path[..] {
+            [] => self.history,
+            [first] => [self.history[1], Some(Face::ALL[first / 3])],
+            _ => return true,
+        };
+        follows(previous, Face::ALL[face], Face::same_axis)
+    }
+
+    /// 第一阶段：转到只需 U、D、R2、L2、F2、B2 即可还原的状态
+    fn phase1(&mut self, twist: usize, flip: usize, slice: usize, depth: usize) -> bool {
+        if depth == 0 {
+            return twist == 0 && flip == 0 && slice == 0 && self.start_phase2();
+        }
+        let t = self.tables;
+        let estimate =
+            t.twist_slice[twist * N_SLICE + slice].max(t.flip_slice[flip * N_SLICE + slice]);
+        if estimate as usize > depth {
+            return false;
+        }
+
+        for m in 0..N_MOVES {
+            if !self.allowed(m) {
+                continue;
+            }
+            self.path.push(m);
+            if self.phase1(
+                t.twist[twist][m] as usize,
+                t.flip[flip][m] as usize,
+                t.slice[slice][m] as usize,
+                depth - 1,
+            ) {
+                return true;
+            }
+            self.path.pop();
+        }
+        false
+    }
+
+    fn start_phase2(&mut self) -> bool {
+        let mut cube = self.cube.clone();
+        for &m in &self.path {
+            cube = cube.multiply(&self.tables.moves[m]);
+        }
+        let limit = MAX_LENGTH - self.path.len();
+        (0..=limit)
+            .any(|depth| self.phase2(cube.corners(), cube.ud_edges(), cube.slice_perm(), depth))
+    }
+
+    /// 第二阶段：只用 U、D、R2、L2、F2、B2 还原
+    fn phase2(&mut self, corners: usize, ud_edges: usize, slice_perm: usize, depth: usize) -> bool {
+        if depth == 0 {
+            return corners == 0 && ud_edges == 0 && slice_perm == 0;
+        }
+        let t = self.tables;
+        let estimate = t.corners_slice_perm[corners * N_PERM4 + slice_perm]
+            .max(t.ud_edges_slice_perm[ud_edges * N_PERM4 + slice_perm]);
+        if estimate as usize > depth {
+            return false;
+        }
+
+        for m in PHASE2_MOVES {
+            if !self.allowed(m) {
+                continue;
+            }
+            self.path.push(m);
+            if self.phase2(
+                t.corners[corners][m] as usize,
+                t.ud_edges[ud_edges][m] as usize,
+                t.slice_perm[slice_perm][m] as usize,
+                depth - 1,
+            ) {
+                return true;
+            }
+            self.path.pop();
+        }
+        false
+    }
+}
+
+#[test]
+fn coordinates_round_trip() {
+    let mut cube = CubieCube::SOLVED;
+    for i in [0, 1, 1000, N_TWIST - 1] {
+        cube.set_twist(i);
+        assert_eq!(cube.twist(), i);
+    }
+    for i in [0, 1, 1000, N_FLIP - 1] {
+        cube.set_flip(i);
+        assert_eq!(cube.flip(), i);
+    }
+    for i in [0, 1, 300, N_SLICE - 1] {
+        cube.set_slice(i);
+        assert_eq!(cube.slice(), i);
+    }
+    for i in [0, 1, 20000, N_PERM8 - 1] {
+        cube.set_corners(i);
+        assert_eq!(cube.corners(), i);
+    }
+}
+
+#[test]
+fn moves_match_geometry() {
+    use super::super::state::CubeState;
+    use bevy::math::Vec3;
+
+    const CORNERS: [[i32; 3]; 8] = [
+        [1, 1, 1],
+        [-1, 1, 1],
+        [-1, 1, -1],
+        [1, 1, -1],
+        [1, -1, 1],
+        [-1, -1, 1],
+        [-1, -1, -1],
+        [1, -1, -1],
+    ];
+    const EDGES: [[i32; 3]; 12] = [
+        [1, 1, 0],
+        [0, 1, 1],
+        [-1, 1, 0],
+        [0, 1, -1],
+        [1, -1, 0],
+        [0, -1, 1],
+        [-1, -1, 0],
+        [0, -1, -1],
+        [1, 0, 1],
+        [-1, 0, 1],
+        [-1, 0, -1],
+        [1, 0, -1],
+    ];
+
+    // 由几何状态推算块的排列与朝向
+    let cubie = |state: &CubeState| {
+        let mut cube = CubieCube::SOLVED;
+        let along = |v: Vec3, axis: Vec3| v.dot(axis).abs() > 0.5;
+        for piece in &state.pieces {
+            let [x, y, z] = piece.position.to_array();
+            let home = piece.home.to_array();
+            if let Some(i) = CORNERS.iter().position(|p| *p == [x, y, z]) {
+                cube.cp[i] = CORNERS.iter().position(|p| *p == home).unwrap() as u8;
+                // U/D 面贴纸所在的面，按从角外看顺时针计数
+                let sticker = piece.orientation * Vec3::new(0., home[1] as f32, 0.);
+                let p = piece.position.as_vec3();
+                let (x_face, z_face) = (Vec3::new(p.x, 0., 0.), Vec3::new(0., 0., p.z));
+                let clockwise = Vec3::new(0., p.y, 0.).cross(x_face).dot(p) < 0.;
+                let second = if clockwise { x_face } else { z_face };
+                cube.co[i] = if along(sticker, Vec3::Y) {
+                    0
+                } else if along(sticker, second) {
+                    1
+                } else {
+                    2
+                };
+            } else if let Some(i) = EDGES.iter().position(|p| *p == [x, y, z]) {
+                cube.ep[i] = EDGES.iter().position(|p| *p == home).unwrap() as u8;
+                let reference = if home[1] != 0 { Vec3::Y } else { Vec3::Z };
+                let sticker = piece.orientation * reference;
+                let face = if y != 0 { Vec3::Y } else { Vec3::Z };
+                cube.eo[i] = u8::from(!along(sticker, face));
+            }
+        }
+        cube
+    };
+
+    for face in Face::ALL {
+        for turn in Turn::ALL {
+            let m = Move::new(face, turn);
+            let mut state = CubeState::solved();
+            state.apply(m);
+            assert_eq!(cubie(&state), tables().moves[move_index(m)], "{m}");
+        }
+    }
+}
+
+#[test]
+fn solves_random_state() {
+    use rand_core::SeedableRng;
+
+    let mut rng = bevy_prng::WyRand::seed_from_u64(42);
+    let histories = [
+        [None, None],
+        [None, Some(Face::Up)],
+        [Some(Face::Right), Some(Face::Left)],
+    ];
+    for history in histories {
+        let target = CubieCube::random(&mut rng);
+        assert_eq!(target.multiply(&target.inverse()), CubieCube::SOLVED);
+        let solution = solve(&target, history);
+        assert!(solution.len() <= MAX_LENGTH);
+        let mut cube = target.clone();
+        for m in &solution {
+            cube.apply(*m);
+        }
+        assert_eq!(cube, CubieCube::SOLVED);
+        let mut previous = history;
+        for m in &solution {
+            assert!(
+                follows(previous, m.face, Face::same_axis),
+                "{m} after {previous:?}"
+            );
+            previous = [previous[1], Some(m.face)];
+        }
+    }
+}
+
+#[test]
+fn random_state_reaches_target() {
+    use rand_core::SeedableRng;
+
+    let history = [Some(Face::Right), Some(Face::Up)];
+    let moves = super::random_state(&mut bevy_prng::WyRand::seed_from_u64(11), history);
+    assert!(follows(history, moves[0].face, Face::same_axis));
+
+    // 从还原状态执行这批转动，得到由同一种子选取的随机状态
+    let mut cube = CubieCube::SOLVED;
+    for m in &moves {
+        cube.apply(*m);
+    }
+    let target = CubieCube::random(&mut bevy_prng::WyRand::seed_from_u64(11));
+    assert_eq!(cube, target);
+}
@@ -0,0 +1,496 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+use image::RgbaImage;
+use ttf_parser::OutlineBuilder;
+
+use super::{
+    face::{face_mesh, MESH_FACES},
+    gradient_mesh, piece_material, piece_mesh, rotation_of_cube,
+    state::CubeState,
+    LIGHT_OFFSET, LOCAL_CORNER, TEXTURE_SIZE, TEXT_SIZE,
+};
+use crate::{
+    font::BUNDLED_FONT,
+    graphics::{theme::Theme, view::ViewAngle},
+};
+
+/// 与默认透视相机一致的垂直视角
+const FOV: f32 = PI / 4.;
+/// 魔方中心到相机的距离
+const CUBE_DISTANCE: f32 = 10.;
+/// 环境光占比
+const AMBIENT: f32 = 0.15;
+/// 画面的超采样倍数
+const SUPERSAMPLE: usize = 2;
+/// 文字的超采样倍数
+const TEXT_SUPERSAMPLE: usize = 4;
+/// 曲线展开为折线的段数
+const CURVE_STEPS: usize = 8;
+
+/// 不依赖 GPU 渲染魔方，用于无显卡环境下的快照测试与 `snapshot` 子命令
+///
+/// 相机位于原点朝 -Z 方向，魔方的朝向与光源位置和运行时一致；
+/// 半透明三角形按深度从远到近混合，文字使用内置字体绘制
+pub fn render_snapshot(state: &CubeState, text: &str, theme: &Theme, size: u32) -> RgbaImage {
+    let cube_translation = Vec3::new(0., 0., -CUBE_DISTANCE);
+    let cube_rotation = rotation_of_cube(cube_translation, ViewAngle::Corner);
+    let cube_transform = Transform::from_translation(cube_translation).with_rotation(cube_rotation);
+    let light = cube_translation + cube_rotation * -LOCAL_CORNER.normalize() * LIGHT_OFFSET;
+
+    let mut triangles = Vec::new();
+    let puzzle = state.puzzle.puzzle();
+    let gradient = gradient_mesh(theme.piece.vertex_alpha);
+    for piece in &state.pieces {
+        let mesh = piece_mesh(theme, puzzle, piece.home);
+        let transform = cube_transform * puzzle.piece_transform(piece.position, piece.orientation);
+        let base_color = piece_material(theme, puzzle, piece.home).base_color;
+        let mesh = mesh.as_ref().unwrap_or(&gradient);
+        push_mesh(&mut triangles, mesh, &transform, base_color, false);
+    }
+    // 外层的各个面按谜题的外形摆放
+    for (index, face) in MESH_FACES.into_iter().enumerate() {
+        if let Some(transform) = puzzle.face_transform(face) {
+            let transform = cube_transform * transform;
+            push_mesh(
+                &mut triangles,
+                &face_mesh(index),
+                &transform,
+                Color::WHITE,
+                true,
+            );
+        }
+    }
+
+    // 从远到近绘制
+    triangles.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+
+    let texture = TextTexture::new(text, theme.text.color);
+    let mut target = Target::new(size as usize * SUPERSAMPLE);
+    for triangle in &triangles {
+        target.draw(triangle, light, &texture);
+    }
+    target.resolve()
+}
+
+struct Vertex {
+    position: Vec3,
+    color: LinearRgba,
+    uv: Vec2,
+}
+
+struct Triangle {
+    vertices: [Vertex; 3],
+    textured: bool,
+    depth: f32,
+}
+
+/// 将网格变换到相机空间，顶点色与材质颜色相乘
+fn push_mesh(
+    triangles: &mut Vec<Triangle>,
+    mesh: &Mesh,
+    transform: &Transform,
+    base_color: Color,
+    textured: bool,
+) {
+    let Some(positions) = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .and_then(|values| values.as_float3())
+    else {
+        return;
+    };
+    let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) => Some(colors),
+        _ => None,
+    };
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => Some(uvs),
+        _ => None,
+    };
+    let base_color = base_color.to_linear();
+
+    let vertex = |i: usize| Vertex {
+        position: transform.transform_point(Vec3::from(positions[i])),
+        color: colors.map_or(base_color, |colors| {
+            let [r, g, b, a] = colors[i];
+            LinearRgba::new(
+                base_color.red * r,
+                base_color.green * g,
+                base_color.blue * b,
+                base_color.alpha * a,
+            )
+        }),
+        uv: uvs.map_or(Vec2::ZERO, |uvs| Vec2::from(uvs[i])),
+    };
+
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    for corner in indices.chunks_exact(3) {
+        let vertices = [vertex(corner[0]), vertex(corner[1]), vertex(corner[2])];
+        let depth = vertices.iter().map(|v| v.position.z).sum::<f32>() / 3.;
+        triangles.push(Triangle {
+            vertices,
+            textured,
+            depth,
+        });
+    }
+}
+
+/// 预乘透明度的线性颜色缓冲
+struct Target {
+    size: usize,
+    pixels: Vec<LinearRgba>,
+}
+
+impl Target {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            pixels: vec![LinearRgba::NONE; size * size],
+        }
+    }
+
+    fn draw(&mut self, triangle: &Triangle, light: Vec3, texture: &TextTexture) {
+        let [a, b, c] = triangle.vertices.each_ref().map(|v| v.position);
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        // 背面剔除
+        if normal.dot(a) >= 0. {
+            return;
+        }
+        let centroid = (a + b + c) / 3.;
+        let lambert = normal.dot((light - centroid).normalize()).max(0.);
+        let shade = AMBIENT + (1. - AMBIENT) * lambert;
+
+        let screen = triangle
+            .vertices
+            .each_ref()
+            .map(|v| self.project(v.position));
+        let area = edge(screen[0], screen[1], screen[2]);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+        let min = screen[0].min(screen[1]).min(screen[2]).max(Vec2::ZERO);
+        let max = screen[0]
+            .max(screen[1])
+            .max(screen[2])
+            .min(Vec2::splat(self.size as f32));
+
+        for y in min.y.floor() as usize..max.y.ceil() as usize {
+            for x in min.x.floor() as usize..max.x.ceil() as usize {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [
+                    edge(screen[1], screen[2], p) / area,
+                    edge(screen[2], screen[0], p) / area,
+                    edge(screen[0], screen[1], p) / area,
+                ];
+                if weights.iter().any(|w| *w < 0.) {
+                    continue;
+                }
+
+                // 透视校正插值
+                let weights = [0, 1, 2].map(|i| weights[i] / -triangle.vertices[i].position.z);
+                let total: f32 = weights.iter().sum();
+                let mut color = LinearRgba::NONE;
+                let mut uv = Vec2::ZERO;
+                for (vertex, weight) in triangle.vertices.iter().zip(weights) {
+                    color += vertex.color * (weight / total);
+                    uv += vertex.uv * (weight / total);
+                }
+                if triangle.textured {
+                    let texel = texture.sample(uv);
+                    color = LinearRgba::new(
+                        color.red * texel.red,
+                        color.green * texel.green,
+                        color.blue * texel.blue,
+                        color.alpha * texel.alpha,
+                    );
+                }
+
+                let alpha = color.alpha;
+                let pixel = &mut self.pixels[y * self.size + x];
+                *pixel = LinearRgba::new(
+                    color.red * shade * alpha,
+                    color.green * shade * alpha,
+                    color.blue * shade * alpha,
+                    alpha,
+                ) + *pixel * (1. - alpha);
+            }
+        }
+    }
+
+    fn project(&self, position: Vec3) -> Vec2 {
+        let ndc = position.xy() / (-position.z * (FOV / 2.).tan());
+        Vec2::new(ndc.x + 1., 1. - ndc.y) / 2. * self.size as f32
+    }
+
+    /// 缩小超采样并转换为 sRGB
+    fn resolve(&self) -> RgbaImage {
+        let size = self.size / SUPERSAMPLE;
+        RgbaImage::from_fn(size as u32, size as u32, |x, y| {
+            let mut sum = LinearRgba::NONE;
+            for dy in 0..SUPERSAMPLE {
+                for dx in 0..SUPERSAMPLE {
+                    let (x, y) = (x as usize * SUPERSAMPLE + dx, y as usize * SUPERSAMPLE + dy);
+                    sum += self.pixels[y * self.size + x];
+                }
+            }
+            let sum = sum / (SUPERSAMPLE * SUPERSAMPLE) as f32;
+            if sum.alpha <= 0. {
+                return image::Rgba([0; 4]);
+            }
+            let color = LinearRgba::new(
+                sum.red / sum.alpha,
+                sum.green / sum.alpha,
+                sum.blue / sum.alpha,
+                sum.alpha,
+            );
+            image::Rgba(Srgba::from(color).to_u8_array())
+        })
+    }
+}
+
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b - a).perp_dot(p - a)
+}
+
+/// 报时纹理，与运行时一样居中并旋转 45°
+struct TextTexture {
+    coverage: Vec<f32>,
+    color: LinearRgba,
+}
+
+impl TextTexture {
+    fn new(text: &str, color: Color) -> Self {
+        let size = TEXTURE_SIZE as usize;
+        let mut coverage = vec![0.; size * size];
+
+        let edges = layout(text);
+        if !edges.is_empty() {
+            let min = edges
+                .iter()
+                .fold(Vec2::INFINITY, |min, (a, b)| min.min(*a).min(*b))
+                .floor()
+                - 1.;
+            let max = edges
+                .iter()
+                .fold(Vec2::NEG_INFINITY, |max, (a, b)| max.max(*a).max(*b))
+                .ceil()
+                + 1.;
+            let (width, height) = ((max.x - min.x) as usize, (max.y - min.y) as usize);
+            let edges: Vec<_> = edges.iter().map(|(a, b)| (*a - min, *b - min)).collect();
+            let text = fill(&edges, width, height);
+
+            // 绕纹理中心旋转，按旋转后的位置反查文字
+            let rotation = Mat2::from_angle(-PI / 4.);
+            let center = Vec2::splat(size as f32 / 2.);
+            for y in 0..size {
+                for x in 0..size {
+                    let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center;
+                    let p = rotation * p - min;
+                    coverage[y * size + x] = bilinear(&text, width, height, p);
+                }
+            }
+        }
+
+        Self {
+            coverage,
+            color: color.to_linear(),
+        }
+    }
+
+    fn sample(&self, uv: Vec2) -> LinearRgba {
+        let size = TEXTURE_SIZE as usize;
+        let coverage = bilinear(&self.coverage, size, size, uv * size as f32);
+        self.color.with_alpha(self.color.alpha * coverage)
+    }
+}
+
+/// 按像素中心双线性采样，超出范围视为 0
+fn bilinear(values: &[f32], width: usize, height: usize, p: Vec2) -> f32 {
+    let p = p - 0.5;
+    let base = p.floor();
+    let t = p - base;
+    let get = |dx: i32, dy: i32| {
+        let (x, y) = (base.x as i32 + dx, base.y as i32 + dy);
+        if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+            0.
+        } else {
+            values[y as usize * width + x as usize]
+        }
+    };
+    let top = get(0, 0).lerp(get(1, 0), t.x);
+    let bottom = get(0, 1).lerp(get(1, 1), t.x);
+    top.lerp(bottom, t.y)
+}
+
+/// 将文字排版为以原点为中心的轮廓边（像素坐标，y 向下）
+fn layout(text: &str) -> Vec<(Vec2, Vec2)> {
+    let face = ttf_parser::Face::parse(BUNDLED_FONT, 0).expect("内置字体格式错误");
+    let scale = TEXT_SIZE / face.units_per_em() as f32;
+    let glyphs: Vec<_> = text
+        .chars()
+        .map(|c| face.glyph_index(c).unwrap_or_default())
+        .collect();
+    let width: f32 = glyphs
+        .iter()
+        .map(|glyph| face.glyph_hor_advance(*glyph).unwrap_or(0) as f32 * scale)
+        .sum();
+    let middle = (face.ascender() + face.descender()) as f32 / 2. * scale;
+
+    let mut outline = Outline {
+        edges: Vec::new(),
+        offset: Vec2::new(-width / 2., middle),
+        scale,
+        start: Vec2::ZERO,
+        last: Vec2::ZERO,
+    };
+    for glyph in glyphs {
+        face.outline_glyph(glyph, &mut outline);
+        outline.offset.x += face.glyph_hor_advance(glyph).unwrap_or(0) as f32 * scale;
+    }
+    outline.edges
+}
+
+/// 扫描线填充轮廓（非零环绕规则），返回每个像素的覆盖率
+fn fill(edges: &[(Vec2, Vec2)], width: usize, height: usize) -> Vec<f32> {
+    let mut coverage = vec![0.; width * height];
+    let step = 1. / TEXT_SUPERSAMPLE as f32;
+    let mut crossings = Vec::new();
+
+    for row in 0..height * TEXT_SUPERSAMPLE {
+        let y = (row as f32 + 0.5) * step;
+        crossings.clear();
+        for (a, b) in edges {
+            if (a.y <= y) != (b.y <= y) {
+                let x = a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x);
+                crossings.push((x, if b.y > a.y { 1 } else { -1 }));
+            }
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut winding = 0;
+        for pair in crossings.windows(2) {
+            winding += pair[0].1;
+            if winding == 0 {
+                continue;
+            }
+            let limit = (width * TEXT_SUPERSAMPLE) as f32;
+            let start = (pair[0].0 / step - 0.5).ceil().clamp(0., limit) as usize;
+            let end = (pair[1].0 / step - 0.5).ceil().clamp(0., limit) as usize;
+            for column in start..end {
+                coverage[row / TEXT_SUPERSAMPLE * width + column / TEXT_SUPERSAMPLE] += step * step;
+            }
+        }
+    }
+    coverage
+}
+
+/// 收集字形轮廓，曲线展开为折线
+struct Outline {
+    edges: Vec<(Vec2, Vec2)>,
+    offset: Vec2,
+    scale: f32,
+    start: Vec2,
+    last: Vec2,
+}
+
+impl Outline {
+    fn point(&self, x: f32, y: f32) -> Vec2 {
+        self.offset + Vec2::new(x, -y) * self.scale
+    }
+
+    fn push(&mut self, to: Vec2) {
+        self.edges.push((self.last, to));
+        self.last = to;
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.push(to);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (self.last, self.point(x1, y1), self.point(x, y));
+        for i in 1..=CURVE_STEPS {
+            let t = i as f32 / CURVE_STEPS as f32;
+            let s = 1. - t;
+            self.push(p0 * s * s + p1 * 2. * s * t + p2 * t * t);
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, p1, p2, p3) = (
+            self.last,
+            self.point(x1, y1),
+            self.point(x2, y2),
+            self.point(x, y),
+        );
+        for i in 1..=CURVE_STEPS {
+            let t = i as f32 / CURVE_STEPS as f32;
+            let s = 1. - t;
+            self.push(p0 * s * s * s + p1 * 3. * s * s * t + p2 * 3. * s * t * t + p3 * t * t * t);
+        }
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.push(self.start);
+        }
+    }
+}
+
+/// 与金标准图片比较，`UPDATE_SNAPSHOTS=1` 时重新生成
+#[cfg(test)]
+fn assert_snapshot(name: &str, image: &RgbaImage) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image.save(&path).unwrap();
+        return;
+    }
+
+    let golden = image::open(&path)
+        .unwrap_or_else(|err| panic!("{path:?}: {err}, run with UPDATE_SNAPSHOTS=1"))
+        .to_rgba8();
+    assert_eq!(golden.dimensions(), image.dimensions(), "{name}");
+    // 允许少量像素因浮点误差略有不同
+    let different = golden
+        .pixels()
+        .zip(image.pixels())
+        .filter(|(a, b)| a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > 8))
+        .count();
+    let ratio = different as f32 / (golden.width() * golden.height()) as f32;
+    assert!(ratio < 0.01, "{name}: {:.2}% pixels differ", ratio * 100.);
+}
+
+#[test]
+fn snapshot_solved_glass() {
+    let theme = crate::graphics::Themes::builtin();
+    let image = render_snapshot(
+        &CubeState::solved(),
+        "12:34",
+        theme.get("glass").unwrap(),
+        256,
+    );
+    assert_snapshot("solved-glass", &image);
+}
+
+#[test]
+fn snapshot_scrambled_classic() {
+    let theme = crate::graphics::Themes::builtin();
+    let mut state = CubeState::solved();
+    state.apply_sequence("F R U' B2 L D'").unwrap();
+    let image = render_snapshot(&state, "08:05", theme.get("classic").unwrap(), 256);
+    assert_snapshot("scrambled-classic", &image);
+}
@@ -0,0 +1,415 @@
+use std::fmt;
+
+use bevy::math::{IVec3, Quat};
+use thiserror::Error;
+
+use super::puzzle::PuzzleKind;
+
+/// 魔方的阶数 N
+///
+/// 方块的逻辑坐标关于中心对称：奇数阶为 -k..=k（k = (N-1)/2），偶数阶为 -(N-1)..=(N-1) 中的奇数，
+/// 绕中心转动 90° 后仍在网格上，三阶与原先一样为 -1..=1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Order(u32);
+
+impl Default for Order {
+    fn default() -> Self {
+        Self(3)
+    }
+}
+
+impl Order {
+    pub const MIN: u32 = 2;
+    pub const MAX: u32 = 7;
+
+    pub fn new(n: u32) -> Option<Self> {
+        (Self::MIN..=Self::MAX).contains(&n).then_some(Self(n))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// 相邻两层的坐标差
+    fn step(self) -> i32 {
+        2 - (self.0 % 2) as i32
+    }
+
+    /// 最外层的坐标
+    pub fn max(self) -> i32 {
+        (self.0 as i32 - 1) * self.step() / 2
+    }
+
+    /// 各层的坐标，从负方向到正方向
+    pub fn coords(self) -> impl Iterator<Item = i32> {
+        (-self.max()..=self.max()).step_by(self.step() as usize)
+    }
+
+    /// 坐标为 `coord` 的层从正方向外侧数起的序号，最外层为 1
+    pub fn layer(self, coord: i32) -> u32 {
+        ((self.max() - coord) / self.step()) as u32 + 1
+    }
+
+    /// 一个坐标单位对应的层数
+    pub fn spacing(self) -> f32 {
+        1. / self.step() as f32
+    }
+
+    /// 位于 `position` 的方块朝外的方向，各分量为 -1、0 或 1
+    pub fn outer(self, position: IVec3) -> IVec3 {
+        position / self.max()
+    }
+}
+
+// 旋转面枚举
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Face {
+    Front,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::Up,
+        Face::Right,
+        Face::Front,
+        Face::Down,
+        Face::Left,
+        Face::Back,
+    ];
+
+    /// 面的外法线
+    pub fn normal(self) -> IVec3 {
+        match self {
+            Face::Front => IVec3::Z,
+            Face::Back => IVec3::NEG_Z,
+            Face::Left => IVec3::NEG_X,
+            Face::Right => IVec3::X,
+            Face::Up => IVec3::Y,
+            Face::Down => IVec3::NEG_Y,
+        }
+    }
+
+    /// 两个面是否绕同一根轴转动（相同或相对的面）
+    pub fn same_axis(self, other: Face) -> bool {
+        self.normal().abs() == other.normal().abs()
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            Face::Front => 'F',
+            Face::Back => 'B',
+            Face::Left => 'L',
+            Face::Right => 'R',
+            Face::Up => 'U',
+            Face::Down => 'D',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Face> {
+        match c {
+            'F' => Some(Face::Front),
+            'B' => Some(Face::Back),
+            'L' => Some(Face::Left),
+            'R' => Some(Face::Right),
+            'U' => Some(Face::Up),
+            'D' => Some(Face::Down),
+            _ => None,
+        }
+    }
+
+    /// 小写字母表示转动外侧两层
+    fn from_wide_char(c: char) -> Option<Face> {
+        c.is_ascii_lowercase()
+            .then(|| Face::from_char(c.to_ascii_uppercase()))
+            .flatten()
+    }
+}
+
+/// 转动幅度
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Turn {
+    /// 顺时针 90°
+    Quarter,
+    /// 180°
+    Half,
+    /// 逆时针 90°
+    Prime,
+}
+
+impl Turn {
+    pub const ALL: [Turn; 3] = [Turn::Quarter, Turn::Half, Turn::Prime];
+
+    /// 顺时针转动的步数，魔方一步为 90°
+    pub fn steps(self) -> i32 {
+        match self {
+            Turn::Quarter => 1,
+            Turn::Half => 2,
+            Turn::Prime => -1,
+        }
+    }
+}
+
+/// 一次转动，顺时针以正对该面观察为准；字母与层号的含义由 [`Puzzle`](super::puzzle::Puzzle) 决定
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Move {
+    pub face: Face,
+    pub turn: Turn,
+    /// 转动的最内层，从该面数起，最外层为 1
+    pub depth: u32,
+    /// 同时转动 `depth` 外侧的所有层
+    pub wide: bool,
+}
+
+impl Move {
+    pub fn new(face: Face, turn: Turn) -> Self {
+        Self {
+            face,
+            turn,
+            depth: 1,
+            wide: false,
+        }
+    }
+
+    /// 转动第 `depth` 层，`wide` 时连同外侧各层一起转动
+    pub fn with_layers(self, depth: u32, wide: bool) -> Self {
+        Self {
+            depth,
+            wide,
+            ..self
+        }
+    }
+
+    #[cfg(test)]
+    pub fn inverse(self) -> Move {
+        let turn = match self.turn {
+            Turn::Quarter => Turn::Prime,
+            Turn::Half => Turn::Half,
+            Turn::Prime => Turn::Quarter,
+        };
+        Move { turn, ..self }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match self.turn {
+            Turn::Quarter => "",
+            Turn::Half => "2",
+            Turn::Prime => "'",
+        };
+        match (self.depth, self.wide) {
+            (1, false) => {}
+            (2, true) => {}
+            (depth, _) => write!(f, "{depth}")?,
+        }
+        let wide = if self.wide { "w" } else { "" };
+        write!(f, "{}{wide}{suffix}", self.face.letter())
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum NotationError {
+    #[error("unknown move `{0}`")]
+    UnknownMove(char),
+    #[error("missing face after layer number {0}")]
+    MissingFace(u32),
+    #[error("invalid layer number {0}")]
+    InvalidLayer(String),
+    #[error("move `{0}` is not possible on a {1}")]
+    Unsupported(String, String),
+}
+
+/// 解析 `R U2 F'` 这样的转动序列，空白可以省略
+///
+/// 高阶魔方可以在前面加层号：`3R` 只转第三层，`3Rw` 转外侧三层，`Rw` 与 `r` 转外侧两层
+pub fn parse_moves(notation: &str) -> Result<Vec<Move>, NotationError> {
+    let mut moves = Vec::new();
+    let mut chars = notation.chars().peekable();
+    while let Some(mut c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut prefix = None;
+        let mut digits = String::new();
+        while let Some(digit) = c.to_digit(10) {
+            digits.push(c);
+            // 层号超出 u32 时视为无效，不回绕
+            let number = prefix
+                .unwrap_or(0u32)
+                .checked_mul(10)
+                .and_then(|number| number.checked_add(digit))
+                .ok_or_else(|| NotationError::InvalidLayer(digits.clone()))?;
+            prefix = Some(number);
+            c = chars.next().ok_or(NotationError::MissingFace(number))?;
+        }
+        let (face, mut wide) = match Face::from_char(c) {
+            Some(face) => (face, false),
+            None => (
+                Face::from_wide_char(c).ok_or(NotationError::UnknownMove(c))?,
+                true,
+            ),
+        };
+        if !wide && chars.peek() == Some(&'w') {
+            chars.next();
+            wide = true;
+        }
+        let depth = match prefix {
+            Some(0) => return Err(NotationError::InvalidLayer(digits)),
+            Some(depth) => depth,
+            None if wide => 2,
+            None => 1,
+        };
+        let turn = match chars.peek() {
+            Some('2') => Turn::Half,
+            Some('\'' | '’') => Turn::Prime,
+            _ => Turn::Quarter,
+        };
+        if turn != Turn::Quarter {
+            chars.next();
+        }
+        // `R2'` 与 `R2` 相同
+        if turn == Turn::Half && matches!(chars.peek(), Some('\'' | '’')) {
+            chars.next();
+        }
+        moves.push(Move::new(face, turn).with_layers(depth, wide));
+    }
+    Ok(moves)
+}
+
+/// 单个方块的逻辑状态
+#[derive(Clone, Debug, PartialEq)]
+pub struct PieceState {
+    /// 初始坐标，决定方块的颜色
+    pub home: IVec3,
+    pub position: IVec3,
+    pub orientation: Quat,
+}
+
+/// 与渲染无关的逻辑状态，方块的坐标与转动由 [`Puzzle`](super::puzzle::Puzzle) 决定
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeState {
+    pub puzzle: PuzzleKind,
+    pub pieces: Vec<PieceState>,
+}
+
+impl CubeState {
+    /// 还原状态的三阶魔方
+    #[cfg(test)]
+    pub fn solved() -> Self {
+        Self::solved_with(PuzzleKind::default())
+    }
+
+    /// 还原状态的谜题
+    pub fn solved_with(puzzle: PuzzleKind) -> Self {
+        let pieces = puzzle
+            .puzzle()
+            .homes()
+            .into_iter()
+            .map(|home| PieceState {
+                home,
+                position: home,
+                orientation: Quat::IDENTITY,
+            })
+            .collect();
+        Self { puzzle, pieces }
+    }
+
+    pub fn apply(&mut self, m: Move) {
+        let puzzle = self.puzzle.puzzle();
+        for piece in self
+            .pieces
+            .iter_mut()
+            .filter(|p| puzzle.contains(m, p.position))
+        {
+            piece.position = puzzle.turn_position(m, piece.position);
+            piece.orientation = (puzzle.rotation(m) * piece.orientation).normalize();
+        }
+    }
+
+    /// 依次执行 `R U2 F'` 这样的转动序列
+    pub fn apply_sequence(&mut self, notation: &str) -> Result<(), NotationError> {
+        for m in self.puzzle.puzzle().parse_moves(notation)? {
+            self.apply(m);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn inverse_restores_cube() {
+    let mut state = CubeState::solved();
+    state.apply_sequence("RRRR").unwrap();
+    state.apply_sequence("R U2 F' F U2 R'").unwrap();
+    for piece in &state.pieces {
+        assert_eq!(piece.position, piece.home);
+        assert!(
+            piece.orientation.abs_diff_eq(Quat::IDENTITY, 1e-4)
+                || piece.orientation.abs_diff_eq(-Quat::IDENTITY, 1e-4)
+        );
+    }
+}
+
+#[test]
+fn parse_notation() {
+    use super::puzzle::Puzzle;
+
+    let moves = parse_moves("R U2 F'D2'").unwrap();
+    let text: Vec<String> = moves.iter().map(Move::to_string).collect();
+    assert_eq!(text, ["R", "U2", "F'", "D2"]);
+    assert_eq!(parse_moves("RX"), Err(NotationError::UnknownMove('X')));
+
+    // 顺时针转动 R 时右上前角块移到右上后
+    assert_eq!(
+        Order::default().turn_position(Move::new(Face::Right, Turn::Quarter), IVec3::ONE),
+        IVec3::new(1, 1, -1)
+    );
+}
+
+#[test]
+fn big_cube_moves() {
+    use super::puzzle::Puzzle;
+
+    let moves = parse_moves("3Rw' r2 2U Lw 1F").unwrap();
+    let text: Vec<String> = moves.iter().map(Move::to_string).collect();
+    assert_eq!(text, ["3Rw'", "Rw2", "2U", "Lw", "F"]);
+    // 空白把层号与前一个转动分开
+    assert_eq!(parse_moves("R 2U").unwrap().len(), 2);
+    assert_eq!(parse_moves("3"), Err(NotationError::MissingFace(3)));
+    assert_eq!(
+        parse_moves("0R"),
+        Err(NotationError::InvalidLayer("0".to_string()))
+    );
+    assert!(matches!(
+        parse_moves("99999999999R"),
+        Err(NotationError::InvalidLayer(_))
+    ));
+
+    for n in Order::MIN..=Order::MAX {
+        let order = Order::new(n).unwrap();
+        let mut state = CubeState::solved_with(PuzzleKind::Cube(order));
+        let n = n as usize;
+        assert_eq!(state.pieces.len(), n.pow(3) - n.saturating_sub(2).pow(3));
+        // 各层都随转动移动，逆序转回后还原
+        let sequence = format!("R {n}U2 2Fw' {n}Lw");
+        state.apply_sequence(&sequence).unwrap();
+        assert_ne!(state, CubeState::solved_with(PuzzleKind::Cube(order)));
+        state
+            .apply_sequence(&format!("{n}Lw' 2Fw {n}U2 R'"))
+            .unwrap();
+        for piece in &state.pieces {
+            assert_eq!(piece.position, piece.home);
+        }
+    }
+    assert!(CubeState::solved().apply_sequence("4R").is_err());
+
+    // 四阶的 2R 只带动第二层
+    let order = Order::new(4).unwrap();
+    let slice = Move::new(Face::Right, Turn::Quarter).with_layers(2, false);
+    assert!(order.contains(slice, IVec3::new(1, 3, 3)));
+    assert!(!order.contains(slice, IVec3::new(3, 3, 3)));
+}
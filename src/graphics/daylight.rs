@@ -0,0 +1,225 @@
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use time::{Date, OffsetDateTime};
+
+/// 未配置经纬度时使用的日出日落时间（分钟）
+const DEFAULT_SUNRISE: f32 = 6. * 60.;
+const DEFAULT_SUNSET: f32 = 18. * 60.;
+/// 日出日落前后的过渡时长（分钟）
+const TWILIGHT: f32 = 45.;
+/// 夜间的暖色灯光
+const NIGHT_LIGHT: Color = Color::srgb(1.0, 0.72, 0.45);
+/// 夜间灯光亮度相对白天的比例
+const NIGHT_LIGHT_SCALE: f32 = 0.35;
+/// 夜间泛光强度相对白天的比例
+const NIGHT_BLOOM_SCALE: f32 = 0.5;
+
+/// 按本地时间调整灯光、泛光、方块与文字颜色，夜间更暗更暖
+///
+/// 环境变量 `TIME_FLY_LOCATION=纬度,经度` 用于计算当天的日出日落时间，
+/// `TIME_FLY_DAYLIGHT=off` 关闭调整
+pub struct DaylightPlugin;
+
+impl Plugin for DaylightPlugin {
+    fn build(&self, app: &mut App) {
+        let enabled = std::env::var("TIME_FLY_DAYLIGHT").as_deref() != Ok("off");
+        let location = std::env::var("TIME_FLY_LOCATION")
+            .ok()
+            .and_then(|value| parse_location(&value));
+        let mut daylight = Daylight {
+            enabled,
+            location,
+            factor: 1.,
+        };
+        daylight.factor = daylight.current_factor();
+
+        app.insert_resource(daylight)
+            .add_systems(
+                Update,
+                update_daylight.run_if(on_timer(Duration::from_secs(60))),
+            )
+            .add_systems(Update, apply_light.run_if(resource_changed::<Daylight>));
+    }
+}
+
+/// 白天程度，1 为白天，0 为夜晚
+#[derive(Resource)]
+pub struct Daylight {
+    enabled: bool,
+    /// 纬度、经度（度）
+    location: Option<(f32, f32)>,
+    factor: f32,
+}
+
+impl Daylight {
+    /// 将灯光原本的颜色向夜间暖光过渡
+    pub fn light_color(&self, color: Color) -> Color {
+        color.mix(&NIGHT_LIGHT, 1. - self.factor)
+    }
+
+    pub fn light_scale(&self) -> f32 {
+        NIGHT_LIGHT_SCALE.lerp(1., self.factor)
+    }
+
+    pub fn bloom_scale(&self) -> f32 {
+        NIGHT_BLOOM_SCALE.lerp(1., self.factor)
+    }
+
+    /// 将颜色向夜间暖色偏移
+    pub fn tint(&self, color: Color) -> Color {
+        color.mix(&NIGHT_LIGHT, (1. - self.factor) * 0.3)
+    }
+
+    /// 按当前时间计算白天程度，未启用或无法获取本地时间时保持原值
+    fn current_factor(&self) -> f32 {
+        if !self.enabled {
+            return self.factor;
+        }
+        let Ok(now) = OffsetDateTime::now_local() else {
+            return self.factor;
+        };
+
+        let minutes = now.hour() as f32 * 60. + now.minute() as f32 + now.second() as f32 / 60.;
+        let sun = match self.location {
+            Some((latitude, longitude)) => {
+                let offset = now.offset().whole_seconds() as f32 / 60.;
+                sun_times(now.date(), latitude, longitude, offset)
+            }
+            None => SunTimes::Normal(DEFAULT_SUNRISE, DEFAULT_SUNSET),
+        };
+        daylight_factor(minutes, sun)
+    }
+}
+
+/// 只在数值变化时写入，避免每分钟都触发 `apply_light`
+fn update_daylight(daylight: ResMut<Daylight>) {
+    let factor = daylight.current_factor();
+    daylight
+        .map_unchanged(|daylight| &mut daylight.factor)
+        .set_if_neq(factor);
+}
+
+/// 灯光创建时的颜色与亮度，按日光调整时以此为准
+#[derive(Component, Clone, Copy)]
+struct ConfiguredLight {
+    color: Color,
+    intensity: f32,
+}
+
+fn apply_light(
+    mut commands: Commands,
+    daylight: Res<Daylight>,
+    mut lights: Query<(Entity, &mut PointLight, Option<&ConfiguredLight>)>,
+) {
+    for (entity, mut light, configured) in lights.iter_mut() {
+        let configured = match configured {
+            Some(configured) => *configured,
+            None => {
+                let configured = ConfiguredLight {
+                    color: light.color,
+                    intensity: light.intensity,
+                };
+                commands.entity(entity).insert(configured);
+                configured
+            }
+        };
+        light.color = daylight.light_color(configured.color);
+        light.intensity = configured.intensity * daylight.light_scale();
+    }
+}
+
+/// 当天的日出日落（本地时间，当天零点起的分钟数）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SunTimes {
+    Normal(f32, f32),
+    /// 极昼
+    AlwaysUp,
+    /// 极夜
+    AlwaysDown,
+}
+
+/// 根据 NOAA 的近似公式计算日出日落时间，offset 为本地时区相对 UTC 的分钟数
+fn sun_times(date: Date, latitude: f32, longitude: f32, offset: f32) -> SunTimes {
+    let gamma = 2. * PI / 365. * (date.ordinal() as f32 - 1.);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2. * gamma).cos()
+            - 0.040849 * (2. * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2. * gamma).cos()
+        + 0.000907 * (2. * gamma).sin()
+        - 0.002697 * (3. * gamma).cos()
+        + 0.00148 * (3. * gamma).sin();
+
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = 90.833_f32.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+    if cos_hour_angle > 1. {
+        return SunTimes::AlwaysDown;
+    }
+    if cos_hour_angle < -1. {
+        return SunTimes::AlwaysUp;
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    let to_local = |utc: f32| (utc + offset).rem_euclid(24. * 60.);
+    SunTimes::Normal(
+        to_local(720. - 4. * (longitude + hour_angle) - equation_of_time),
+        to_local(720. - 4. * (longitude - hour_angle) - equation_of_time),
+    )
+}
+
+/// 日出日落前后平滑过渡
+fn daylight_factor(minutes: f32, sun: SunTimes) -> f32 {
+    let (sunrise, sunset) = match sun {
+        SunTimes::Normal(sunrise, sunset) => (sunrise, sunset),
+        SunTimes::AlwaysUp => return 1.,
+        SunTimes::AlwaysDown => return 0.,
+    };
+
+    // 距离某时刻的有向分钟数，跨越零点时取较近的一侧
+    let since = |time: f32| (minutes - time + 12. * 60.).rem_euclid(24. * 60.) - 12. * 60.;
+    let ramp = |t: f32| {
+        let t = ((t + TWILIGHT) / (2. * TWILIGHT)).clamp(0., 1.);
+        t * t * (3. - 2. * t)
+    };
+    let after_sunrise = since(sunrise);
+    let after_sunset = since(sunset);
+    if after_sunrise.abs() <= TWILIGHT {
+        ramp(after_sunrise)
+    } else if after_sunset.abs() <= TWILIGHT {
+        1. - ramp(after_sunset)
+    } else {
+        let day_length = (sunset - sunrise).rem_euclid(24. * 60.);
+        if (minutes - sunrise).rem_euclid(24. * 60.) < day_length {
+            1.
+        } else {
+            0.
+        }
+    }
+}
+
+fn parse_location(value: &str) -> Option<(f32, f32)> {
+    let (latitude, longitude) = value.split_once(',')?;
+    let latitude: f32 = latitude.trim().parse().ok()?;
+    let longitude: f32 = longitude.trim().parse().ok()?;
+    ((-90.0..=90.).contains(&latitude) && (-180.0..=180.).contains(&longitude))
+        .then_some((latitude, longitude))
+}
+
+#[test]
+fn sun_times_at_greenwich_equinox() {
+    let date = Date::from_calendar_date(2025, time::Month::March, 20).unwrap();
+    let SunTimes::Normal(sunrise, sunset) = sun_times(date, 51.48, 0., 0.) else {
+        panic!("expected sunrise and sunset");
+    };
+    assert!((sunrise - 6.08 * 60.).abs() < 10.);
+    assert!((sunset - 18.19 * 60.).abs() < 10.);
+
+    let sun = SunTimes::Normal(sunrise, sunset);
+    assert_eq!(daylight_factor(12. * 60., sun), 1.);
+    assert_eq!(daylight_factor(0., sun), 0.);
+    assert!((daylight_factor(sunrise, sun) - 0.5).abs() < 1e-3);
+}
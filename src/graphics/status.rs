@@ -0,0 +1,264 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+
+use crate::{action::Action, font::FallbackText};
+
+use super::{daylight::Daylight, theme::CurrentTheme, time::unix_now};
+
+/// 最多同时显示的状态行
+const MAX_LINES: usize = 3;
+/// 状态行相对报时的字号
+const STATUS_SCALE: f32 = 0.4;
+/// 来自状态文件的行的标识前缀，IPC 的 `status` 命令不能使用
+pub const STATUS_FILE_PREFIX: &str = "file:";
+/// 状态文件中各行的优先级
+const FILE_PRIORITY: i32 = 0;
+
+/// 报时下方的状态行（构建状态、值班提醒、备注等）
+///
+/// 来源：IPC 的 `status` 命令，以及状态文件 `<配置目录>/time-fly/status.txt`
+/// （可由 `TIME_FLY_STATUS_FILE` 指定），文件每行一条，`#` 开头为注释，修改后自动重新加载；
+/// 文件中的行优先级都为 0，按文件中的顺序排列
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        let path = std::env::var_os("TIME_FLY_STATUS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| dirs::config_dir().map(|dir| dir.join("time-fly").join("status.txt")));
+
+        app.init_resource::<StatusLines>()
+            .insert_resource(StatusFile {
+                path,
+                modified: None,
+            })
+            .add_systems(
+                Update,
+                (
+                    (watch_file, expire_lines).run_if(on_timer(Duration::from_secs(1))),
+                    apply_actions,
+                    layout_lines.run_if(
+                        resource_changed::<StatusLines>
+                            .or(resource_changed::<CurrentTheme>)
+                            .or(resource_changed::<Daylight>),
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// 报时下方放置状态行的节点
+#[derive(Component)]
+pub struct StatusList {
+    pub font_size: f32,
+}
+
+#[derive(Component)]
+#[require(FallbackText)]
+pub struct StatusText;
+
+#[derive(Debug, Clone, PartialEq)]
+struct StatusLine {
+    /// 相同标识的状态行会被替换
+    id: String,
+    text: String,
+    /// 越大越靠前
+    priority: i32,
+    /// 过期时间（Unix 时间戳，秒）
+    expires: Option<i64>,
+}
+
+#[derive(Resource, Debug, Default)]
+struct StatusLines(Vec<StatusLine>);
+
+impl StatusLines {
+    /// 添加或替换状态行，优先级相同时先添加的在前
+    fn set(&mut self, line: StatusLine) {
+        self.remove(&line.id);
+        let index = self
+            .0
+            .partition_point(|other| other.priority >= line.priority);
+        self.0.insert(index, line);
+    }
+
+    fn remove(&mut self, id: &str) {
+        self.0.retain(|line| line.id != id);
+    }
+
+    /// 移除过期的行，返回是否有变化
+    fn expire(&mut self, now: i64) -> bool {
+        let len = self.0.len();
+        self.0
+            .retain(|line| line.expires.is_none_or(|expires| expires > now));
+        self.0.len() != len
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().take(MAX_LINES).map(|line| line.text.as_str())
+    }
+}
+
+#[derive(Resource)]
+struct StatusFile {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+fn read_status_file(path: &Path) -> Vec<StatusLine> {
+    let Ok(source) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .enumerate()
+        .map(|(i, text)| StatusLine {
+            id: format!("{STATUS_FILE_PREFIX}{i}"),
+            text: text.to_string(),
+            priority: FILE_PRIORITY,
+            expires: None,
+        })
+        .collect()
+}
+
+fn watch_file(mut file: ResMut<StatusFile>, mut lines: ResMut<StatusLines>) {
+    let Some(path) = &file.path else {
+        return;
+    };
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok();
+    if modified == file.modified {
+        return;
+    }
+
+    let loaded = read_status_file(path);
+    file.modified = modified;
+    lines
+        .0
+        .retain(|line| !line.id.starts_with(STATUS_FILE_PREFIX));
+    for line in loaded {
+        lines.set(line);
+    }
+}
+
+fn expire_lines(mut lines: ResMut<StatusLines>) {
+    let now = unix_now();
+    // 避免每秒都触发重新布局
+    if lines.bypass_change_detection().expire(now) {
+        lines.set_changed();
+    }
+}
+
+fn apply_actions(mut actions: EventReader<Action>, mut lines: ResMut<StatusLines>) {
+    for action in actions.read() {
+        let Action::Status {
+            id,
+            text,
+            priority,
+            ttl,
+        } = action
+        else {
+            continue;
+        };
+        match text {
+            Some(text) => lines.set(StatusLine {
+                id: id.clone(),
+                text: text.clone(),
+                priority: *priority,
+                expires: ttl.and_then(|ttl| expires_at(unix_now(), ttl)),
+            }),
+            None => lines.remove(id),
+        }
+    }
+}
+
+/// `ttl` 秒后过期的时间，超出范围时视为不过期
+fn expires_at(now: i64, ttl: u64) -> Option<i64> {
+    i64::try_from(ttl).ok().and_then(|ttl| now.checked_add(ttl))
+}
+
+fn layout_lines(
+    mut commands: Commands,
+    lines: Res<StatusLines>,
+    theme: Res<CurrentTheme>,
+    daylight: Res<Daylight>,
+    list: Single<(Entity, &StatusList)>,
+) {
+    let (list, StatusList { font_size }) = *list;
+    let color = TextColor(daylight.tint(theme.text.color));
+    commands
+        .entity(list)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for text in lines.visible() {
+                parent.spawn((
+                    StatusText,
+                    FallbackText(text.to_string()),
+                    TextFont {
+                        font_size: font_size * STATUS_SCALE,
+                        ..default()
+                    },
+                    color,
+                ));
+            }
+        });
+}
+
+#[test]
+fn status_lines_order_and_expiry() {
+    let line = |id: &str, priority, expires| StatusLine {
+        id: id.to_string(),
+        text: id.to_uppercase(),
+        priority,
+        expires,
+    };
+    let mut lines = StatusLines::default();
+    lines.set(line("build", 0, None));
+    lines.set(line("call", 5, Some(100)));
+    lines.set(line("note", 0, None));
+    lines.set(line("build", 1, Some(200)));
+    assert_eq!(
+        lines.visible().collect::<Vec<_>>(),
+        ["CALL", "BUILD", "NOTE"]
+    );
+
+    assert!(lines.expire(150));
+    assert!(!lines.expire(150));
+    assert_eq!(lines.visible().collect::<Vec<_>>(), ["BUILD", "NOTE"]);
+
+    // 过大的有效期不回绕成已过期
+    assert_eq!(expires_at(100, 60), Some(160));
+    assert_eq!(expires_at(100, u64::MAX), None);
+    assert_eq!(expires_at(100, i64::MAX as u64), None);
+}
+
+#[test]
+fn status_file_lines_keep_file_order() {
+    let path = std::env::temp_dir().join(format!("time-fly-status-{}.txt", std::process::id()));
+    std::fs::write(&path, "# 注释\nfirst\n\n second \n").unwrap();
+    let loaded = read_status_file(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(loaded.iter().all(|line| line.priority == FILE_PRIORITY));
+
+    let mut lines = StatusLines::default();
+    lines.set(StatusLine {
+        id: "call".into(),
+        text: "CALL".into(),
+        priority: 1,
+        expires: None,
+    });
+    for line in loaded {
+        lines.set(line);
+    }
+    assert_eq!(
+        lines.visible().collect::<Vec<_>>(),
+        ["CALL", "first", "second"]
+    );
+}
@@ -0,0 +1,234 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+};
+
+use super::theme::StickerTheme;
+
+/// 倒角宽度
+const BEVEL: f32 = 0.08;
+/// 贴纸相对于面边缘的内缩
+const STICKER_INSET: f32 = 0.05;
+/// 贴纸浮出表面的高度，避免深度冲突
+const STICKER_LIFT: f32 = 0.002;
+/// 多面体方块向自身中心收缩的比例，留出块之间的缝隙
+const PIECE_SHRINK: f32 = 0.94;
+/// 多面体方块上贴纸相对于所在面的大小
+const STICKER_SCALE: f32 = 0.8;
+/// 判断顶点是否在平面上的误差
+const EPSILON: f32 = 1e-4;
+
+/// 生成带倒角塑料块身与贴纸的方块网格，初始位置在外侧的面贴上对应颜色的贴纸
+pub fn sticker_mesh(size: f32, home: IVec3, theme: &StickerTheme, alpha: f32) -> Mesh {
+    let mut builder = MeshBuilder::default();
+    let half = size / 2.;
+    let inner = half - BEVEL * size;
+    let body = theme.body.with_alpha(alpha).to_linear().to_f32_array();
+
+    for axis in 0..3 {
+        for sign in [-1., 1.] {
+            let normal = Vec3::AXES[axis] * sign;
+            let (u, v) = (Vec3::AXES[(axis + 1) % 3], Vec3::AXES[(axis + 2) % 3]);
+
+            // 块身的面
+            builder.push_polygon(
+                &face_quad(normal * half, u * inner, v * inner),
+                normal,
+                body,
+            );
+
+            // 贴纸
+            if home[axis] as f32 == sign {
+                let extent = inner - STICKER_INSET * size;
+                let color = theme
+                    .color(normal.as_ivec3())
+                    .with_alpha(alpha)
+                    .to_linear()
+                    .to_f32_array();
+                builder.push_polygon(
+                    &face_quad(normal * (half + STICKER_LIFT), u * extent, v * extent),
+                    normal,
+                    color,
+                );
+            }
+        }
+    }
+
+    // 棱的倒角
+    for (i, j, k) in [(0, 1, 2), (1, 2, 0), (2, 0, 1)] {
+        let (ei, ej, ek) = (Vec3::AXES[i], Vec3::AXES[j], Vec3::AXES[k]);
+        for si in [-1., 1.] {
+            for sj in [-1., 1.] {
+                let a = ei * si * half + ej * sj * inner;
+                let b = ei * si * inner + ej * sj * half;
+                let points = [
+                    a - ek * inner,
+                    a + ek * inner,
+                    b + ek * inner,
+                    b - ek * inner,
+                ];
+                builder.push_polygon(&points, (ei * si + ej * sj).normalize(), body);
+            }
+        }
+    }
+
+    // 角的倒角
+    for sx in [-1., 1.] {
+        for sy in [-1., 1.] {
+            for sz in [-1., 1.] {
+                let corner = Vec3::new(sx, sy, sz);
+                let points = [0, 1, 2].map(|axis| {
+                    let mut point = corner * inner;
+                    point[axis] = corner[axis] * half;
+                    point
+                });
+                builder.push_polygon(&points, corner.normalize(), body);
+            }
+        }
+    }
+
+    builder.build()
+}
+
+/// 由顶点生成凸多面体方块的网格，用于斜转魔方、金字塔等不是立方体的方块
+///
+/// 顶点为整个谜题中的坐标；`sticker` 按面的外法线与到原点的距离给出贴纸颜色，块内的切面返回 `None`
+pub fn polyhedron_mesh(
+    points: &[Vec3],
+    sticker: impl Fn(Vec3, f32) -> Option<Color>,
+    theme: &StickerTheme,
+    alpha: f32,
+) -> Mesh {
+    let mut builder = MeshBuilder::default();
+    let body = theme.body.with_alpha(alpha).to_linear().to_f32_array();
+    for (polygon, normal, distance) in hull_faces(points) {
+        builder.push_polygon(&polygon, normal, body);
+        if let Some(color) = sticker(normal, distance) {
+            let color = color.with_alpha(alpha).to_linear().to_f32_array();
+            let center = polygon.iter().sum::<Vec3>() / polygon.len() as f32;
+            let points: Vec<Vec3> = polygon
+                .iter()
+                .map(|point| center + (*point - center) * STICKER_SCALE + normal * STICKER_LIFT)
+                .collect();
+            builder.push_polygon(&points, normal, color);
+        }
+    }
+    builder.build()
+}
+
+/// 按顶点坐标着色的凸多面体网格，与立方体方块的渐变网格着色方式相同，`size` 为整个谜题的边长
+pub fn gradient_polyhedron_mesh(points: &[Vec3], size: f32, alpha: f32) -> Mesh {
+    let mut builder = MeshBuilder::default();
+    for (polygon, normal, _) in hull_faces(points) {
+        builder.push_polygon(&polygon, normal, [1.; 4]);
+    }
+    builder.colors = builder
+        .positions
+        .iter()
+        .map(|position| {
+            let [r, g, b] = position.map(|x| (1. - x / size) / 2.);
+            [r, g, b, alpha]
+        })
+        .collect();
+    builder.build()
+}
+
+/// 凸包的各个面：顶点绕外法线逆时针排列并向方块中心收缩，以及外法线与收缩前到原点的距离
+fn hull_faces(points: &[Vec3]) -> Vec<(Vec<Vec3>, Vec3, f32)> {
+    let center = points.iter().sum::<Vec3>() / points.len() as f32;
+    let mut seen: Vec<Vec<usize>> = Vec::new();
+    let mut faces = Vec::new();
+    for i in 0..points.len() {
+        for j in i + 1..points.len() {
+            for k in j + 1..points.len() {
+                let Some(normal) = (points[j] - points[i])
+                    .cross(points[k] - points[i])
+                    .try_normalize()
+                else {
+                    continue;
+                };
+                // 其余顶点都在同一侧时为凸包的面
+                let distance = normal.dot(points[i]);
+                let sides: Vec<f32> = points.iter().map(|p| normal.dot(*p) - distance).collect();
+                let (normal, distance) = if sides.iter().all(|side| *side <= EPSILON) {
+                    (normal, distance)
+                } else if sides.iter().all(|side| *side >= -EPSILON) {
+                    (-normal, -distance)
+                } else {
+                    continue;
+                };
+                let on_plane: Vec<usize> = (0..points.len())
+                    .filter(|index| sides[*index].abs() <= EPSILON)
+                    .collect();
+                if seen.contains(&on_plane) {
+                    continue;
+                }
+
+                let mut polygon: Vec<Vec3> = on_plane.iter().map(|index| points[*index]).collect();
+                let face_center = polygon.iter().sum::<Vec3>() / polygon.len() as f32;
+                let u = polygon[0] - face_center;
+                let v = normal.cross(u);
+                let angle = |p: &Vec3| (*p - face_center).dot(v).atan2((*p - face_center).dot(u));
+                polygon.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+                for point in &mut polygon {
+                    *point = center + (*point - center) * PIECE_SHRINK;
+                }
+                seen.push(on_plane);
+                faces.push((polygon, normal, distance));
+            }
+        }
+    }
+    faces
+}
+
+fn face_quad(center: Vec3, u: Vec3, v: Vec3) -> [Vec3; 4] {
+    [
+        center - u - v,
+        center + u - v,
+        center + u + v,
+        center - u + v,
+    ]
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    /// 加入一个凸多边形，按法线方向调整为逆时针
+    fn push_polygon(&mut self, points: &[Vec3], normal: Vec3, color: [f32; 4]) {
+        let start = self.positions.len() as u32;
+        let facing = (points[1] - points[0])
+            .cross(points[2] - points[0])
+            .dot(normal);
+
+        for point in points {
+            self.positions.push(point.to_array());
+            self.normals.push(normal.to_array());
+            self.colors.push(color);
+        }
+        for i in 1..points.len() as u32 - 1 {
+            if facing >= 0. {
+                self.indices.extend([start, start + i, start + i + 1]);
+            } else {
+                self.indices.extend([start, start + i + 1, start + i]);
+            }
+        }
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
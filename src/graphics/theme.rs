@@ -0,0 +1,377 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer};
+
+use crate::{action::Action, power::Animating};
+
+use super::accessibility::Accessibility;
+
+/// 主题切换的渐变时长（秒）
+const FADE_SECS: f32 = 1.0;
+
+const DEFAULT_THEME: &str = "glass";
+
+/// 内置主题，用户主题目录中的同名主题会覆盖它们
+const BUILTIN_THEMES: &[&str] = &[
+    include_str!("../../assets/themes/glass.toml"),
+    include_str!("../../assets/themes/classic.toml"),
+    include_str!("../../assets/themes/neon.toml"),
+    include_str!("../../assets/themes/monochrome.toml"),
+    include_str!("../../assets/themes/high-contrast.toml"),
+];
+
+/// 主题插件，需要在使用 [`CurrentTheme`] 的插件之前、插入 [`Accessibility`] 之后加入
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        let themes = Themes::load();
+        let name = std::env::var("TIME_FLY_THEME").unwrap_or_else(|_| DEFAULT_THEME.to_string());
+        let theme = themes.get(&name).or_else(|| {
+            warn!("theme `{name}` not found, using `{DEFAULT_THEME}`");
+            themes.get(DEFAULT_THEME)
+        });
+        let mut theme = theme.cloned().unwrap_or_else(|| themes.0[0].clone());
+        if let Some(accessibility) = app.world().get_resource::<Accessibility>() {
+            accessibility.apply(&mut theme);
+        }
+
+        app.add_event::<SwitchTheme>()
+            .insert_resource(CurrentTheme(theme))
+            .insert_resource(themes)
+            .init_resource::<ThemeFade>()
+            .add_systems(Update, (theme_actions, switch_theme, fade_theme).chain());
+    }
+}
+
+/// 外观主题
+#[derive(Debug, Clone, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub piece: PieceTheme,
+    #[serde(default)]
+    pub sticker: StickerTheme,
+    pub bloom: BloomTheme,
+    pub text: TextTheme,
+}
+
+/// 魔方块的材质
+#[derive(Debug, Clone, Deserialize)]
+pub struct PieceTheme {
+    #[serde(default)]
+    pub style: PieceStyle,
+    pub roughness: f32,
+    pub metallic: f32,
+    pub reflectance: f32,
+    /// 顶点色的透明度
+    pub vertex_alpha: f32,
+    /// 按块的初始位置插值的颜色，x/y/z 分别对应红/绿/蓝通道，-1 层取前者，1 层取后者；
+    /// 贴纸外观不使用，可以省略，默认从黑到白
+    #[serde(default = "default_gradient", deserialize_with = "deserialize_colors")]
+    pub gradient: [Color; 2],
+}
+
+/// 魔方块的外观
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PieceStyle {
+    /// 整块按位置着色
+    #[default]
+    Gradient,
+    /// 黑色倒角块身加六色贴纸
+    Stickers,
+}
+
+/// 贴纸配色，默认为经典六色
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StickerTheme {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub body: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub up: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub down: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub front: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub back: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub right: Color,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub left: Color,
+}
+
+impl Default for StickerTheme {
+    fn default() -> Self {
+        Self {
+            body: Srgba::hex("111111").unwrap().into(),
+            up: Srgba::hex("ffffff").unwrap().into(),
+            down: Srgba::hex("ffd500").unwrap().into(),
+            front: Srgba::hex("009b48").unwrap().into(),
+            back: Srgba::hex("0046ad").unwrap().into(),
+            right: Srgba::hex("b71234").unwrap().into(),
+            left: Srgba::hex("ff5800").unwrap().into(),
+        }
+    }
+}
+
+impl StickerTheme {
+    /// 朝向为 normal 的面的贴纸颜色
+    pub fn color(&self, normal: IVec3) -> Color {
+        match normal.to_array() {
+            [1, 0, 0] => self.right,
+            [-1, 0, 0] => self.left,
+            [0, 1, 0] => self.up,
+            [0, -1, 0] => self.down,
+            [0, 0, 1] => self.front,
+            [0, 0, -1] => self.back,
+            _ => self.body,
+        }
+    }
+
+    fn lerp(&self, other: &StickerTheme, t: f32) -> StickerTheme {
+        StickerTheme {
+            body: self.body.mix(&other.body, t),
+            up: self.up.mix(&other.up, t),
+            down: self.down.mix(&other.down, t),
+            front: self.front.mix(&other.front, t),
+            back: self.back.mix(&other.back, t),
+            right: self.right.mix(&other.right, t),
+            left: self.left.mix(&other.left, t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BloomTheme {
+    pub intensity: f32,
+    pub low_frequency_boost: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextTheme {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: Color,
+}
+
+/// 当前生效的主题，渐变期间每帧更新
+#[derive(Resource, Deref)]
+pub struct CurrentTheme(Theme);
+
+/// 所有可用的主题
+#[derive(Resource)]
+pub struct Themes(Vec<Theme>);
+
+/// 切换到指定名称的主题
+#[derive(Event)]
+pub struct SwitchTheme(pub String);
+
+#[derive(Resource, Default)]
+struct ThemeFade {
+    from: Option<Theme>,
+    to: Option<Theme>,
+    progress: f32,
+}
+
+impl Themes {
+    /// 内置主题与用户主题
+    pub fn load() -> Self {
+        let Themes(mut themes) = Self::builtin();
+        for (path, theme) in user_themes() {
+            match theme {
+                Ok(theme) => {
+                    themes.retain(|t| t.name != theme.name);
+                    themes.push(theme);
+                }
+                Err(err) => warn!("failed to load theme {path:?}: {err}"),
+            }
+        }
+        Self(themes)
+    }
+
+    /// 仅内置主题，不受用户配置影响
+    pub fn builtin() -> Self {
+        Self(
+            BUILTIN_THEMES
+                .iter()
+                .map(|source| toml::from_str(source).expect("内置主题格式错误"))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.0.iter().find(|theme| theme.name == name)
+    }
+}
+
+impl Theme {
+    /// 两个主题之间插值，t 为 0 时为 self，为 1 时为 other
+    pub fn lerp(&self, other: &Theme, t: f32) -> Theme {
+        let f = |a: f32, b: f32| a.lerp(b, t);
+        Theme {
+            name: if t < 1. {
+                self.name.clone()
+            } else {
+                other.name.clone()
+            },
+            piece: PieceTheme {
+                // 外观在渐变过半时切换
+                style: if t < 0.5 {
+                    self.piece.style
+                } else {
+                    other.piece.style
+                },
+                roughness: f(self.piece.roughness, other.piece.roughness),
+                metallic: f(self.piece.metallic, other.piece.metallic),
+                reflectance: f(self.piece.reflectance, other.piece.reflectance),
+                vertex_alpha: f(self.piece.vertex_alpha, other.piece.vertex_alpha),
+                gradient: [
+                    self.piece.gradient[0].mix(&other.piece.gradient[0], t),
+                    self.piece.gradient[1].mix(&other.piece.gradient[1], t),
+                ],
+            },
+            sticker: self.sticker.lerp(&other.sticker, t),
+            bloom: BloomTheme {
+                intensity: f(self.bloom.intensity, other.bloom.intensity),
+                low_frequency_boost: f(
+                    self.bloom.low_frequency_boost,
+                    other.bloom.low_frequency_boost,
+                ),
+            },
+            text: TextTheme {
+                color: self.text.color.mix(&other.text.color, t),
+            },
+        }
+    }
+}
+
+impl PieceTheme {
+    /// 初始位置为 (x, y, z) 的块的颜色，坐标按魔方大小缩放到 -1..=1
+    pub fn color(&self, home: Vec3) -> Color {
+        let [from, to] = self.gradient.map(|color| color.to_srgba());
+        let t = |i: f32| (i + 1.) / 2.;
+        Color::srgba(
+            from.red.lerp(to.red, t(home.x)),
+            from.green.lerp(to.green, t(home.y)),
+            from.blue.lerp(to.blue, t(home.z)),
+            1.,
+        )
+    }
+}
+
+fn theme_actions(mut actions: EventReader<Action>, mut switch: EventWriter<SwitchTheme>) {
+    for action in actions.read() {
+        if let Action::Theme { name } = action {
+            switch.send(SwitchTheme(name.clone()));
+        }
+    }
+}
+
+fn switch_theme(
+    mut events: EventReader<SwitchTheme>,
+    themes: Res<Themes>,
+    current: Res<CurrentTheme>,
+    mut fade: ResMut<ThemeFade>,
+) {
+    for SwitchTheme(name) in events.read() {
+        let Some(theme) = themes.get(name) else {
+            warn!("theme `{name}` not found");
+            continue;
+        };
+        fade.from = Some(current.0.clone());
+        fade.to = Some(theme.clone());
+        fade.progress = 0.;
+    }
+}
+
+fn fade_theme(
+    time: Res<Time>,
+    accessibility: Res<Accessibility>,
+    mut fade: ResMut<ThemeFade>,
+    mut current: ResMut<CurrentTheme>,
+    mut animating: ResMut<Animating>,
+) {
+    let (Some(from), Some(to)) = (&fade.from, &fade.to) else {
+        return;
+    };
+    animating.0 = true;
+
+    let progress = if accessibility.reduced_motion {
+        1.
+    } else {
+        (fade.progress + time.delta_secs() / FADE_SECS).min(1.)
+    };
+    current.0 = from.lerp(to, progress);
+    accessibility.apply(&mut current.0);
+    fade.progress = progress;
+
+    if progress >= 1. {
+        fade.from = None;
+        fade.to = None;
+    }
+}
+
+/// 用户主题目录 `<配置目录>/time-fly/themes/*.toml`
+fn user_themes() -> Vec<(PathBuf, Result<Theme, String>)> {
+    let Some(dir) = dirs::config_dir().map(|dir| dir.join("time-fly").join("themes")) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .map(|path| {
+            let theme = std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| toml::from_str(&source).map_err(|err| err.to_string()));
+            (path, theme)
+        })
+        .collect()
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    Srgba::hex(&hex)
+        .map(Color::from)
+        .map_err(serde::de::Error::custom)
+}
+
+fn default_gradient() -> [Color; 2] {
+    [Color::BLACK, Color::WHITE]
+}
+
+fn deserialize_colors<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[Color; N], D::Error> {
+    let hexes = Vec::<String>::deserialize(deserializer)?;
+    if hexes.len() != N {
+        return Err(serde::de::Error::invalid_length(
+            hexes.len(),
+            &format!("{N} colors").as_str(),
+        ));
+    }
+    let mut colors = [Color::NONE; N];
+    for (color, hex) in colors.iter_mut().zip(hexes) {
+        *color = Srgba::hex(&hex)
+            .map(Color::from)
+            .map_err(serde::de::Error::custom)?;
+    }
+    Ok(colors)
+}
+
+#[test]
+fn builtin_themes_parse() {
+    let themes = Themes::builtin();
+    let glass = themes.get(DEFAULT_THEME).unwrap();
+    // 与原先硬编码的 pos2color 一致
+    let color = glass.piece.color(Vec3::new(-1., 0., 1.)).to_srgba();
+    assert!((color.red - 0.2).abs() < 1e-3);
+    assert!((color.green - 0.5).abs() < 1e-3);
+    assert!((color.blue - 0.8).abs() < 1e-3);
+}
@@ -1,21 +1,100 @@
 use std::time::Duration;
 
 use bevy::{app::Plugin, prelude::*};
+use serde::{Deserialize, Serialize};
 use time::{macros::format_description, OffsetDateTime};
 
+use crate::{action::Action, font::FallbackText};
+
+use super::{daylight::Daylight, theme::CurrentTheme};
+
 const DELTA_SECOND: u64 = 60;
+/// 番茄钟的工作与休息时长（秒）
+const POMODORO_WORK: i64 = 25 * 60;
+const POMODORO_BREAK: i64 = 5 * 60;
+/// 倒计时的最长时长（秒），更长的视为无效
+const MAX_COUNTDOWN: i64 = 7 * 24 * 60 * 60;
 
 #[derive(Component)]
-#[require(Text)]
+#[require(FallbackText)]
 pub struct TimeSpan;
 
+/// 显示模式，结束时间为 Unix 时间戳（秒），重启后仍按原时间结束
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
+pub enum ClockMode {
+    /// 显示当前时间
+    #[default]
+    Clock,
+    /// 倒计时，结束后回到时钟
+    Countdown { end: i64 },
+    /// 番茄钟，工作与休息交替
+    Pomodoro { working: bool, end: i64 },
+}
+
+impl ClockMode {
+    /// 解析 `clock`、`countdown:<分钟>`、`pomodoro`，倒计时的分钟数不能为负数，最长一周
+    pub fn parse(value: &str) -> Option<Self> {
+        let now = unix_now();
+        let mode = match value.trim().split_once(':') {
+            None if value.trim() == "clock" => ClockMode::Clock,
+            None if value.trim() == "pomodoro" => ClockMode::Pomodoro {
+                working: true,
+                end: now + POMODORO_WORK,
+            },
+            Some(("countdown", minutes)) => {
+                let minutes = minutes.trim().parse::<f64>().ok()?;
+                let seconds = minutes * 60.;
+                if !(0. ..=MAX_COUNTDOWN as f64).contains(&seconds) {
+                    return None;
+                }
+                let seconds = seconds as i64;
+                ClockMode::Countdown {
+                    end: now.checked_add(seconds)?,
+                }
+            }
+            _ => return None,
+        };
+        Some(mode)
+    }
+}
+
+/// 代替时间显示的自定义文字
+#[derive(Resource, Debug, Default)]
+struct CustomText(Option<String>);
+
+/// 倒计时或番茄钟的一个阶段结束
+#[derive(Event, Debug)]
+pub struct TimerExpired;
+
 /// 报时插件
+///
+/// 环境变量 `TIME_FLY_MODE` 指定启动时的显示模式，见 [`ClockMode::parse`]，
+/// 未指定时沿用已有的 [`ClockMode`]
 pub struct TimePlugin;
 
 impl Plugin for TimePlugin {
     fn build(&self, app: &mut App) {
+        let mode = std::env::var("TIME_FLY_MODE").ok().and_then(|value| {
+            let mode = ClockMode::parse(&value);
+            if mode.is_none() {
+                warn!("unknown mode `{value}`");
+            }
+            mode
+        });
+        match mode {
+            Some(mode) => app.insert_resource(mode),
+            None => app.init_resource::<ClockMode>(),
+        };
+
+        app.add_event::<TimerExpired>();
+        app.init_resource::<CustomText>();
         app.add_systems(Startup, setup);
-        app.add_systems(Update, alert);
+        app.add_systems(Update, (apply_actions, countdown, alert).chain());
+        app.add_systems(
+            Update,
+            apply_theme.run_if(resource_changed::<CurrentTheme>.or(resource_changed::<Daylight>)),
+        );
     }
 }
 
@@ -23,12 +102,39 @@ fn setup(mut commands: Commands) {
     commands.init_resource::<SystemTimer>();
 }
 
+fn apply_actions(
+    mut actions: EventReader<Action>,
+    mut mode: ResMut<ClockMode>,
+    mut custom: ResMut<CustomText>,
+    mut time_alert: Single<&mut FallbackText, With<TimeSpan>>,
+) {
+    for action in actions.read() {
+        match action {
+            Action::Mode { name } => match ClockMode::parse(name) {
+                Some(value) => *mode = value,
+                None => warn!("unknown mode `{name}`"),
+            },
+            Action::Text { value } => {
+                custom.0 = value.clone();
+                // 清空后由 alert 或 countdown 立即刷新时间
+                time_alert.0 = value.clone().unwrap_or_default();
+            }
+            _ => {}
+        }
+    }
+}
+
 fn alert(
     time: Res<Time<Real>>,
+    mode: Res<ClockMode>,
+    custom: Res<CustomText>,
     mut state: ResMut<SystemTimer>,
-    mut time_alert: Single<&mut Text, With<TimeSpan>>,
+    mut time_alert: Single<&mut FallbackText, With<TimeSpan>>,
 ) {
-    if !state.tick(time.delta()).just_finished() && !time_alert.0.is_empty() {
+    if *mode != ClockMode::Clock || custom.0.is_some() {
+        return;
+    }
+    if !state.tick(time.delta()).just_finished() && !time_alert.0.is_empty() && !mode.is_changed() {
         return;
     }
 
@@ -44,6 +150,62 @@ fn alert(
     }
 }
 
+// 倒计时与番茄钟显示剩余的分:秒
+fn countdown(
+    mut mode: ResMut<ClockMode>,
+    custom: Res<CustomText>,
+    mut expired: EventWriter<TimerExpired>,
+    mut time_alert: Single<&mut FallbackText, With<TimeSpan>>,
+) {
+    let end = match *mode {
+        ClockMode::Clock => return,
+        ClockMode::Countdown { end } | ClockMode::Pomodoro { end, .. } => end,
+    };
+
+    let now = unix_now();
+    if now >= end {
+        expired.send(TimerExpired);
+        *mode = match *mode {
+            ClockMode::Pomodoro { working, .. } => ClockMode::Pomodoro {
+                working: !working,
+                end: now
+                    + if working {
+                        POMODORO_BREAK
+                    } else {
+                        POMODORO_WORK
+                    },
+            },
+            _ => ClockMode::Clock,
+        };
+        return;
+    }
+
+    if custom.0.is_some() {
+        return;
+    }
+    let remaining = end - now;
+    let text = format!("{:02}:{:02}", remaining / 60, remaining % 60);
+    if time_alert.0 != text {
+        time_alert.0 = text;
+    }
+}
+
+pub(super) fn unix_now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+// 按字体拆分出的文本段随报时文本一起变色
+fn apply_theme(
+    theme: Res<CurrentTheme>,
+    daylight: Res<Daylight>,
+    mut colors: Query<&mut TextColor, With<TimeSpan>>,
+) {
+    let text_color = daylight.tint(theme.text.color);
+    for mut color in colors.iter_mut() {
+        color.0 = text_color;
+    }
+}
+
 #[derive(Resource, Deref, DerefMut)]
 struct SystemTimer(Timer);
 
@@ -56,3 +218,24 @@ impl Default for SystemTimer {
         Self(timer)
     }
 }
+
+#[test]
+fn parse_countdown_limits() {
+    let now = unix_now();
+    let end = |value: &str| match ClockMode::parse(value) {
+        Some(ClockMode::Countdown { end }) => Some(end - now),
+        _ => None,
+    };
+    assert!(end("countdown:25").is_some_and(|secs| (1500..1502).contains(&secs)));
+    assert!(end("countdown:10080").is_some_and(|secs| secs <= MAX_COUNTDOWN + 1));
+    for value in [
+        "countdown:10081",
+        "countdown:1e300",
+        "countdown:inf",
+        "countdown:NaN",
+        "countdown:-5",
+        "countdown:abc",
+    ] {
+        assert_eq!(ClockMode::parse(value), None, "{value}");
+    }
+}
@@ -0,0 +1,144 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, render::camera::ScalingMode};
+
+/// 魔方中心到相机的距离
+pub const CUBE_DEPTH: f32 = 20.;
+/// 透视投影的垂直视角
+const FOV: f32 = PI / 4.;
+
+/// 魔方的朝向、相机的投影方式与魔方的缩放
+///
+/// `TIME_FLY_VIEW` 为 `corner`（默认，一个角正对相机）、`face`、`edge`，
+/// 或 `yaw,pitch,roll`（角度，在正对一个面的基础上旋转）；
+/// `TIME_FLY_PROJECTION` 为 `perspective`（默认）或 `orthographic`；
+/// `TIME_FLY_CUBE_SCALE` 为魔方的缩放，默认 1
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct ViewConfig {
+    pub angle: ViewAngle,
+    pub orthographic: bool,
+    pub scale: f32,
+}
+
+impl Default for ViewConfig {
+    fn default() -> Self {
+        Self {
+            angle: ViewAngle::Corner,
+            orthographic: false,
+            scale: 1.,
+        }
+    }
+}
+
+/// 魔方朝向相机的部分
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ViewAngle {
+    #[default]
+    Corner,
+    Face,
+    Edge,
+    /// 正对前面后再按 YXZ 顺序旋转的欧拉角（弧度）
+    Euler(Vec3),
+}
+
+impl ViewAngle {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "corner" => Some(ViewAngle::Corner),
+            "face" => Some(ViewAngle::Face),
+            "edge" => Some(ViewAngle::Edge),
+            value => {
+                let angles: Vec<f32> = value
+                    .split(',')
+                    .map(|angle| angle.trim().parse::<f32>().ok().filter(|a| a.is_finite()))
+                    .collect::<Option<_>>()?;
+                let [yaw, pitch, roll] = angles[..] else {
+                    return None;
+                };
+                Some(ViewAngle::Euler(Vec3::new(yaw, pitch, roll) * PI / 180.))
+            }
+        }
+    }
+
+    /// 局部坐标中背向相机的方向，旋转后与相机到魔方中心的方向一致
+    pub fn away_from_camera(self) -> Vec3 {
+        match self {
+            ViewAngle::Corner => Vec3::new(1., -1., -1.).normalize(),
+            ViewAngle::Face | ViewAngle::Euler(_) => Vec3::NEG_Z,
+            ViewAngle::Edge => Vec3::new(0., -1., -1.).normalize(),
+        }
+    }
+
+    /// 朝向相机之后在局部坐标中追加的旋转
+    pub fn local_rotation(self) -> Quat {
+        match self {
+            ViewAngle::Euler(angles) => {
+                Quat::from_euler(EulerRot::YXZ, angles.x, angles.y, angles.z)
+            }
+            _ => Quat::IDENTITY,
+        }
+    }
+}
+
+impl ViewConfig {
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("TIME_FLY_VIEW") {
+            match ViewAngle::parse(&value) {
+                Some(angle) => config.angle = angle,
+                None => warn!("unknown TIME_FLY_VIEW `{value}`, using `corner`"),
+            }
+        }
+        match std::env::var("TIME_FLY_PROJECTION") {
+            Ok(value) if value.trim() == "orthographic" => config.orthographic = true,
+            Ok(value) if value.trim() == "perspective" => {}
+            Ok(value) => warn!("unknown TIME_FLY_PROJECTION `{value}`, using `perspective`"),
+            Err(_) => {}
+        }
+        if let Ok(value) = std::env::var("TIME_FLY_CUBE_SCALE") {
+            match value.trim().parse::<f32>() {
+                Ok(scale) if scale.is_finite() && scale > 0. => config.scale = scale,
+                _ => warn!("invalid TIME_FLY_CUBE_SCALE `{value}`, using 1"),
+            }
+        }
+        config
+    }
+
+    /// 正交投影的可见高度与透视投影在魔方深度处相同，两者下魔方大小一致
+    pub fn projection(&self) -> Projection {
+        if self.orthographic {
+            Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::FixedVertical {
+                    viewport_height: visible_height(),
+                },
+                ..OrthographicProjection::default_3d()
+            })
+        } else {
+            Projection::Perspective(PerspectiveProjection {
+                fov: FOV,
+                ..default()
+            })
+        }
+    }
+
+    /// 魔方深度处一个世界单位对应的逻辑像素数
+    pub fn pixels_per_unit(&self, logical_height: f32) -> f32 {
+        logical_height / visible_height()
+    }
+}
+
+/// 魔方深度处画面的可见高度（世界单位）
+fn visible_height() -> f32 {
+    2. * CUBE_DEPTH * (FOV / 2.).tan()
+}
+
+#[test]
+fn parse_view_angle() {
+    assert_eq!(ViewAngle::parse(" face "), Some(ViewAngle::Face));
+    assert_eq!(
+        ViewAngle::parse("90, 0, 0"),
+        Some(ViewAngle::Euler(Vec3::new(PI / 2., 0., 0.)))
+    );
+    assert_eq!(ViewAngle::parse("90,0"), None);
+    assert_eq!(ViewAngle::parse("diagonal"), None);
+}
@@ -0,0 +1,225 @@
+use std::{fmt, str::FromStr, sync::mpsc::Sender};
+
+use bevy::prelude::*;
+
+use crate::action::{Action, ActionQueue};
+
+#[cfg(target_os = "linux")]
+mod portal;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "linux")]
+mod x11;
+
+/// 默认快捷键：显示/隐藏、25 分钟倒计时、鼠标穿透
+const DEFAULT_HOTKEYS: &str = "super+alt+t=toggle_visibility; super+alt+p=mode countdown:25; \
+                               super+alt+c=toggle_click_through";
+
+/// 全局快捷键，触发的操作与 IPC、托盘共用 [`Action`]
+///
+/// `TIME_FLY_HOTKEYS="super+alt+t=toggle_visibility; ctrl+alt+f5=mode countdown:25"`，
+/// 分号分隔，操作的写法见 [`Action`] 的文本解析；设为空时不注册快捷键。
+/// X11 上直接抓取按键，Wayland 上通过桌面门户注册，Windows 上使用 `RegisterHotKey`
+pub struct HotkeyPlugin;
+
+impl Plugin for HotkeyPlugin {
+    fn build(&self, app: &mut App) {
+        let config =
+            std::env::var("TIME_FLY_HOTKEYS").unwrap_or_else(|_| DEFAULT_HOTKEYS.to_string());
+        let bindings: Vec<Binding> = parse_bindings(&config)
+            .filter_map(|binding| binding.inspect_err(|err| warn!("{err}")).ok())
+            .collect();
+        if bindings.is_empty() {
+            return;
+        }
+
+        let actions = app
+            .init_resource::<ActionQueue>()
+            .world()
+            .resource::<ActionQueue>()
+            .sender();
+        if let Err(err) = spawn(bindings, actions) {
+            warn!("failed to register global hotkeys: {err}");
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn spawn(bindings: Vec<Binding>, actions: Sender<Action>) -> anyhow::Result<()> {
+    // Wayland 不允许应用抓取按键，只能由混成器经门户分发
+    if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return x11::spawn(bindings, actions);
+    }
+    portal::spawn(bindings.clone(), actions.clone()).or_else(|err| {
+        warn!("global shortcuts portal unavailable, falling back to X11: {err}");
+        x11::spawn(bindings, actions)
+    })
+}
+
+#[cfg(target_os = "windows")]
+use self::windows::spawn;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn spawn(_: Vec<Binding>, _: Sender<Action>) -> anyhow::Result<()> {
+    anyhow::bail!("not supported on this platform")
+}
+
+/// 修饰键
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// Super / Win 键
+    pub logo: bool,
+}
+
+/// 快捷键的主键
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// 小写字母或数字
+    Char(char),
+    /// F1 到 F24
+    F(u8),
+    Space,
+    Enter,
+    Escape,
+    Tab,
+}
+
+/// `super+alt+t` 形式的快捷键，不区分大小写
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let mut modifiers = Modifiers::default();
+        let mut key = None;
+        for part in value.split('+').map(|part| part.trim().to_lowercase()) {
+            if key.is_some() {
+                return Err(format!("key must come last in hotkey `{value}`"));
+            }
+            match part.as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "super" | "win" | "logo" | "meta" => modifiers.logo = true,
+                _ => key = Some(parse_key(&part).ok_or_else(|| format!("unknown key `{part}`"))?),
+            }
+        }
+        let key = key.ok_or_else(|| format!("missing key in hotkey `{value}`"))?;
+        Ok(Self { modifiers, key })
+    }
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphanumeric() => return Some(Key::Char(c)),
+        _ => {}
+    }
+    let key = match name {
+        "space" => Key::Space,
+        "enter" | "return" => Key::Enter,
+        "esc" | "escape" => Key::Escape,
+        "tab" => Key::Tab,
+        _ => match name.strip_prefix('f')?.parse() {
+            Ok(n @ 1..=24) => Key::F(n),
+            _ => return None,
+        },
+    };
+    Some(key)
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers {
+            ctrl,
+            alt,
+            shift,
+            logo,
+        } = self.modifiers;
+        for (held, name) in [
+            (ctrl, "Ctrl"),
+            (alt, "Alt"),
+            (shift, "Shift"),
+            (logo, "Super"),
+        ] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        match self.key {
+            Key::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            Key::F(n) => write!(f, "F{n}"),
+            Key::Space => write!(f, "Space"),
+            Key::Enter => write!(f, "Enter"),
+            Key::Escape => write!(f, "Escape"),
+            Key::Tab => write!(f, "Tab"),
+        }
+    }
+}
+
+/// 快捷键与触发的操作
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub hotkey: Hotkey,
+    pub action: Action,
+    /// 配置中操作的原文，门户会向用户展示
+    pub description: String,
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let (hotkey, action) = value
+            .split_once('=')
+            .ok_or_else(|| format!("expected `<hotkey>=<action>`, got `{value}`"))?;
+        let description = action.trim().to_string();
+        Ok(Self {
+            hotkey: hotkey.parse()?,
+            action: description
+                .parse()
+                .map_err(|err| format!("invalid action for hotkey `{}`: {err}", hotkey.trim()))?,
+            description,
+        })
+    }
+}
+
+/// 解析分号分隔的快捷键配置，空项被忽略
+fn parse_bindings(config: &str) -> impl Iterator<Item = Result<Binding, String>> + '_ {
+    config
+        .split(';')
+        .filter(|item| !item.trim().is_empty())
+        .map(str::parse)
+}
+
+#[test]
+fn parse_hotkey_config() {
+    let bindings: Vec<Binding> = parse_bindings(DEFAULT_HOTKEYS)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(bindings.len(), 3);
+    assert_eq!(bindings[0].hotkey.to_string(), "Alt+Super+T");
+    assert_eq!(bindings[0].action, Action::ToggleVisibility);
+    assert_eq!(
+        bindings[1].action,
+        Action::Mode {
+            name: "countdown:25".into()
+        }
+    );
+
+    let hotkey: Hotkey = "Ctrl + Shift + F5".parse().unwrap();
+    assert_eq!(hotkey.key, Key::F(5));
+    assert!(hotkey.modifiers.ctrl && hotkey.modifiers.shift && !hotkey.modifiers.alt);
+    assert!("ctrl+f25".parse::<Hotkey>().is_err());
+    assert!("t+ctrl".parse::<Hotkey>().is_err());
+    assert!("ctrl+alt".parse::<Hotkey>().is_err());
+    assert!(parse_bindings("ctrl+t=fly; ;").all(|binding| binding.is_err()));
+}
@@ -0,0 +1,160 @@
+use std::sync::mpsc::Sender;
+
+use bevy::prelude::*;
+
+use crate::{
+    action::Action,
+    dbus::{Connection, DbusError, Message, MessageKind, Value},
+};
+
+use super::{Binding, Hotkey, Key};
+
+const PORTAL: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SHORTCUTS: &str = "org.freedesktop.portal.GlobalShortcuts";
+const REQUEST: &str = "org.freedesktop.portal.Request";
+const BUS: &str = "org.freedesktop.DBus";
+
+/// 通过 `org.freedesktop.portal.GlobalShortcuts` 注册快捷键
+///
+/// 会话在此创建，门户不支持时直接返回错误；绑定时混成器可能询问用户，因此在 `hotkeys` 线程中进行，
+/// 最终生效的按键由用户决定，配置中的按键只作为建议
+pub fn spawn(bindings: Vec<Binding>, actions: Sender<Action>) -> anyhow::Result<()> {
+    let mut connection = Connection::session()?;
+    // 请求的结果通过 Response 信号返回，需要在调用前订阅
+    for (interface, member) in [(REQUEST, "Response"), (SHORTCUTS, "Activated")] {
+        connection.call(
+            Message::method_call(BUS, "/org/freedesktop/DBus", BUS, "AddMatch").with_body(vec![
+                Value::str(format!(
+                    "type='signal',interface='{interface}',member='{member}'"
+                )),
+            ]),
+        )?;
+    }
+
+    let token = format!("time_fly_{}", std::process::id());
+    let reply = connection.call(
+        Message::method_call(PORTAL, PORTAL_PATH, SHORTCUTS, "CreateSession").with_body(vec![
+            Value::dict([
+                ("handle_token", Value::str(&token)),
+                ("session_handle_token", Value::str(&token)),
+            ]),
+        ]),
+    )?;
+    let results = wait_response(&mut connection, reply.first())?;
+    let session = results
+        .dict_get("session_handle")
+        .and_then(Value::as_str)
+        .ok_or(DbusError::Malformed("missing session handle"))?
+        .to_string();
+
+    std::thread::Builder::new()
+        .name("hotkeys".into())
+        .spawn(move || {
+            if let Err(err) = run(connection, session, bindings, actions) {
+                warn!("global shortcuts session closed: {err}");
+            }
+        })?;
+    Ok(())
+}
+
+fn run(
+    mut connection: Connection,
+    session: String,
+    bindings: Vec<Binding>,
+    actions: Sender<Action>,
+) -> Result<(), DbusError> {
+    let shortcuts = bindings
+        .iter()
+        .enumerate()
+        .map(|(id, binding)| {
+            Value::Struct(vec![
+                Value::str(id.to_string()),
+                Value::dict([
+                    ("description", Value::str(&binding.description)),
+                    ("preferred_trigger", Value::str(trigger(&binding.hotkey))),
+                ]),
+            ])
+        })
+        .collect();
+    let reply = connection.call(
+        Message::method_call(PORTAL, PORTAL_PATH, SHORTCUTS, "BindShortcuts").with_body(vec![
+            Value::ObjectPath(session.clone()),
+            Value::Array("(sa{sv})".into(), shortcuts),
+            Value::str(""),
+            Value::dict([(
+                "handle_token",
+                Value::str(format!("time_fly_bind_{}", std::process::id())),
+            )]),
+        ]),
+    )?;
+    wait_response(&mut connection, reply.first())?;
+
+    loop {
+        let message = connection.receive()?;
+        if message.kind != MessageKind::Signal
+            || !message.is(SHORTCUTS, "Activated")
+            || message.body.first().and_then(Value::as_str) != Some(&session)
+        {
+            continue;
+        }
+        let binding = message
+            .body
+            .get(1)
+            .and_then(Value::as_str)
+            .and_then(|id| id.parse::<usize>().ok())
+            .and_then(|id| bindings.get(id));
+        if let Some(binding) = binding {
+            let _ = actions.send(binding.action.clone());
+        }
+    }
+}
+
+/// 等待请求对象的 Response 信号，返回结果字典
+fn wait_response(connection: &mut Connection, request: Option<&Value>) -> Result<Value, DbusError> {
+    let request = request
+        .and_then(Value::as_str)
+        .ok_or(DbusError::Malformed("missing request handle"))?
+        .to_string();
+    loop {
+        let message = connection.receive()?;
+        if message.kind != MessageKind::Signal
+            || !message.is(REQUEST, "Response")
+            || message.path.as_deref() != Some(&request)
+        {
+            continue;
+        }
+        let mut body = message.body.into_iter();
+        return match (body.next(), body.next()) {
+            (Some(Value::UInt32(0)), Some(results)) => Ok(results),
+            _ => Err(DbusError::Remote {
+                name: REQUEST.into(),
+                message: "request was cancelled".into(),
+            }),
+        };
+    }
+}
+
+/// 快捷键规范中的写法，如 `CTRL+ALT+t`
+fn trigger(hotkey: &Hotkey) -> String {
+    let modifiers = hotkey.modifiers;
+    let mut parts: Vec<String> = [
+        (modifiers.ctrl, "CTRL"),
+        (modifiers.alt, "ALT"),
+        (modifiers.shift, "SHIFT"),
+        (modifiers.logo, "LOGO"),
+    ]
+    .into_iter()
+    .filter(|(held, _)| *held)
+    .map(|(_, name)| name.to_string())
+    .collect();
+    parts.push(match hotkey.key {
+        Key::Char(c) => c.to_string(),
+        Key::F(n) => format!("F{n}"),
+        Key::Space => "space".into(),
+        Key::Enter => "Return".into(),
+        Key::Escape => "Escape".into(),
+        Key::Tab => "Tab".into(),
+    });
+    parts.join("+")
+}
@@ -0,0 +1,75 @@
+use std::sync::mpsc::{self, Sender};
+
+use bevy::prelude::*;
+use windows::Win32::UI::{
+    Input::KeyboardAndMouse::{
+        RegisterHotKey, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+    },
+    WindowsAndMessaging::{GetMessageW, MSG, WM_HOTKEY},
+};
+
+use crate::action::Action;
+
+use super::{Binding, Key};
+
+/// 注册快捷键并在 `hotkeys` 线程中等待 `WM_HOTKEY`
+///
+/// 快捷键属于注册它的线程，注册与消息循环都在该线程中进行
+pub fn spawn(bindings: Vec<Binding>, actions: Sender<Action>) -> anyhow::Result<()> {
+    let (registered_tx, registered_rx) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("hotkeys".into())
+        .spawn(move || {
+            let mut registered = 0;
+            for (id, binding) in bindings.iter().enumerate() {
+                let held = binding.hotkey.modifiers;
+                let mut modifiers = MOD_NOREPEAT;
+                for (held, modifier) in [
+                    (held.ctrl, MOD_CONTROL),
+                    (held.alt, MOD_ALT),
+                    (held.shift, MOD_SHIFT),
+                    (held.logo, MOD_WIN),
+                ] {
+                    if held {
+                        modifiers |= modifier;
+                    }
+                }
+                let vk = virtual_key(binding.hotkey.key);
+                match unsafe { RegisterHotKey(None, id as i32, modifiers, vk) } {
+                    Ok(()) => registered += 1,
+                    Err(err) => warn!("failed to register hotkey {}: {err}", binding.hotkey),
+                }
+            }
+            let _ = registered_tx.send(registered);
+            if registered == 0 {
+                return;
+            }
+
+            let mut message = MSG::default();
+            while unsafe { GetMessageW(&mut message, None, 0, 0) }.as_bool() {
+                if message.message != WM_HOTKEY {
+                    continue;
+                }
+                if let Some(binding) = bindings.get(message.wParam.0) {
+                    let _ = actions.send(binding.action.clone());
+                }
+            }
+        })?;
+
+    if registered_rx.recv()? == 0 {
+        anyhow::bail!("no hotkey could be registered");
+    }
+    Ok(())
+}
+
+fn virtual_key(key: Key) -> u32 {
+    match key {
+        // 字母与数字的虚拟键码即其大写 ASCII 编码
+        Key::Char(c) => c.to_ascii_uppercase() as u32,
+        Key::F(n) => 0x70 + u32::from(n) - 1,
+        Key::Space => 0x20,
+        Key::Enter => 0x0d,
+        Key::Escape => 0x1b,
+        Key::Tab => 0x09,
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::mpsc::Sender;
+
+use bevy::prelude::*;
+use x11rb::{
+    connection::Connection,
+    errors::ReplyError,
+    protocol::{
+        xproto::{ConnectionExt, GrabMode, ModMask},
+        Event,
+    },
+};
+
+use crate::action::Action;
+
+use super::{Binding, Key, Modifiers};
+
+/// 参与匹配的修饰键，CapsLock 与 NumLock 不影响快捷键
+const RELEVANT: u16 = 1 | 4 | 8 | 64;
+/// 抓取时额外组合的 CapsLock（Lock）与 NumLock（Mod2）
+const IGNORED: [u16; 4] = [0, 2, 16, 2 | 16];
+
+/// 在根窗口上抓取按键，在 `hotkeys` 线程中等待按下
+pub fn spawn(bindings: Vec<Binding>, actions: Sender<Action>) -> anyhow::Result<()> {
+    let (conn, screen) = x11rb::connect(None)?;
+    let setup = conn.setup();
+    let root = setup.roots[screen].root;
+    let (min, max) = (setup.min_keycode, setup.max_keycode);
+    let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+    let per_keycode = usize::from(mapping.keysyms_per_keycode.max(1));
+    let keycode = |keysym: u32| {
+        mapping
+            .keysyms
+            .chunks(per_keycode)
+            .position(|keysyms| keysyms.contains(&keysym))
+            .map(|index| min + index as u8)
+    };
+
+    let mut grabs = Vec::new();
+    for binding in bindings {
+        let Some(code) = keycode(keysym(binding.hotkey.key)) else {
+            warn!("hotkey {} is not on the keyboard", binding.hotkey);
+            continue;
+        };
+        let modifiers = modifiers(binding.hotkey.modifiers);
+        let grabbed = IGNORED
+            .iter()
+            .try_for_each(|extra| -> Result<(), ReplyError> {
+                conn.grab_key(
+                    false,
+                    root,
+                    ModMask::from(modifiers | extra),
+                    code,
+                    GrabMode::ASYNC,
+                    GrabMode::ASYNC,
+                )?
+                .check()
+            });
+        match grabbed {
+            Ok(()) => grabs.push((code, modifiers, binding.action)),
+            // 已被其他程序占用时为 BadAccess
+            Err(err) => warn!("failed to grab hotkey {}: {err}", binding.hotkey),
+        }
+    }
+    if grabs.is_empty() {
+        anyhow::bail!("no hotkey could be grabbed");
+    }
+    conn.flush()?;
+
+    std::thread::Builder::new()
+        .name("hotkeys".into())
+        .spawn(move || loop {
+            match conn.wait_for_event() {
+                Ok(Event::KeyPress(event)) => {
+                    let state = u16::from(event.state) & RELEVANT;
+                    for (code, modifiers, action) in &grabs {
+                        if (*code, *modifiers) == (event.detail, state) {
+                            let _ = actions.send(action.clone());
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    warn!("hotkey connection closed: {err}");
+                    break;
+                }
+            }
+        })?;
+    Ok(())
+}
+
+fn modifiers(modifiers: Modifiers) -> u16 {
+    [
+        (modifiers.shift, ModMask::SHIFT),
+        (modifiers.ctrl, ModMask::CONTROL),
+        (modifiers.alt, ModMask::M1),
+        (modifiers.logo, ModMask::M4),
+    ]
+    .into_iter()
+    .filter(|(held, _)| *held)
+    .fold(0, |mask, (_, modifier)| mask | u16::from(modifier))
+}
+
+fn keysym(key: Key) -> u32 {
+    match key {
+        // Latin-1 字符的 keysym 即其编码
+        Key::Char(c) => c as u32,
+        Key::F(n) => 0xffbe + u32::from(n) - 1,
+        Key::Space => 0x20,
+        Key::Enter => 0xff0d,
+        Key::Escape => 0xff1b,
+        Key::Tab => 0xff09,
+    }
+}
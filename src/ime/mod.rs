@@ -1,13 +1,13 @@
 #[cfg(target_os = "windows")]
 mod windows;
 
-use anyhow::Result;
+use thiserror::Error;
 
 #[allow(dead_code)]
 pub trait InputMode {
     fn new(check_timeout: u32, base_status: bool) -> Self;
-    fn get_input_mode(&self) -> Result<IMEResponse>;
-    fn set_input_mode(&self, is_cn: bool) -> Result<()>;
+    fn get_input_mode(&self) -> Result<IMEResponse, IMEError>;
+    fn set_input_mode(&self, is_cn: bool) -> Result<(), IMEError>;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,6 +16,19 @@ pub struct IMEResponse {
     pub is_cn: bool,
 }
 
+/// 输入法查询失败的原因，调用方据此显示“未知”而不是错误的状态
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum IMEError {
+    #[error("IME window did not respond within {0}ms")]
+    Timeout(u32),
+    #[error("no focused window")]
+    NoFocusedWindow,
+    #[error("focused window has no IME window")]
+    NoIMEWindow,
+}
+
+#[allow(dead_code)]
 pub struct IMEControl {
     check_timeout: u32,
     base_status: bool,
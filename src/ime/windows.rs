@@ -1,4 +1,3 @@
-use anyhow::{anyhow, Result};
 use windows::Win32::{
     Foundation::{HWND, LPARAM, WPARAM},
     UI::{
@@ -10,7 +9,7 @@ use windows::Win32::{
     },
 };
 
-use super::{IMEControl, IMEResponse, InputMode};
+use super::{IMEControl, IMEError, IMEResponse, InputMode};
 
 impl InputMode for IMEControl {
     fn new(check_timeout: u32, base_status: bool) -> Self {
@@ -21,18 +20,18 @@ impl InputMode for IMEControl {
         }
     }
 
-    fn get_input_mode(&self) -> Result<IMEResponse> {
+    fn get_input_mode(&self) -> Result<IMEResponse, IMEError> {
         self.get_input_mode(None)
     }
 
-    fn set_input_mode(&self, is_cn: bool) -> Result<()> {
+    fn set_input_mode(&self, is_cn: bool) -> Result<(), IMEError> {
         self.set_input_mode(is_cn, None)
     }
 }
 
 impl IMEControl {
     /// 获取当前输入模式
-    fn get_input_mode(&self, hwnd: Option<HWND>) -> Result<IMEResponse> {
+    fn get_input_mode(&self, hwnd: Option<HWND>) -> Result<IMEResponse, IMEError> {
         let hwnd = Self::ensure_hwnd(hwnd)?;
 
         if self.status_mode == 0
@@ -87,50 +86,32 @@ impl IMEControl {
             }
         }
 
-        // 状态码逻辑
+        // 状态码逻辑，走到这里时前面的判断保证已配置了状态码规则
         let v = self.get_open_status_internal(hwnd)?;
         let flag = (v & 1) != 0;
 
-        if self.base_status {
-            if let Some(even_sm) = self.even_status_mode {
-                return Ok(IMEResponse {
-                    code: v,
-                    is_cn: even_sm ^ flag,
-                });
-            }
-            if self.status_mode != 0 {
-                let contains = format!(":{}:", v).contains(&format!(":{}:", self.status_mode));
-                return Ok(IMEResponse {
-                    code: 0,
-                    is_cn: contains,
-                });
-            }
-        } else {
-            if let Some(even_sm) = self.even_status_mode {
-                return Ok(IMEResponse {
-                    code: v,
-                    is_cn: even_sm && flag,
-                });
-            }
-            if self.status_mode != 0 {
-                let contains = format!(":{}:", v).contains(&format!(":{}:", self.status_mode));
-                return Ok(IMEResponse {
-                    code: 0,
-                    is_cn: !contains,
-                });
-            }
+        if let Some(even_sm) = self.even_status_mode {
+            let is_cn = if self.base_status {
+                even_sm ^ flag
+            } else {
+                even_sm && flag
+            };
+            return Ok(IMEResponse { code: v, is_cn });
         }
-
-        Err(anyhow!("Invalid IME state"))
+        let contains = format!(":{}:", v).contains(&format!(":{}:", self.status_mode));
+        Ok(IMEResponse {
+            code: 0,
+            is_cn: contains == self.base_status,
+        })
     }
 
     /// 设置输入模式
-    fn set_input_mode(&self, is_cn: bool, hwnd: Option<HWND>) -> Result<()> {
+    fn set_input_mode(&self, is_cn: bool, hwnd: Option<HWND>) -> Result<(), IMEError> {
         let hwnd = Self::ensure_hwnd(hwnd)?;
 
         if is_cn {
             self.set_open_status_internal(true, hwnd)?;
-            match Self::get_keyboard_layout(hwnd)? as u32 {
+            match Self::get_keyboard_layout(hwnd) as u32 {
                 0x08040804 => self.set_conversion_mode_internal(1025, hwnd)?,
                 0x04110411 => self.set_conversion_mode_internal(9, hwnd)?,
                 _ => (),
@@ -142,110 +123,92 @@ impl IMEControl {
     }
 
     // 内部实现方法
-    fn get_open_status_internal(&self, hwnd: HWND) -> Result<isize> {
-        let ime_wnd = unsafe { ImmGetDefaultIMEWnd(hwnd) };
+    fn get_open_status_internal(&self, hwnd: HWND) -> Result<isize, IMEError> {
         let mut status = 0;
-
-        unsafe {
-            SendMessageTimeoutW(
-                ime_wnd,
-                WM_IME_CONTROL,
-                WPARAM(0x5),
-                LPARAM(0),
-                SMTO_ABORTIFHUNG,
-                self.check_timeout,
-                Some(&mut status as *mut _ as *mut _),
-            );
-        }
-
+        self.send_ime_control(hwnd, 0x5, 0, Some(&mut status))?;
         Ok(status)
     }
 
-    fn set_open_status_internal(&self, status: bool, hwnd: HWND) -> Result<()> {
-        let ime_wnd = unsafe { ImmGetDefaultIMEWnd(hwnd) };
-        let status = isize::from(status);
-
-        unsafe {
-            SendMessageTimeoutW(
-                ime_wnd,
-                WM_IME_CONTROL,
-                WPARAM(0x6),
-                LPARAM(status),
-                SMTO_ABORTIFHUNG,
-                self.check_timeout,
-                None,
-            );
-        }
-
-        Ok(())
+    fn set_open_status_internal(&self, status: bool, hwnd: HWND) -> Result<(), IMEError> {
+        self.send_ime_control(hwnd, 0x6, isize::from(status), None)
     }
 
-    fn get_conversion_mode_internal(&self, hwnd: HWND) -> Result<isize> {
-        let ime_wnd = unsafe { ImmGetDefaultIMEWnd(hwnd) };
+    fn get_conversion_mode_internal(&self, hwnd: HWND) -> Result<isize, IMEError> {
         let mut mode = 0;
-
-        unsafe {
-            SendMessageTimeoutW(
-                ime_wnd,
-                WM_IME_CONTROL,
-                WPARAM(0x1),
-                LPARAM(0),
-                SMTO_ABORTIFHUNG,
-                self.check_timeout,
-                Some(&mut mode as *mut _ as *mut _),
-            );
-        }
-
+        self.send_ime_control(hwnd, 0x1, 0, Some(&mut mode))?;
         Ok(mode)
     }
 
-    fn set_conversion_mode_internal(&self, mode: isize, hwnd: HWND) -> Result<()> {
+    fn set_conversion_mode_internal(&self, mode: isize, hwnd: HWND) -> Result<(), IMEError> {
+        self.send_ime_control(hwnd, 0x2, mode, None)
+    }
+
+    /// 向输入法窗口发送 WM_IME_CONTROL，超时或目标窗口挂起时返回错误
+    fn send_ime_control(
+        &self,
+        hwnd: HWND,
+        command: usize,
+        param: isize,
+        result: Option<&mut isize>,
+    ) -> Result<(), IMEError> {
         let ime_wnd = unsafe { ImmGetDefaultIMEWnd(hwnd) };
+        if ime_wnd.is_invalid() {
+            return Err(IMEError::NoIMEWindow);
+        }
 
-        unsafe {
+        let mut value = 0usize;
+        let sent = unsafe {
             SendMessageTimeoutW(
                 ime_wnd,
                 WM_IME_CONTROL,
-                WPARAM(0x2),
-                LPARAM(mode),
+                WPARAM(command),
+                LPARAM(param),
                 SMTO_ABORTIFHUNG,
                 self.check_timeout,
-                None,
-            );
+                Some(&mut value as *mut _),
+            )
+        };
+        if sent.0 == 0 {
+            return Err(IMEError::Timeout(self.check_timeout));
         }
 
+        if let Some(result) = result {
+            *result = value as isize;
+        }
         Ok(())
     }
 
-    fn get_keyboard_layout(hwnd: HWND) -> Result<isize> {
+    fn get_keyboard_layout(hwnd: HWND) -> isize {
         let tid = unsafe { GetWindowThreadProcessId(hwnd, None) };
-        Ok(unsafe { GetKeyboardLayout(tid) }.0 as isize)
+        unsafe { GetKeyboardLayout(tid) }.0 as isize
     }
 
-    fn ensure_hwnd(hwnd: Option<HWND>) -> Result<HWND> {
+    fn ensure_hwnd(hwnd: Option<HWND>) -> Result<HWND, IMEError> {
         hwnd.ok_or_else(Self::get_focused_window)
             .or_else(|_| Self::get_focused_window())
     }
 
-    fn get_focused_window() -> Result<HWND> {
+    fn get_focused_window() -> Result<HWND, IMEError> {
         let mut gui_thread_info = GUITHREADINFO {
             cbSize: std::mem::size_of::<GUITHREADINFO>() as u32,
             ..Default::default()
         };
 
         unsafe {
-            if GetGUIThreadInfo(0, &mut gui_thread_info).is_ok() {
+            if GetGUIThreadInfo(0, &mut gui_thread_info).is_ok()
+                && !gui_thread_info.hwndFocus.is_invalid()
+            {
                 return Ok(gui_thread_info.hwndFocus);
             }
         }
 
-        Err(anyhow!("Failed to get focused window"))
+        Err(IMEError::NoFocusedWindow)
     }
 }
 
 // 使用示例
 #[test]
-fn test() -> Result<()> {
+fn test() -> Result<(), IMEError> {
     // 初始化配置
     let ctrl = IMEControl::new(500, true);
 
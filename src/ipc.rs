@@ -0,0 +1,344 @@
+use std::{
+    io::{self, BufRead, Lines, Write},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    action::{Action, ActionQueue},
+    graphics::{MoveFinished, PuzzleKind, TimerExpired},
+};
+
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+use self::unix as platform;
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+use self::windows as platform;
+
+/// 本地控制接口，Unix 上为 `$XDG_RUNTIME_DIR/time-fly.sock`，Windows 上为命名管道 `\\.\pipe\time-fly`
+///
+/// 每行一个 JSON 命令，如 `{"cmd":"move","seq":"R U R'"}`，每个命令回复一行
+/// `{"ok":true}` 或 `{"ok":false,"error":"..."}`；
+/// `{"cmd":"subscribe","events":["move","timer"]}` 之后该连接只用于推送事件
+///
+/// 控制接口同时保证只运行一个实例，见 [`IpcPlugin::bind`]
+pub struct IpcPlugin {
+    listener: Mutex<Option<io::Result<platform::Listener>>>,
+    startup: Vec<Action>,
+}
+
+impl IpcPlugin {
+    /// 绑定控制接口，`startup` 为启动参数指定的操作；
+    /// 已有实例在运行时返回 `None`，此时应改用 [`forward`] 将操作转发给该实例
+    pub fn bind(startup: Vec<Action>) -> Option<Self> {
+        let listener = match platform::bind() {
+            Err(err) if err.kind() == io::ErrorKind::AddrInUse => return None,
+            listener => listener,
+        };
+        Some(Self {
+            listener: Mutex::new(Some(listener)),
+            startup,
+        })
+    }
+}
+
+impl Plugin for IpcPlugin {
+    fn build(&self, app: &mut App) {
+        let actions = app
+            .init_resource::<ActionQueue>()
+            .world()
+            .resource::<ActionQueue>()
+            .sender();
+        let subscribers = Subscribers::default();
+        // 需要在加入 `PuzzleKind` 的 `GraphicsPlugin` 之后构建
+        let puzzle = app
+            .world()
+            .get_resource::<PuzzleKind>()
+            .copied()
+            .unwrap_or_default();
+        for action in &self.startup {
+            match action.validate_for(puzzle) {
+                Ok(()) => {
+                    let _ = actions.send(action.clone());
+                }
+                Err(err) => warn!("ignoring startup action: {err}"),
+            }
+        }
+
+        let server = Server {
+            actions,
+            subscribers: subscribers.clone(),
+            puzzle,
+        };
+        let listener = self
+            .listener
+            .lock()
+            .ok()
+            .and_then(|mut listener| listener.take());
+        if let Some(Err(err)) = listener
+            .map(|listener| listener?.spawn(move |reader, writer| server.serve(reader, writer)))
+        {
+            warn!("failed to start control socket: {err}");
+        }
+
+        app.insert_resource(IpcChannel { subscribers })
+            .add_systems(Update, broadcast_events);
+    }
+}
+
+type Reader = Box<dyn BufRead + Send>;
+type Writer = Box<dyn Write + Send>;
+
+/// 可订阅的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EventKind {
+    /// 一次转动完成
+    Move,
+    /// 倒计时或番茄钟的一个阶段结束
+    Timer,
+}
+
+struct Subscriber {
+    events: Vec<EventKind>,
+    sender: Sender<String>,
+}
+
+#[derive(Clone, Default)]
+struct Subscribers(Arc<Mutex<Vec<Subscriber>>>);
+
+impl Subscribers {
+    /// 推送事件，并移除已断开的订阅者
+    fn send(&self, kind: EventKind, event: Value) {
+        let Ok(mut subscribers) = self.0.lock() else {
+            return;
+        };
+        let line = event.to_string();
+        subscribers.retain(|subscriber| {
+            !subscriber.events.contains(&kind) || subscriber.sender.send(line.clone()).is_ok()
+        });
+    }
+}
+
+#[derive(Resource)]
+struct IpcChannel {
+    subscribers: Subscribers,
+}
+
+enum Request {
+    Action(Action),
+    Subscribe(Vec<EventKind>),
+}
+
+fn parse_request(line: &str, puzzle: PuzzleKind) -> Result<Request, String> {
+    let value: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    if value.get("cmd").and_then(Value::as_str) == Some("subscribe") {
+        let events = match value.get("events") {
+            Some(events) => {
+                serde_json::from_value(events.clone()).map_err(|err| err.to_string())?
+            }
+            None => vec![EventKind::Move, EventKind::Timer],
+        };
+        return Ok(Request::Subscribe(events));
+    }
+
+    let action: Action = serde_json::from_value(value).map_err(|err| err.to_string())?;
+    action.validate_for(puzzle)?;
+    Ok(Request::Action(action))
+}
+
+struct Server {
+    actions: Sender<Action>,
+    subscribers: Subscribers,
+    /// 转动按当前谜题检查
+    puzzle: PuzzleKind,
+}
+
+impl Server {
+    /// 处理一个连接，在该连接的线程中运行
+    fn serve(&self, reader: Reader, mut writer: Writer) {
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (response, subscription) = match parse_request(&line, self.puzzle) {
+                Ok(Request::Action(action)) => match self.actions.send(action) {
+                    Ok(()) => (Ok(()), None),
+                    Err(_) => (Err("application is shutting down".to_string()), None),
+                },
+                Ok(Request::Subscribe(events)) => (Ok(()), Some(events)),
+                Err(err) => (Err(err), None),
+            };
+            let response = match response {
+                Ok(()) => serde_json::json!({ "ok": true }),
+                Err(error) => serde_json::json!({ "ok": false, "error": error }),
+            };
+            if writeln!(writer, "{response}")
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                return;
+            }
+
+            if let Some(events) = subscription {
+                self.stream_events(events, writer);
+                return;
+            }
+        }
+    }
+
+    fn stream_events(&self, events: Vec<EventKind>, mut writer: Writer) {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.0.lock() {
+            subscribers.push(Subscriber { events, sender });
+        }
+        for line in receiver {
+            if writeln!(writer, "{line}")
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+fn broadcast_events(
+    channel: Res<IpcChannel>,
+    puzzle: Res<PuzzleKind>,
+    mut moves: EventReader<MoveFinished>,
+    mut timers: EventReader<TimerExpired>,
+) {
+    for MoveFinished(m, _) in moves.read() {
+        let event = serde_json::json!({ "event": "move", "move": puzzle.puzzle().format_move(*m) });
+        channel.subscribers.send(EventKind::Move, event);
+    }
+    for _ in timers.read() {
+        let event = serde_json::json!({ "event": "timer" });
+        channel.subscribers.send(EventKind::Timer, event);
+    }
+}
+
+const CTL_USAGE: &str = "usage: time-fly ctl <move|mode|text|theme|status|show|hide|toggle_visibility|toggle_click_through|pause|resume|open_config|quit|subscribe|raw> [args]";
+
+/// `time-fly ctl` 子命令：将参数转换为命令发送给正在运行的实例并输出回复
+///
+/// `ctl move "R U R'"`、`ctl mode countdown:25`、`ctl text 部署中`、`ctl text`（恢复时间）、
+/// `ctl theme neon`、`ctl status <标识> [文字] [--priority 5] [--ttl 600]`（无文字时移除）、
+/// `ctl show`、`ctl hide`、`ctl toggle_visibility`、`ctl toggle_click_through`、`ctl pause`、
+/// `ctl resume`、`ctl open_config`、`ctl quit`、`ctl subscribe [move] [timer]`、`ctl raw '<JSON>'`
+pub fn ctl(args: &[String]) -> anyhow::Result<()> {
+    let value = |i: usize| args.get(i).map(String::as_str);
+    let command = match (value(0), value(1)) {
+        (Some("status"), Some(id)) => status_command(id, &args[2..])?,
+        (Some("subscribe"), _) if args.len() > 1 => {
+            serde_json::json!({ "cmd": "subscribe", "events": args[1..] })
+        }
+        (Some("subscribe"), _) => serde_json::json!({ "cmd": "subscribe" }),
+        (Some("raw"), Some(json)) => serde_json::from_str(json)?,
+        (Some(_), _) => {
+            let action: Action = args
+                .join(" ")
+                .parse()
+                .map_err(|err| anyhow::anyhow!("{err}\n{CTL_USAGE}"))?;
+            serde_json::to_value(action)?
+        }
+        _ => anyhow::bail!(CTL_USAGE),
+    };
+    let subscribe = command["cmd"] == "subscribe";
+
+    let (reader, mut writer) = platform::connect()?;
+    let mut lines = reader.lines();
+    println!("{}", request(&mut lines, &mut writer, &command)?);
+    if subscribe {
+        for line in lines {
+            println!("{}", line?);
+        }
+    }
+    Ok(())
+}
+
+fn status_command(id: &str, args: &[String]) -> anyhow::Result<Value> {
+    let mut command = serde_json::json!({ "cmd": "status", "id": id });
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--priority" | "--ttl" => {
+                let value: i64 = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("missing value for `{arg}`"))?
+                    .parse()?;
+                command[&arg[2..]] = value.into();
+            }
+            text => command["text"] = text.into(),
+        }
+    }
+    Ok(command)
+}
+
+/// 将启动参数指定的操作转发给正在运行的实例
+pub fn forward(actions: &[Action]) -> anyhow::Result<()> {
+    if actions.is_empty() {
+        eprintln!("time-fly is already running");
+        return Ok(());
+    }
+    let (reader, mut writer) = platform::connect()?;
+    let mut lines = reader.lines();
+    for action in actions {
+        request(&mut lines, &mut writer, &serde_json::to_value(action)?)?;
+    }
+    Ok(())
+}
+
+/// 发送一个命令并等待回复
+fn request(
+    lines: &mut Lines<Reader>,
+    writer: &mut Writer,
+    command: &Value,
+) -> anyhow::Result<Value> {
+    writeln!(writer, "{command}")?;
+    writer.flush()?;
+    let line = lines
+        .next()
+        .transpose()?
+        .ok_or_else(|| anyhow::anyhow!("connection closed"))?;
+    let response: Value = serde_json::from_str(&line)?;
+    if response["ok"] != true {
+        anyhow::bail!("{}", response["error"].as_str().unwrap_or("request failed"));
+    }
+    Ok(response)
+}
+
+#[test]
+fn parse_requests() {
+    let cube = PuzzleKind::default();
+    assert!(matches!(
+        parse_request(r#"{"cmd":"move","seq":"R U R'"}"#, cube),
+        Ok(Request::Action(Action::Move { .. }))
+    ));
+    assert!(matches!(
+        parse_request(r#"{"cmd":"text","value":null}"#, cube),
+        Ok(Request::Action(Action::Text { value: None }))
+    ));
+    assert!(matches!(
+        parse_request(r#"{"cmd":"subscribe","events":["timer"]}"#, cube),
+        Ok(Request::Subscribe(events)) if events == [EventKind::Timer]
+    ));
+    assert!(parse_request(r#"{"cmd":"move","seq":"X"}"#, cube).is_err());
+    assert!(parse_request(r#"{"cmd":"move","seq":"4R"}"#, cube).is_err());
+    assert!(parse_request(r#"{"cmd":"move","seq":"F"}"#, PuzzleKind::Skewb).is_err());
+    assert!(parse_request(r#"{"cmd":"mode","name":"nap"}"#, cube).is_err());
+}
@@ -0,0 +1,81 @@
+use std::{
+    fs::{File, TryLockError},
+    io::{self, BufReader, ErrorKind},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+};
+
+use super::{Reader, Writer};
+
+fn socket_path() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("time-fly.sock"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_default();
+            std::env::temp_dir().join(format!("time-fly-{user}.sock"))
+        }
+    }
+}
+
+pub struct Listener {
+    listener: UnixListener,
+    /// 套接字旁的锁文件，进程退出时才释放
+    _lock: File,
+}
+
+/// 绑定套接字，已有实例在监听时返回 [`ErrorKind::AddrInUse`]
+///
+/// 先独占锁文件再检查遗留的套接字，同时启动的两个实例不会都把对方的套接字当作遗留文件删除
+pub fn bind() -> io::Result<Listener> {
+    let path = socket_path();
+    let lock = File::create(path.with_extension("lock"))?;
+    match lock.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Err(ErrorKind::AddrInUse.into()),
+        Err(TryLockError::Error(err)) => return Err(err),
+    }
+    let listener = match UnixListener::bind(&path) {
+        // 上次退出时留下的套接字文件，连接不上说明已无实例在监听
+        Err(err) if err.kind() == ErrorKind::AddrInUse => {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(err);
+            }
+            std::fs::remove_file(&path)?;
+            UnixListener::bind(&path)?
+        }
+        listener => listener?,
+    };
+    Ok(Listener {
+        listener,
+        _lock: lock,
+    })
+}
+
+impl Listener {
+    /// 在后台线程中接受连接，每个连接一个线程
+    pub fn spawn(self, serve: impl Fn(Reader, Writer) + Send + Sync + 'static) -> io::Result<()> {
+        let serve = std::sync::Arc::new(serve);
+        std::thread::Builder::new()
+            .name("ipc".into())
+            .spawn(move || {
+                for stream in self.listener.incoming().flatten() {
+                    let serve = serve.clone();
+                    let Ok(reader) = stream.try_clone() else {
+                        continue;
+                    };
+                    let _ = std::thread::Builder::new()
+                        .name("ipc-connection".into())
+                        .spawn(move || serve(Box::new(BufReader::new(reader)), Box::new(stream)));
+                }
+            })?;
+        Ok(())
+    }
+}
+
+pub fn connect() -> io::Result<(Reader, Writer)> {
+    let stream = UnixStream::connect(socket_path())?;
+    Ok((
+        Box::new(BufReader::new(stream.try_clone()?)),
+        Box::new(stream),
+    ))
+}
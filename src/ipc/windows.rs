@@ -0,0 +1,97 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, ErrorKind},
+    os::windows::io::{AsRawHandle, FromRawHandle},
+    sync::Arc,
+};
+
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::{ERROR_PIPE_CONNECTED, HANDLE},
+        Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+        System::Pipes::{
+            ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE,
+            PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+        },
+    },
+};
+
+use super::{Reader, Writer};
+
+const PIPE_NAME: &str = r"\\.\pipe\time-fly";
+const BUFFER_SIZE: u32 = 4096;
+
+/// 创建一个管道实例
+fn create_pipe(first: bool) -> io::Result<File> {
+    let mut mode = PIPE_ACCESS_DUPLEX;
+    if first {
+        mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let handle = unsafe {
+        CreateNamedPipeW(
+            &HSTRING::from(PIPE_NAME),
+            mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            None,
+        )
+    };
+    if handle.is_invalid() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_handle(handle.0) })
+}
+
+pub struct Listener(File);
+
+/// 创建第一个管道实例，已有实例在监听时返回 [`ErrorKind::AddrInUse`]
+pub fn bind() -> io::Result<Listener> {
+    match create_pipe(true) {
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            Err(io::Error::new(ErrorKind::AddrInUse, err))
+        }
+        pipe => pipe.map(Listener),
+    }
+}
+
+impl Listener {
+    /// 在后台线程中接受连接，每个连接一个线程
+    ///
+    /// 同步管道上的读写会互相阻塞，所以每个连接在同一线程中依次读写
+    pub fn spawn(self, serve: impl Fn(Reader, Writer) + Send + Sync + 'static) -> io::Result<()> {
+        let mut pipe = self.0;
+        let serve = Arc::new(serve);
+        std::thread::Builder::new()
+            .name("ipc".into())
+            .spawn(move || loop {
+                let connected = unsafe { ConnectNamedPipe(HANDLE(pipe.as_raw_handle()), None) };
+                if let Err(err) = connected {
+                    if err.code() != ERROR_PIPE_CONNECTED.to_hresult() {
+                        return;
+                    }
+                }
+
+                let Ok(next) = create_pipe(false) else {
+                    return;
+                };
+                let stream = std::mem::replace(&mut pipe, next);
+                let serve = serve.clone();
+                let Ok(reader) = stream.try_clone() else {
+                    continue;
+                };
+                let _ = std::thread::Builder::new()
+                    .name("ipc-connection".into())
+                    .spawn(move || serve(Box::new(BufReader::new(reader)), Box::new(stream)));
+            })?;
+        Ok(())
+    }
+}
+
+pub fn connect() -> io::Result<(Reader, Writer)> {
+    let pipe = OpenOptions::new().read(true).write(true).open(PIPE_NAME)?;
+    Ok((Box::new(BufReader::new(pipe.try_clone()?)), Box::new(pipe)))
+}
@@ -0,0 +1,351 @@
+use std::{
+    ffi::c_void,
+    ptr::NonNull,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::PluginsState,
+    prelude::*,
+    render::camera::RenderTarget,
+    window::{
+        CompositeAlphaMode, Monitor, PrimaryMonitor, PrimaryWindow, RawHandleWrapper, WindowRef,
+        WindowWrapper,
+    },
+    winit::{UpdateMode, WinitSettings},
+};
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle,
+    RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle, WindowHandle,
+};
+use wayland_client::{
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{
+        wl_compositor::WlCompositor,
+        wl_output::{self, WlOutput},
+        wl_region::WlRegion,
+        wl_registry::WlRegistry,
+        wl_surface::WlSurface,
+    },
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+};
+
+/// 用 wlr-layer-shell 的覆盖层代替 winit 窗口，需启用 `layer-shell` 特性
+///
+/// 表面铺满输出、不受面板的保留区域影响，输入区域为空，点击直接穿透；
+/// 渲染器通过 [`RawHandleWrapper`] 直接绘制到该表面，应用由本插件的运行循环驱动，
+/// 等待时间沿用 [`PowerPlugin`](crate::power::PowerPlugin) 设置的 [`WinitSettings`]
+pub struct LayerShellPlugin {
+    shell: Mutex<Option<LayerShell>>,
+}
+
+impl LayerShellPlugin {
+    /// 连接 Wayland 并创建覆盖层；不在 Wayland 下或混成器不支持该协议时返回 `None`，
+    /// 此时应继续使用 winit 窗口
+    pub fn connect() -> Option<Self> {
+        std::env::var_os("WAYLAND_DISPLAY")?;
+        let shell = LayerShell::connect().ok()?;
+        Some(Self {
+            shell: Mutex::new(Some(shell)),
+        })
+    }
+}
+
+impl Plugin for LayerShellPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(shell) = self.shell.lock().ok().and_then(|mut shell| shell.take()) else {
+            return;
+        };
+        let size = shell.physical_size();
+        app.world_mut().spawn((
+            Monitor {
+                name: None,
+                physical_width: size.x,
+                physical_height: size.y,
+                physical_position: IVec2::ZERO,
+                refresh_rate_millihertz: None,
+                scale_factor: shell.state.scale as f64,
+                video_modes: Vec::new(),
+            },
+            PrimaryMonitor,
+        ));
+        app.insert_non_send_resource(shell)
+            .set_runner(run)
+            .add_systems(PreStartup, attach_surface)
+            .add_systems(PreUpdate, dispatch_events)
+            .add_systems(PostUpdate, sync_window);
+    }
+}
+
+struct LayerShell {
+    connection: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    compositor: WlCompositor,
+    surface: WlSurface,
+    _layer_surface: ZwlrLayerSurfaceV1,
+}
+
+#[derive(Default)]
+struct State {
+    /// 混成器要求的表面大小（逻辑像素）
+    size: UVec2,
+    scale: i32,
+    closed: bool,
+}
+
+impl LayerShell {
+    fn connect() -> anyhow::Result<Self> {
+        let connection = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+        let qh = queue.handle();
+        let compositor: WlCompositor = globals.bind(&qh, 4..=6, ())?;
+        let layer_shell: ZwlrLayerShellV1 = globals.bind(&qh, 1..=4, ())?;
+        // 只用于获取缩放比例
+        let _output: Option<WlOutput> = globals.bind(&qh, 2..=4, ()).ok();
+
+        let surface = compositor.create_surface(&qh, ());
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            None,
+            Layer::Overlay,
+            "time-fly".into(),
+            &qh,
+            (),
+        );
+        layer_surface.set_anchor(Anchor::Top | Anchor::Bottom | Anchor::Left | Anchor::Right);
+        layer_surface.set_exclusive_zone(-1);
+        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+        let mut shell = Self {
+            connection,
+            queue,
+            state: State {
+                scale: 1,
+                ..default()
+            },
+            compositor,
+            surface,
+            _layer_surface: layer_surface,
+        };
+        shell.set_click_through(true);
+        shell.surface.commit();
+
+        // 等待第一次 configure 后才能绘制
+        while shell.state.size == UVec2::ZERO {
+            shell.queue.blocking_dispatch(&mut shell.state)?;
+            if shell.state.closed {
+                anyhow::bail!("layer surface closed");
+            }
+        }
+        shell.surface.set_buffer_scale(shell.state.scale);
+        Ok(shell)
+    }
+
+    fn physical_size(&self) -> UVec2 {
+        self.state.size * self.state.scale as u32
+    }
+
+    /// 空输入区域让点击穿透，`None` 则整个表面接收输入
+    fn set_click_through(&self, enabled: bool) {
+        if enabled {
+            let region = self.compositor.create_region(&self.queue.handle(), ());
+            self.surface.set_input_region(Some(&region));
+            region.destroy();
+        } else {
+            self.surface.set_input_region(None);
+        }
+    }
+}
+
+/// 交给渲染器的表面句柄，持有连接与表面使其在渲染期间保持有效
+struct SurfaceHandles {
+    connection: Connection,
+    surface: WlSurface,
+}
+
+impl HasWindowHandle for SurfaceHandles {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        let surface = NonNull::new(self.surface.id().as_ptr().cast::<c_void>())
+            .ok_or(HandleError::Unavailable)?;
+        let raw = RawWindowHandle::Wayland(WaylandWindowHandle::new(surface));
+        // SAFETY: 表面随 self 一起存活
+        Ok(unsafe { WindowHandle::borrow_raw(raw) })
+    }
+}
+
+impl HasDisplayHandle for SurfaceHandles {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        let display = NonNull::new(self.connection.backend().display_ptr().cast::<c_void>())
+            .ok_or(HandleError::Unavailable)?;
+        let raw = RawDisplayHandle::Wayland(WaylandDisplayHandle::new(display));
+        // SAFETY: 连接随 self 一起存活
+        Ok(unsafe { DisplayHandle::borrow_raw(raw) })
+    }
+}
+
+fn attach_surface(
+    mut commands: Commands,
+    shell: NonSend<LayerShell>,
+    window: Single<(Entity, &mut Window), With<PrimaryWindow>>,
+) {
+    let (entity, mut window) = window.into_inner();
+    let handles = SurfaceHandles {
+        connection: shell.connection.clone(),
+        surface: shell.surface.clone(),
+    };
+    match RawHandleWrapper::new(&WindowWrapper::new(handles)) {
+        Ok(handle) => {
+            commands.entity(entity).insert(handle);
+        }
+        Err(err) => error!("failed to attach layer surface: {err}"),
+    }
+    // 透明背景需要预乘透明度合成
+    window.composite_alpha_mode = CompositeAlphaMode::PreMultiplied;
+}
+
+/// 非阻塞地读取并处理 Wayland 事件，表面大小变化时更新窗口与显示器
+fn dispatch_events(
+    mut shell: NonSendMut<LayerShell>,
+    mut window: Single<&mut Window, With<PrimaryWindow>>,
+    mut monitor: Single<&mut Monitor, With<PrimaryMonitor>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let LayerShell {
+        connection,
+        queue,
+        state,
+        ..
+    } = &mut *shell;
+    if let Some(guard) = queue.prepare_read() {
+        // 没有新事件时为 WouldBlock
+        let _ = guard.read();
+    }
+    if let Err(err) = queue.dispatch_pending(state) {
+        warn!("wayland connection error: {err}");
+    }
+    let _ = connection.flush();
+    if state.closed {
+        exit.send(AppExit::Success);
+        return;
+    }
+
+    let size = shell.physical_size();
+    if window.physical_size() != size {
+        let scale = shell.state.scale as f32;
+        shell.surface.set_buffer_scale(shell.state.scale);
+        window.resolution.set_physical_resolution(size.x, size.y);
+        window.resolution.set_scale_factor_override(Some(scale));
+        monitor.physical_width = size.x;
+        monitor.physical_height = size.y;
+        monitor.scale_factor = scale as f64;
+    }
+}
+
+/// 隐藏时停用绘制到表面的相机，表面只清除为透明；鼠标穿透对应输入区域
+fn sync_window(
+    shell: NonSend<LayerShell>,
+    window: Single<Ref<Window>, With<PrimaryWindow>>,
+    mut cameras: Query<&mut Camera>,
+) {
+    if !window.is_changed() {
+        return;
+    }
+    for mut camera in &mut cameras {
+        if matches!(camera.target, RenderTarget::Window(WindowRef::Primary)) {
+            camera.is_active = window.visible;
+        }
+    }
+    shell.set_click_through(!window.cursor_options.hit_test);
+}
+
+/// 与 `ScheduleRunnerPlugin` 相同的循环，每帧之间按 [`WinitSettings`] 的等待时间休眠
+fn run(mut app: App) -> AppExit {
+    if app.plugins_state() != PluginsState::Cleaned {
+        while app.plugins_state() == PluginsState::Adding {
+            bevy::tasks::tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+    }
+
+    loop {
+        let start = Instant::now();
+        app.update();
+        if let Some(exit) = app.should_exit() {
+            return exit;
+        }
+
+        let wait = app
+            .world()
+            .get_resource::<WinitSettings>()
+            .map_or(Duration::ZERO, |settings| match settings.focused_mode {
+                UpdateMode::Reactive { wait, .. } => wait,
+                UpdateMode::Continuous => Duration::ZERO,
+            });
+        if let Some(delay) = wait.checked_sub(start.elapsed()) {
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure {
+                serial,
+                width,
+                height,
+            } => {
+                layer_surface.ack_configure(serial);
+                state.size = UVec2::new(width, height);
+            }
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlOutput, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &WlOutput,
+        event: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Scale { factor } = event {
+            state.scale = factor.max(1);
+        }
+    }
+}
+
+delegate_noop!(State: WlCompositor);
+delegate_noop!(State: WlRegion);
+delegate_noop!(State: ZwlrLayerShellV1);
+delegate_noop!(State: ignore WlSurface);
@@ -1,45 +1,156 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::path::PathBuf;
+
+use action::ActionPlugin;
+use anyhow::{bail, Context};
 use bevy::{
     prelude::*,
     window::{CursorOptions, PresentMode, WindowLevel},
 };
-use bevy_prng::WyRand;
-use bevy_rand::plugin::EntropyPlugin;
 use font::FontPlugin;
-use graphics::GraphicsPlugin;
+use graphics::{render_snapshot, CubeState, GraphicsPlugin, MoveLog, Order, PuzzleKind, Themes};
+use hotkey::HotkeyPlugin;
+use ipc::IpcPlugin;
+use power::PowerPlugin;
+use tray::TrayPlugin;
 
 #[cfg(target_os = "macos")]
 use bevy::window::CompositeAlphaMode;
 
+mod action;
+#[cfg(target_os = "linux")]
+mod dbus;
 mod font;
 mod graphics;
+mod hotkey;
 mod ime;
+mod ipc;
+#[cfg(all(target_os = "linux", feature = "layer-shell"))]
+mod layer_shell;
+mod power;
+mod text;
+mod tray;
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("snapshot") => return snapshot(&args[1..]),
+        Some("ctl") => return ipc::ctl(&args[1..]),
+        _ => {}
+    }
+
+    let actions = action::parse_args(&args).map_err(anyhow::Error::msg)?;
+    let Some(ipc) = IpcPlugin::bind(actions.clone()) else {
+        return ipc::forward(&actions);
+    };
+
+    let plugins = DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            transparent: true,
+            decorations: false,
+            cursor_options: CursorOptions {
+                hit_test: false,
+                ..default()
+            },
+            present_mode: PresentMode::AutoNoVsync,
+            window_level: WindowLevel::AlwaysOnTop,
+            skip_taskbar: true,
+            #[cfg(target_os = "macos")]
+            composite_alpha_mode: CompositeAlphaMode::PostMultiplied,
+            ..default()
+        }),
+        ..default()
+    });
+    // 混成器支持 wlr-layer-shell 时用覆盖层代替 winit 窗口，覆盖层总是铺满输出，紧凑窗口不使用
+    #[cfg(all(target_os = "linux", feature = "layer-shell"))]
+    let layer_shell = (!graphics::Placement::from_env().compact)
+        .then(layer_shell::LayerShellPlugin::connect)
+        .flatten();
+    #[cfg(all(target_os = "linux", feature = "layer-shell"))]
+    let plugins = match layer_shell {
+        Some(layer_shell) => plugins
+            .disable::<bevy::winit::WinitPlugin>()
+            .add(layer_shell),
+        None => plugins,
+    };
 
-fn main() {
     App::new()
+        .add_plugins((plugins, FontPlugin::from_env()))
         .add_plugins((
-            DefaultPlugins.set(WindowPlugin {
-                primary_window: Some(Window {
-                    transparent: true,
-                    decorations: false,
-                    cursor_options: CursorOptions {
-                        hit_test: false,
-                        ..default()
-                    },
-                    present_mode: PresentMode::AutoNoVsync,
-                    window_level: WindowLevel::AlwaysOnTop,
-                    skip_taskbar: true,
-                    #[cfg(target_os = "macos")]
-                    composite_alpha_mode: CompositeAlphaMode::PostMultiplied,
-                    ..default()
-                }),
-                ..default()
-            }),
-            FontPlugin,
+            ActionPlugin,
+            GraphicsPlugin,
+            PowerPlugin,
+            TrayPlugin,
+            HotkeyPlugin,
+            ipc,
         ))
-        .add_plugins(EntropyPlugin::<WyRand>::default())
-        .add_plugins(GraphicsPlugin)
-        .insert_resource(Time::<Fixed>::from_hz(60.0))
         .run();
+    Ok(())
+}
+
+/// 不创建窗口，将魔方渲染为图片
+///
+/// `time-fly snapshot <输出.png> [--moves "R U2 F'"] [--replay moves.log] [--text 12:34] [--theme glass] [--size 512] [--order 3] [--puzzle cube]`
+///
+/// `--replay` 先重放转动记录，再执行 `--moves`；重放时谜题与阶数取自记录，`--puzzle`、`--order` 不生效
+fn snapshot(args: &[String]) -> anyhow::Result<()> {
+    let mut output = None;
+    let mut moves = String::new();
+    let mut replay = None;
+    let mut text = String::from("12:34");
+    let mut theme = String::from("glass");
+    let mut size = 512;
+    let mut order = Order::default();
+    let mut puzzle = String::from("cube");
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--moves" | "--replay" | "--text" | "--theme" | "--size" | "--order" | "--puzzle" => {
+                let value = args
+                    .next()
+                    .with_context(|| format!("missing value for `{arg}`"))?
+                    .clone();
+                match arg.as_str() {
+                    "--moves" => moves = value,
+                    "--replay" => replay = Some(PathBuf::from(value)),
+                    "--text" => text = value,
+                    "--theme" => theme = value,
+                    "--puzzle" => puzzle = value,
+                    "--order" => {
+                        order = value
+                            .parse()
+                            .ok()
+                            .and_then(Order::new)
+                            .context("invalid `--order`")?
+                    }
+                    _ => size = value.parse().context("invalid `--size`")?,
+                }
+            }
+            _ if output.is_none() => output = Some(PathBuf::from(arg)),
+            _ => bail!("unexpected argument `{arg}`"),
+        }
+    }
+    let output = output.context("missing output path")?;
+
+    let themes = Themes::load();
+    let theme = themes
+        .get(&theme)
+        .with_context(|| format!("theme `{theme}` not found"))?;
+    let mut state = match replay {
+        Some(path) => MoveLog::load(&path)
+            .with_context(|| format!("failed to load {path:?}"))?
+            .replay(),
+        None => CubeState::solved_with(
+            PuzzleKind::from_id(&puzzle, order)
+                .with_context(|| format!("unknown puzzle `{puzzle}`"))?,
+        ),
+    };
+    state.apply_sequence(&moves)?;
+
+    render_snapshot(&state, &text, theme, size)
+        .save(&output)
+        .with_context(|| format!("failed to write {output:?}"))?;
+    Ok(())
 }
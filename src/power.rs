@@ -0,0 +1,173 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    window::WindowOccluded,
+    winit::{UpdateMode, WinitSettings},
+};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use self::linux as platform;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    pub struct ScreenWatcher;
+
+    impl ScreenWatcher {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn inactive(&mut self) -> bool {
+            false
+        }
+    }
+}
+
+/// 动画默认的帧率上限
+const DEFAULT_FPS: u32 = 30;
+/// 没有动画时的唤醒间隔，用于更新时间等
+const IDLE_WAIT: Duration = Duration::from_secs(1);
+/// 暂停时检查是否恢复的间隔
+const PAUSED_WAIT: Duration = Duration::from_secs(2);
+/// 检查锁屏与全屏应用的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+/// 虚拟时间单帧的最大步长，需大于唤醒间隔，否则空闲时按虚拟时间计时的系统会变慢
+const MAX_DELTA: Duration = Duration::from_secs(5);
+
+/// 按需重绘
+///
+/// 有动画时以 `TIME_FLY_FPS`（默认 30）为上限刷新，空闲时每秒唤醒一次，
+/// 锁屏、前台为全屏应用或窗口被遮挡时暂停所有动画，见 [`ScreenInactive`]；
+/// `TIME_FLY_DIAGNOSTICS=1` 定期输出帧率，便于对比功耗
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        let fps = match std::env::var("TIME_FLY_FPS") {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|fps| *fps > 0)
+                .unwrap_or_else(|| {
+                    warn!("invalid TIME_FLY_FPS `{value}`, using {DEFAULT_FPS}");
+                    DEFAULT_FPS
+                }),
+            Err(_) => DEFAULT_FPS,
+        };
+
+        let inactive = Arc::new(AtomicBool::new(false));
+        let watcher = inactive.clone();
+        let spawned = std::thread::Builder::new()
+            .name("screen-watcher".into())
+            .spawn(move || {
+                let mut screen = platform::ScreenWatcher::new();
+                loop {
+                    watcher.store(screen.inactive(), Ordering::Relaxed);
+                    std::thread::sleep(WATCH_INTERVAL);
+                }
+            });
+        if let Err(err) = spawned {
+            warn!("failed to watch screen state: {err}");
+        }
+
+        app.insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive_low_power(IDLE_WAIT),
+            unfocused_mode: UpdateMode::reactive_low_power(IDLE_WAIT),
+        })
+        .insert_resource(PowerState {
+            frame: Duration::from_secs_f64(1. / fps as f64),
+            inactive,
+        })
+        .init_resource::<Animating>()
+        .init_resource::<ScreenInactive>()
+        .add_systems(Startup, |mut time: ResMut<Time<Virtual>>| {
+            time.set_max_delta(MAX_DELTA)
+        })
+        .add_systems(First, |mut animating: ResMut<Animating>| {
+            animating.0 = false
+        })
+        .add_systems(First, update_inactive)
+        .add_systems(Last, update_mode);
+
+        if std::env::var_os("TIME_FLY_DIAGNOSTICS").is_some() {
+            app.add_plugins((FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin::default()));
+        }
+    }
+}
+
+/// 本帧是否有动画在进行，由各动画系统在 `Update` 中置位
+#[derive(Resource, Default)]
+pub struct Animating(pub bool);
+
+/// 锁屏、前台为全屏应用或窗口被遮挡，此时自动转动等动画应当停止，不再置位 [`Animating`]
+#[derive(Resource, Default)]
+pub struct ScreenInactive(pub bool);
+
+#[derive(Resource)]
+struct PowerState {
+    /// 动画时的帧间隔
+    frame: Duration,
+    /// 锁屏或前台为全屏应用
+    inactive: Arc<AtomicBool>,
+}
+
+fn update_inactive(
+    power: Res<PowerState>,
+    mut occlusion: EventReader<WindowOccluded>,
+    mut occluded: Local<bool>,
+    mut screen: ResMut<ScreenInactive>,
+) {
+    if let Some(event) = occlusion.read().last() {
+        *occluded = event.occluded;
+    }
+    let inactive = *occluded || power.inactive.load(Ordering::Relaxed);
+    if screen.0 != inactive {
+        screen.0 = inactive;
+    }
+}
+
+fn update_mode(
+    animating: Res<Animating>,
+    screen: Res<ScreenInactive>,
+    power: Res<PowerState>,
+    mut settings: ResMut<WinitSettings>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let inactive = screen.0;
+    if inactive != time.is_paused() {
+        if inactive {
+            info!("screen inactive, pausing");
+            time.pause();
+        } else {
+            info!("screen active, resuming");
+            time.unpause();
+        }
+    }
+
+    let wait = if inactive {
+        PAUSED_WAIT
+    } else if animating.0 {
+        power.frame
+    } else {
+        IDLE_WAIT
+    };
+    let mode = UpdateMode::reactive_low_power(wait);
+    if settings.focused_mode != mode {
+        settings.focused_mode = mode;
+        settings.unfocused_mode = mode;
+    }
+}
@@ -0,0 +1,166 @@
+use bevy::log::warn;
+use x11rb::{
+    connection::Connection,
+    errors::{ConnectionError, ReplyError},
+    protocol::xproto::{Atom, AtomEnum, ConnectionExt, GetPropertyReply, Window},
+    rust_connection::RustConnection,
+};
+
+use crate::dbus::{self, DbusError, Message, Value};
+
+const LOGIND: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const MANAGER: &str = "org.freedesktop.login1.Manager";
+const SESSION: &str = "org.freedesktop.login1.Session";
+
+/// 锁屏状态经系统总线读取 logind 的 `LockedHint`，全屏应用通过 X11 的 `_NET_WM_STATE` 判断；
+/// Wayland 下没有通用的方法查询其他窗口，只检测锁屏
+pub struct ScreenWatcher {
+    logind: Option<Logind>,
+    x11: Option<X11>,
+}
+
+/// 系统总线连接与当前会话的对象路径
+struct Logind {
+    connection: dbus::Connection,
+    session: String,
+}
+
+struct X11 {
+    connection: RustConnection,
+    root: Window,
+    active_window: Atom,
+    wm_state: Atom,
+    fullscreen: Atom,
+}
+
+impl ScreenWatcher {
+    pub fn new() -> Self {
+        Self {
+            logind: Logind::connect()
+                .inspect_err(|err| warn!("failed to query logind: {err}"))
+                .ok(),
+            x11: X11::connect(),
+        }
+    }
+
+    pub fn inactive(&mut self) -> bool {
+        if let Some(logind) = &mut self.logind {
+            match logind.locked() {
+                Ok(true) => return true,
+                Ok(false) => {}
+                Err(err) => {
+                    warn!("failed to read LockedHint: {err}");
+                    self.logind = None;
+                }
+            }
+        }
+        match &self.x11 {
+            Some(x11) => match x11.fullscreen_active() {
+                Ok(fullscreen) => fullscreen,
+                Err(_) => {
+                    // 连接断开后不再尝试
+                    self.x11 = None;
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+}
+
+impl Logind {
+    /// 按 `XDG_SESSION_ID` 查找会话，未设置时按进程查找
+    fn connect() -> Result<Self, DbusError> {
+        let mut connection = dbus::Connection::system()?;
+        let call = match std::env::var("XDG_SESSION_ID") {
+            Ok(id) => Message::method_call(LOGIND, LOGIND_PATH, MANAGER, "GetSession")
+                .with_body(vec![Value::str(id)]),
+            Err(_) => Message::method_call(LOGIND, LOGIND_PATH, MANAGER, "GetSessionByPID")
+                .with_body(vec![Value::UInt32(std::process::id())]),
+        };
+        let session = connection
+            .call(call)?
+            .first()
+            .and_then(Value::as_str)
+            .ok_or(DbusError::Malformed("missing session path"))?
+            .to_string();
+        Ok(Self {
+            connection,
+            session,
+        })
+    }
+
+    fn locked(&mut self) -> Result<bool, DbusError> {
+        let reply = self.connection.call(
+            Message::method_call(
+                LOGIND,
+                &self.session,
+                "org.freedesktop.DBus.Properties",
+                "Get",
+            )
+            .with_body(vec![Value::str(SESSION), Value::str("LockedHint")]),
+        )?;
+        match reply.first() {
+            Some(Value::Variant(value)) => Ok(**value == Value::Bool(true)),
+            _ => Err(DbusError::Malformed("LockedHint is not a variant")),
+        }
+    }
+}
+
+impl X11 {
+    fn connect() -> Option<Self> {
+        std::env::var_os("DISPLAY")?;
+        let (connection, screen) = x11rb::connect(None).ok()?;
+        let root = connection.setup().roots.get(screen)?.root;
+        let atom = |name: &[u8]| -> Option<Atom> {
+            Some(connection.intern_atom(false, name).ok()?.reply().ok()?.atom)
+        };
+        Some(Self {
+            active_window: atom(b"_NET_ACTIVE_WINDOW")?,
+            wm_state: atom(b"_NET_WM_STATE")?,
+            fullscreen: atom(b"_NET_WM_STATE_FULLSCREEN")?,
+            connection,
+            root,
+        })
+    }
+
+    /// 只有连接断开时返回错误
+    fn fullscreen_active(&self) -> Result<bool, ConnectionError> {
+        let Some(active) = self.property(self.root, self.active_window, AtomEnum::WINDOW, 1)?
+        else {
+            return Ok(false);
+        };
+        let Some(window) = active.value32().and_then(|mut values| values.next()) else {
+            return Ok(false);
+        };
+        if window == 0 {
+            return Ok(false);
+        }
+        let Some(state) = self.property(window, self.wm_state, AtomEnum::ATOM, 32)? else {
+            return Ok(false);
+        };
+        Ok(state
+            .value32()
+            .is_some_and(|mut atoms| atoms.any(|atom| atom == self.fullscreen)))
+    }
+
+    /// 读取窗口属性；活动窗口在两次查询之间关闭等协议错误时返回 `None`，之后照常查询
+    fn property(
+        &self,
+        window: Window,
+        property: Atom,
+        kind: AtomEnum,
+        length: u32,
+    ) -> Result<Option<GetPropertyReply>, ConnectionError> {
+        match self
+            .connection
+            .get_property(false, window, property, kind, 0, length)?
+            .reply()
+        {
+            Ok(reply) => Ok(Some(reply)),
+            Err(ReplyError::X11Error(_)) => Ok(None),
+            Err(ReplyError::ConnectionError(err)) => Err(err),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use windows::Win32::UI::Shell::{
+    SHQueryUserNotificationState, QUNS_BUSY, QUNS_NOT_PRESENT, QUNS_PRESENTATION_MODE,
+    QUNS_RUNNING_D3D_FULL_SCREEN,
+};
+
+pub struct ScreenWatcher;
+
+impl ScreenWatcher {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 锁屏、屏保、全屏应用或演示模式
+    pub fn inactive(&mut self) -> bool {
+        match unsafe { SHQueryUserNotificationState() } {
+            Ok(state) => matches!(
+                state,
+                QUNS_NOT_PRESENT
+                    | QUNS_BUSY
+                    | QUNS_RUNNING_D3D_FULL_SCREEN
+                    | QUNS_PRESENTATION_MODE
+            ),
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::OnceLock;
+
+/// 界面语言
+///
+/// `TIME_FLY_LANG=zh|en` 指定，未指定时按 `LC_ALL`、`LC_MESSAGES`、`LANG` 判断，都没有设置时为中文
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Zh,
+    En,
+}
+
+impl Language {
+    /// 启动时确定，之后不再改变
+    pub fn current() -> Self {
+        static CURRENT: OnceLock<Language> = OnceLock::new();
+        *CURRENT.get_or_init(|| {
+            let value = ["TIME_FLY_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+                .into_iter()
+                .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()));
+            match value {
+                Some(value) => Self::parse(&value),
+                None => Language::Zh,
+            }
+        })
+    }
+
+    /// `zh_CN.UTF-8` 等中文区域为中文，其余为英文
+    fn parse(value: &str) -> Self {
+        if value.to_ascii_lowercase().starts_with("zh") {
+            Language::Zh
+        } else {
+            Language::En
+        }
+    }
+}
+
+/// 界面上固定的文字
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    Show,
+    PauseRotation,
+    Mode,
+    Clock,
+    Countdown,
+    Pomodoro,
+    OpenConfig,
+    Quit,
+}
+
+impl Label {
+    /// 当前语言的文字
+    pub fn text(self) -> &'static str {
+        self.text_in(Language::current())
+    }
+
+    pub fn text_in(self, language: Language) -> &'static str {
+        match language {
+            Language::Zh => match self {
+                Label::Show => "显示",
+                Label::PauseRotation => "暂停转动",
+                Label::Mode => "模式",
+                Label::Clock => "时钟",
+                Label::Countdown => "倒计时 25 分钟",
+                Label::Pomodoro => "番茄钟",
+                Label::OpenConfig => "打开配置目录",
+                Label::Quit => "退出",
+            },
+            Language::En => match self {
+                Label::Show => "Show",
+                Label::PauseRotation => "Pause rotation",
+                Label::Mode => "Mode",
+                Label::Clock => "Clock",
+                Label::Countdown => "Countdown 25 min",
+                Label::Pomodoro => "Pomodoro",
+                Label::OpenConfig => "Open config folder",
+                Label::Quit => "Quit",
+            },
+        }
+    }
+}
+
+#[test]
+fn labels_in_every_language() {
+    assert_eq!(Language::parse("zh_CN.UTF-8"), Language::Zh);
+    assert_eq!(Language::parse("en_US.UTF-8"), Language::En);
+    assert_eq!(Language::parse("C"), Language::En);
+    for language in [Language::Zh, Language::En] {
+        for label in [
+            Label::Show,
+            Label::PauseRotation,
+            Label::Mode,
+            Label::Clock,
+            Label::Countdown,
+            Label::Pomodoro,
+            Label::OpenConfig,
+            Label::Quit,
+        ] {
+            // 空文字在菜单中表示分隔线
+            assert!(!label.text_in(language).is_empty(), "{label:?}");
+        }
+    }
+}
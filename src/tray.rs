@@ -0,0 +1,204 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    action::{Action, ActionQueue},
+    graphics::{ClockMode, RotationPaused},
+    text::Label,
+};
+
+#[cfg(target_os = "linux")]
+mod sni;
+#[cfg(target_os = "linux")]
+use self::sni as platform;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use std::sync::mpsc::Sender;
+
+    use super::*;
+
+    pub fn spawn(_: Arc<Mutex<TrayState>>, _: Sender<Action>) -> anyhow::Result<Tray> {
+        anyhow::bail!("not supported on this platform")
+    }
+}
+
+/// 托盘图标与菜单：Linux 上为 StatusNotifierItem，Windows 上为通知区域图标
+///
+/// 菜单提供显示/隐藏、暂停转动、切换模式、打开配置目录与退出
+pub struct TrayPlugin;
+
+impl Plugin for TrayPlugin {
+    fn build(&self, app: &mut App) {
+        let actions = app
+            .init_resource::<ActionQueue>()
+            .world()
+            .resource::<ActionQueue>()
+            .sender();
+        let state = Arc::new(Mutex::new(TrayState::default()));
+        match platform::spawn(state, actions) {
+            Ok(tray) => {
+                app.insert_resource(tray)
+                    .add_systems(Update, sync_state)
+                    .add_systems(Last, remove_icon.run_if(on_event::<AppExit>));
+            }
+            Err(err) => warn!("failed to create tray icon: {err}"),
+        }
+    }
+}
+
+/// 菜单显示的状态
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrayState {
+    pub visible: bool,
+    pub paused: bool,
+    pub mode: &'static str,
+    /// 每次变化加一，用于通知菜单更新
+    pub revision: u32,
+}
+
+/// 平台实现创建的托盘
+#[derive(Resource)]
+pub struct Tray {
+    state: Arc<Mutex<TrayState>>,
+    /// 状态变化后通知托盘刷新菜单
+    changed: Box<dyn Fn(&TrayState) + Send + Sync>,
+    /// 退出时移除图标
+    remove: Box<dyn Fn() + Send + Sync>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toggle {
+    None,
+    Check(bool),
+    Radio(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub id: i32,
+    /// 为空时是分隔线
+    pub label: &'static str,
+    pub toggle: Toggle,
+    pub action: Option<Action>,
+    pub children: Vec<MenuItem>,
+}
+
+impl MenuItem {
+    fn new(id: i32, label: &'static str, action: Action) -> Self {
+        Self {
+            id,
+            label,
+            toggle: Toggle::None,
+            action: Some(action),
+            children: Vec::new(),
+        }
+    }
+
+    fn toggle(mut self, toggle: Toggle) -> Self {
+        self.toggle = toggle;
+        self
+    }
+
+    fn separator(id: i32) -> Self {
+        Self {
+            id,
+            label: "",
+            toggle: Toggle::None,
+            action: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn is_separator(&self) -> bool {
+        self.label.is_empty()
+    }
+}
+
+/// 菜单项，id 在各平台实现中用作菜单命令
+pub fn menu(state: &TrayState) -> Vec<MenuItem> {
+    let mode = |id, label: Label, name: &str| {
+        MenuItem::new(id, label.text(), Action::Mode { name: name.into() }).toggle(Toggle::Radio(
+            state.mode == name.split(':').next().unwrap_or_default(),
+        ))
+    };
+    vec![
+        MenuItem::new(
+            1,
+            Label::Show.text(),
+            if state.visible {
+                Action::Hide
+            } else {
+                Action::Show
+            },
+        )
+        .toggle(Toggle::Check(state.visible)),
+        MenuItem::new(
+            2,
+            Label::PauseRotation.text(),
+            if state.paused {
+                Action::Resume
+            } else {
+                Action::Pause
+            },
+        )
+        .toggle(Toggle::Check(state.paused)),
+        MenuItem {
+            id: 3,
+            label: Label::Mode.text(),
+            toggle: Toggle::None,
+            action: None,
+            children: vec![
+                mode(31, Label::Clock, "clock"),
+                mode(32, Label::Countdown, "countdown:25"),
+                mode(33, Label::Pomodoro, "pomodoro"),
+            ],
+        },
+        MenuItem::separator(4),
+        MenuItem::new(5, Label::OpenConfig.text(), Action::OpenConfig),
+        MenuItem::new(6, Label::Quit.text(), Action::Quit),
+    ]
+}
+
+/// 按 id 查找菜单项
+pub fn find_item(items: &[MenuItem], id: i32) -> Option<&MenuItem> {
+    items.iter().find_map(|item| {
+        if item.id == id {
+            Some(item)
+        } else {
+            find_item(&item.children, id)
+        }
+    })
+}
+
+fn sync_state(
+    window: Single<&Window, With<PrimaryWindow>>,
+    paused: Res<RotationPaused>,
+    mode: Res<ClockMode>,
+    tray: Res<Tray>,
+) {
+    let mode = match *mode {
+        ClockMode::Clock => "clock",
+        ClockMode::Countdown { .. } => "countdown",
+        ClockMode::Pomodoro { .. } => "pomodoro",
+    };
+    let Ok(mut state) = tray.state.lock() else {
+        return;
+    };
+    if (state.visible, state.paused, state.mode) == (window.visible, paused.0, mode) {
+        return;
+    }
+    state.visible = window.visible;
+    state.paused = paused.0;
+    state.mode = mode;
+    state.revision += 1;
+    (tray.changed)(&state);
+}
+
+fn remove_icon(tray: Res<Tray>) {
+    (tray.remove)();
+}
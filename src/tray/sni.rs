@@ -0,0 +1,491 @@
+use std::sync::{mpsc::Sender, Arc, Mutex};
+
+use bevy::prelude::*;
+
+use crate::{
+    action::Action,
+    dbus::{Connection, DbusError, Message, MessageKind, Value},
+};
+
+use super::{find_item, menu, MenuItem, Toggle, Tray, TrayState};
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+const BUS: &str = "org.freedesktop.DBus";
+const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+/// 图标边长
+const ICON_SIZE: i32 = 24;
+
+pub fn spawn(state: Arc<Mutex<TrayState>>, actions: Sender<Action>) -> anyhow::Result<Tray> {
+    let connection = Connection::session()?;
+    let sender = connection.sender();
+    let shared = state.clone();
+    std::thread::Builder::new()
+        .name("tray".into())
+        .spawn(move || {
+            if let Err(err) = run(connection, shared, actions) {
+                warn!("tray connection closed: {err}");
+            }
+        })?;
+
+    Ok(Tray {
+        state,
+        changed: Box::new(move |state| {
+            let signal = Message::signal(MENU_PATH, MENU_INTERFACE, "LayoutUpdated")
+                .with_body(vec![Value::UInt32(state.revision), Value::Int32(0)]);
+            if let Err(err) = sender.send(signal) {
+                warn!("failed to update tray menu: {err}");
+            }
+        }),
+        remove: Box::new(|| {}),
+    })
+}
+
+/// 注册到 StatusNotifierWatcher 并处理托盘宿主的请求，连接断开时返回
+fn run(
+    mut connection: Connection,
+    state: Arc<Mutex<TrayState>>,
+    actions: Sender<Action>,
+) -> Result<(), DbusError> {
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    connection.call(
+        Message::method_call(BUS, "/org/freedesktop/DBus", BUS, "RequestName")
+            .with_body(vec![Value::str(&name), Value::UInt32(0)]),
+    )?;
+    // 面板重启后重新注册
+    connection.call(
+        Message::method_call(BUS, "/org/freedesktop/DBus", BUS, "AddMatch").with_body(vec![
+            Value::str(format!(
+                "type='signal',interface='{BUS}',member='NameOwnerChanged',arg0='{WATCHER}'"
+            )),
+        ]),
+    )?;
+    if let Err(err) = connection.call(register(&name)) {
+        warn!("no tray host available yet: {err}");
+    }
+
+    let service = Service { state, actions };
+    let sender = connection.sender();
+    loop {
+        let message = connection.receive()?;
+        match message.kind {
+            MessageKind::Signal if message.is(BUS, "NameOwnerChanged") => {
+                let owner = message.body.get(2).and_then(Value::as_str);
+                if owner.is_some_and(|owner| !owner.is_empty()) {
+                    sender.send(register(&name))?;
+                }
+            }
+            MessageKind::MethodCall => {
+                let reply = service.handle(&message);
+                if message.expects_reply() {
+                    sender.send(reply)?;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn register(name: &str) -> Message {
+    Message::method_call(
+        WATCHER,
+        "/StatusNotifierWatcher",
+        WATCHER,
+        "RegisterStatusNotifierItem",
+    )
+    .with_body(vec![Value::str(name)])
+}
+
+struct Service {
+    state: Arc<Mutex<TrayState>>,
+    actions: Sender<Action>,
+}
+
+impl Service {
+    fn state(&self) -> TrayState {
+        self.state
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_default()
+    }
+
+    fn handle(&self, message: &Message) -> Message {
+        let args = &message.body;
+        let int = |i: usize| args.get(i).and_then(Value::as_i32).unwrap_or_default();
+        let text = |i: usize| args.get(i).and_then(Value::as_str).unwrap_or_default();
+        let path = message.path.as_deref().unwrap_or_default();
+        let interface = message.interface.as_deref().unwrap_or_default();
+        let member = message.member.as_deref().unwrap_or_default();
+
+        let body = match (interface, member) {
+            (PROPERTIES, "Get") => match self
+                .properties(text(0))
+                .into_iter()
+                .find(|(key, _)| *key == text(1))
+            {
+                Some((_, value)) => vec![Value::variant(value)],
+                None => {
+                    return message.error("org.freedesktop.DBus.Error.UnknownProperty", text(1))
+                }
+            },
+            (PROPERTIES, "GetAll") => vec![Value::dict(self.properties(text(0)))],
+            ("org.freedesktop.DBus.Introspectable", "Introspect") => {
+                vec![Value::str(introspection(path))]
+            }
+            ("org.freedesktop.DBus.Peer", "Ping") => Vec::new(),
+            (ITEM_INTERFACE, "Activate") => {
+                let _ = self.actions.send(Action::ToggleVisibility);
+                Vec::new()
+            }
+            (ITEM_INTERFACE, "SecondaryActivate" | "ContextMenu" | "Scroll") => Vec::new(),
+            (MENU_INTERFACE, "GetLayout") => {
+                let state = self.state();
+                let items = menu(&state);
+                let layout = match int(0) {
+                    0 => root_layout(&items, int(1)),
+                    id => match find_item(&items, id) {
+                        Some(item) => item_layout(item, int(1)),
+                        None => {
+                            return message.error(
+                                "org.freedesktop.DBus.Error.InvalidArgs",
+                                "unknown menu item",
+                            )
+                        }
+                    },
+                };
+                vec![Value::UInt32(state.revision), layout]
+            }
+            (MENU_INTERFACE, "GetGroupProperties") => {
+                let items = menu(&self.state());
+                let ids = match args.first() {
+                    Some(Value::Array(_, ids)) => ids.iter().filter_map(Value::as_i32).collect(),
+                    _ => Vec::new(),
+                };
+                let groups = ids
+                    .into_iter()
+                    .filter_map(|id| find_item(&items, id))
+                    .map(|item| {
+                        Value::Struct(vec![
+                            Value::Int32(item.id),
+                            Value::dict(item_properties(item)),
+                        ])
+                    })
+                    .collect();
+                vec![Value::Array("(ia{sv})".into(), groups)]
+            }
+            (MENU_INTERFACE, "GetProperty") => {
+                let items = menu(&self.state());
+                let property = find_item(&items, int(0)).and_then(|item| {
+                    item_properties(item)
+                        .into_iter()
+                        .find(|(key, _)| *key == text(1))
+                });
+                match property {
+                    Some((_, value)) => vec![Value::variant(value)],
+                    None => {
+                        return message.error("org.freedesktop.DBus.Error.InvalidArgs", text(1))
+                    }
+                }
+            }
+            (MENU_INTERFACE, "Event") => {
+                if text(1) == "clicked" {
+                    self.clicked(int(0));
+                }
+                Vec::new()
+            }
+            (MENU_INTERFACE, "EventGroup") => {
+                if let Some(Value::Array(_, events)) = args.first() {
+                    for event in events {
+                        if let Value::Struct(fields) = event {
+                            let id = fields.first().and_then(Value::as_i32);
+                            let kind = fields.get(1).and_then(Value::as_str);
+                            if let (Some(id), Some("clicked")) = (id, kind) {
+                                self.clicked(id);
+                            }
+                        }
+                    }
+                }
+                vec![Value::Array("i".into(), Vec::new())]
+            }
+            (MENU_INTERFACE, "AboutToShow") => vec![Value::Bool(false)],
+            (MENU_INTERFACE, "AboutToShowGroup") => vec![
+                Value::Array("i".into(), Vec::new()),
+                Value::Array("i".into(), Vec::new()),
+            ],
+            _ => {
+                return message.error(
+                    "org.freedesktop.DBus.Error.UnknownMethod",
+                    &format!("unknown method {interface}.{member}"),
+                )
+            }
+        };
+        message.method_return(body)
+    }
+
+    fn clicked(&self, id: i32) {
+        let items = menu(&self.state());
+        if let Some(action) = find_item(&items, id).and_then(|item| item.action.clone()) {
+            let _ = self.actions.send(action);
+        }
+    }
+
+    fn properties(&self, interface: &str) -> Vec<(&'static str, Value)> {
+        match interface {
+            ITEM_INTERFACE => vec![
+                ("Category", Value::str("ApplicationStatus")),
+                ("Id", Value::str("time-fly")),
+                ("Title", Value::str("Time Fly")),
+                ("Status", Value::str("Active")),
+                ("WindowId", Value::Int32(0)),
+                ("IconName", Value::str("")),
+                (
+                    "IconPixmap",
+                    Value::Array("(iiay)".into(), vec![icon_pixmap()]),
+                ),
+                ("OverlayIconName", Value::str("")),
+                ("AttentionIconName", Value::str("")),
+                (
+                    "ToolTip",
+                    Value::Struct(vec![
+                        Value::str(""),
+                        Value::Array("(iiay)".into(), Vec::new()),
+                        Value::str("Time Fly"),
+                        Value::str(""),
+                    ]),
+                ),
+                ("ItemIsMenu", Value::Bool(true)),
+                ("Menu", Value::ObjectPath(MENU_PATH.into())),
+            ],
+            MENU_INTERFACE => vec![
+                ("Version", Value::UInt32(3)),
+                ("TextDirection", Value::str("ltr")),
+                ("Status", Value::str("normal")),
+                ("IconThemePath", Value::Array("s".into(), Vec::new())),
+            ],
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn item_properties(item: &MenuItem) -> Vec<(&'static str, Value)> {
+    if item.is_separator() {
+        return vec![("type", Value::str("separator"))];
+    }
+    let mut properties = vec![
+        ("label", Value::str(item.label)),
+        ("enabled", Value::Bool(true)),
+    ];
+    match item.toggle {
+        Toggle::None => {}
+        Toggle::Check(on) | Toggle::Radio(on) => {
+            let kind = if matches!(item.toggle, Toggle::Check(_)) {
+                "checkmark"
+            } else {
+                "radio"
+            };
+            properties.push(("toggle-type", Value::str(kind)));
+            properties.push(("toggle-state", Value::Int32(on as i32)));
+        }
+    }
+    if !item.children.is_empty() {
+        properties.push(("children-display", Value::str("submenu")));
+    }
+    properties
+}
+
+/// `(ia{sv}av)`，`depth` 为 -1 时包含所有层级
+fn layout(
+    id: i32,
+    properties: Vec<(&'static str, Value)>,
+    children: &[MenuItem],
+    depth: i32,
+) -> Value {
+    let children = if depth == 0 {
+        Vec::new()
+    } else {
+        children
+            .iter()
+            .map(|child| Value::variant(item_layout(child, depth - 1)))
+            .collect()
+    };
+    Value::Struct(vec![
+        Value::Int32(id),
+        Value::dict(properties),
+        Value::Array("v".into(), children),
+    ])
+}
+
+fn item_layout(item: &MenuItem, depth: i32) -> Value {
+    layout(item.id, item_properties(item), &item.children, depth)
+}
+
+fn root_layout(items: &[MenuItem], depth: i32) -> Value {
+    layout(
+        0,
+        vec![("children-display", Value::str("submenu"))],
+        items,
+        depth,
+    )
+}
+
+/// 3×3 的彩色方格，ARGB32，网络字节序
+fn icon_pixmap() -> Value {
+    const COLORS: [[u8; 3]; 9] = [
+        [0xff, 0xff, 0xff],
+        [0xe5, 0x39, 0x35],
+        [0x1e, 0x88, 0xe5],
+        [0x43, 0xa0, 0x47],
+        [0xfd, 0xd8, 0x35],
+        [0xfb, 0x8c, 0x00],
+        [0xe5, 0x39, 0x35],
+        [0xff, 0xff, 0xff],
+        [0x43, 0xa0, 0x47],
+    ];
+    let cell = ICON_SIZE / 3;
+    let mut pixels = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let gap = x % cell == 0 || y % cell == 0;
+            let [r, g, b] = COLORS[(y / cell * 3 + x / cell) as usize];
+            let pixel = if gap { [0, 0, 0, 0] } else { [0xff, r, g, b] };
+            pixels.extend(pixel.map(Value::Byte));
+        }
+    }
+    Value::Struct(vec![
+        Value::Int32(ICON_SIZE),
+        Value::Int32(ICON_SIZE),
+        Value::Array("y".into(), pixels),
+    ])
+}
+
+fn introspection(path: &str) -> String {
+    let interface = match path {
+        ITEM_PATH => ITEM_INTERFACE,
+        MENU_PATH => MENU_INTERFACE,
+        _ => {
+            return "<node><node name=\"StatusNotifierItem\"/><node name=\"MenuBar\"/></node>"
+                .into()
+        }
+    };
+    format!("<node><interface name=\"{interface}\"/><interface name=\"{PROPERTIES}\"/></node>")
+}
+
+#[test]
+fn mock_watcher_registration_and_menu() {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixStream,
+    };
+
+    let (client, mut bus) = UnixStream::pair().unwrap();
+    let (actions, received) = std::sync::mpsc::channel();
+    let state = Arc::new(Mutex::new(TrayState {
+        visible: true,
+        mode: "clock",
+        ..default()
+    }));
+    std::thread::spawn(move || {
+        let connection = Connection::authenticate(client, 1000)?;
+        run(connection, state, actions)
+    });
+
+    // 模拟总线与 StatusNotifierWatcher
+    let mut reader = BufReader::new(bus.try_clone().unwrap());
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).unwrap();
+    assert_eq!(line, b"\0AUTH EXTERNAL 31303030\r\n");
+    bus.write_all(b"OK 0123456789abcdef\r\n").unwrap();
+    line.clear();
+    reader.read_until(b'\n', &mut line).unwrap();
+    assert_eq!(line, b"BEGIN\r\n");
+
+    let mut reply = |expected: &str, body: Vec<Value>| {
+        let call = Message::read(&mut reader).unwrap();
+        assert_eq!(call.member.as_deref(), Some(expected));
+        bus.write_all(&call.method_return(body).encode()).unwrap();
+        call
+    };
+    reply("Hello", vec![Value::str(":1.7")]);
+    reply("RequestName", vec![Value::UInt32(1)]);
+    reply("AddMatch", Vec::new());
+    let register = reply("RegisterStatusNotifierItem", Vec::new());
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    assert_eq!(register.body, [Value::str(&name)]);
+
+    let mut serial = 100;
+    let mut call = |path: &str, interface: &str, member: &str, body: Vec<Value>| {
+        serial += 1;
+        let message = Message {
+            serial,
+            sender: Some(":1.1".into()),
+            ..Message::method_call(&name, path, interface, member).with_body(body)
+        };
+        bus.write_all(&message.encode()).unwrap();
+        let reply = Message::read(&mut reader).unwrap();
+        assert_eq!(reply.kind, MessageKind::MethodReturn, "{member}: {reply:?}");
+        assert_eq!(reply.reply_serial, Some(serial));
+        reply.body
+    };
+
+    let menu_path = call(
+        ITEM_PATH,
+        PROPERTIES,
+        "Get",
+        vec![Value::str(ITEM_INTERFACE), Value::str("Menu")],
+    );
+    assert_eq!(
+        menu_path,
+        [Value::variant(Value::ObjectPath(MENU_PATH.into()))]
+    );
+
+    let layout = call(
+        MENU_PATH,
+        MENU_INTERFACE,
+        "GetLayout",
+        vec![
+            Value::Int32(0),
+            Value::Int32(-1),
+            Value::Array("s".into(), Vec::new()),
+        ],
+    );
+    let Some(Value::Struct(root)) = layout.get(1) else {
+        panic!("invalid layout {layout:?}");
+    };
+    let Some(Value::Array(_, children)) = root.get(2) else {
+        panic!("invalid layout {root:?}");
+    };
+    assert_eq!(children.len(), 6);
+
+    call(
+        MENU_PATH,
+        MENU_INTERFACE,
+        "Event",
+        vec![
+            Value::Int32(6),
+            Value::str("clicked"),
+            Value::variant(Value::Int32(0)),
+            Value::UInt32(0),
+        ],
+    );
+    assert_eq!(received.recv().unwrap(), Action::Quit);
+    call(
+        MENU_PATH,
+        MENU_INTERFACE,
+        "Event",
+        vec![
+            Value::Int32(33),
+            Value::str("clicked"),
+            Value::variant(Value::str("")),
+            Value::UInt32(0),
+        ],
+    );
+    assert_eq!(
+        received.recv().unwrap(),
+        Action::Mode {
+            name: "pomodoro".into()
+        }
+    );
+}
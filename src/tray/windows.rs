@@ -0,0 +1,224 @@
+use std::sync::{
+    mpsc::{self, Sender},
+    Arc, Mutex, OnceLock,
+};
+
+use windows::{
+    core::{w, HSTRING},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Shell::{
+                Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE,
+                NOTIFYICONDATAW,
+            },
+            WindowsAndMessaging::{
+                AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu,
+                DispatchMessageW, GetCursorPos, GetMessageW, LoadIconW, PostMessageW,
+                PostQuitMessage, RegisterClassW, SetForegroundWindow, TrackPopupMenuEx,
+                TranslateMessage, HMENU, IDI_APPLICATION, MF_CHECKED, MF_POPUP, MF_SEPARATOR,
+                MF_STRING, MSG, TPM_NONOTIFY, TPM_RETURNCMD, TPM_RIGHTBUTTON, WINDOW_EX_STYLE,
+                WM_APP, WM_CLOSE, WM_CONTEXTMENU, WM_LBUTTONUP, WM_RBUTTONUP, WNDCLASSW,
+                WS_OVERLAPPED,
+            },
+        },
+    },
+};
+
+use crate::action::Action;
+
+use super::{find_item, menu, MenuItem, Toggle, Tray, TrayState};
+
+/// 通知区域图标的回调消息
+const CALLBACK: u32 = WM_APP + 1;
+const ICON_ID: u32 = 1;
+
+/// 窗口过程只能访问全局状态
+struct Context {
+    state: Arc<Mutex<TrayState>>,
+    actions: Mutex<Sender<Action>>,
+}
+
+static CONTEXT: OnceLock<Context> = OnceLock::new();
+
+pub fn spawn(state: Arc<Mutex<TrayState>>, actions: Sender<Action>) -> anyhow::Result<Tray> {
+    let context = Context {
+        state: state.clone(),
+        actions: Mutex::new(actions),
+    };
+    if CONTEXT.set(context).is_err() {
+        anyhow::bail!("tray icon already created");
+    }
+
+    // 图标属于创建它的线程，菜单与消息循环都在该线程中
+    let (ready, created) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("tray".into())
+        .spawn(move || match create_icon() {
+            Ok(hwnd) => {
+                let _ = ready.send(Ok(hwnd.0 as isize));
+                message_loop();
+            }
+            Err(err) => {
+                let _ = ready.send(Err(err));
+            }
+        })?;
+    let hwnd = created.recv()??;
+
+    Ok(Tray {
+        state,
+        // 菜单在弹出时按当前状态创建，不需要通知
+        changed: Box::new(|_| {}),
+        remove: Box::new(move || unsafe {
+            let _ = PostMessageW(Some(HWND(hwnd as _)), WM_CLOSE, WPARAM(0), LPARAM(0));
+        }),
+    })
+}
+
+fn create_icon() -> windows::core::Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            lpszClassName: w!("TimeFlyTray"),
+            ..Default::default()
+        };
+        RegisterClassW(&class);
+        // 不显示的普通窗口，弹出菜单需要能成为前台窗口
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            w!("TimeFlyTray"),
+            w!("Time Fly"),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        )?;
+
+        let mut data = icon_data(hwnd);
+        data.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP;
+        data.uCallbackMessage = CALLBACK;
+        data.hIcon = LoadIconW(None, IDI_APPLICATION)?;
+        let tip: Vec<u16> = "Time Fly".encode_utf16().collect();
+        data.szTip[..tip.len()].copy_from_slice(&tip);
+        Shell_NotifyIconW(NIM_ADD, &data).ok()?;
+        Ok(hwnd)
+    }
+}
+
+fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    NOTIFYICONDATAW {
+        cbSize: size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: ICON_ID,
+        ..Default::default()
+    }
+}
+
+fn message_loop() {
+    let mut message = MSG::default();
+    unsafe {
+        while GetMessageW(&mut message, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+    }
+}
+
+fn send(action: Action) {
+    if let Some(context) = CONTEXT.get() {
+        if let Ok(actions) = context.actions.lock() {
+            let _ = actions.send(action);
+        }
+    }
+}
+
+fn current_state() -> TrayState {
+    CONTEXT
+        .get()
+        .and_then(|context| context.state.lock().ok().map(|state| state.clone()))
+        .unwrap_or_default()
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        CALLBACK => {
+            match lparam.0 as u32 & 0xffff {
+                WM_LBUTTONUP => send(Action::ToggleVisibility),
+                WM_RBUTTONUP | WM_CONTEXTMENU => show_menu(hwnd),
+                _ => {}
+            }
+            LRESULT(0)
+        }
+        WM_CLOSE => {
+            let _ = Shell_NotifyIconW(NIM_DELETE, &icon_data(hwnd));
+            PostQuitMessage(0);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, message, wparam, lparam),
+    }
+}
+
+fn append_items(target: HMENU, items: &[MenuItem]) -> windows::core::Result<()> {
+    for item in items {
+        unsafe {
+            if item.is_separator() {
+                AppendMenuW(target, MF_SEPARATOR, 0, None)?;
+            } else if !item.children.is_empty() {
+                let submenu = CreatePopupMenu()?;
+                append_items(submenu, &item.children)?;
+                AppendMenuW(
+                    target,
+                    MF_POPUP,
+                    submenu.0 as usize,
+                    &HSTRING::from(item.label),
+                )?;
+            } else {
+                let mut flags = MF_STRING;
+                if let Toggle::Check(true) | Toggle::Radio(true) = item.toggle {
+                    flags |= MF_CHECKED;
+                }
+                AppendMenuW(target, flags, item.id as usize, &HSTRING::from(item.label))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn show_menu(hwnd: HWND) {
+    let items = menu(&current_state());
+    unsafe {
+        let Ok(popup) = CreatePopupMenu() else {
+            return;
+        };
+        let mut cursor = POINT::default();
+        if append_items(popup, &items).is_ok() && GetCursorPos(&mut cursor).is_ok() {
+            // 否则点击菜单外部时菜单不会关闭
+            let _ = SetForegroundWindow(hwnd);
+            let id = TrackPopupMenuEx(
+                popup,
+                (TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTBUTTON).0,
+                cursor.x,
+                cursor.y,
+                hwnd,
+                None,
+            );
+            if let Some(action) = find_item(&items, id.0).and_then(|item| item.action.clone()) {
+                send(action);
+            }
+        }
+        let _ = DestroyMenu(popup);
+    }
+}